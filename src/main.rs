@@ -6,6 +6,7 @@
 #[macro_use]
 extern crate clap;
 extern crate env_logger;
+#[macro_use]
 extern crate log;
 extern crate time;
 
@@ -16,8 +17,13 @@ use clap::{App, AppSettings, ArgMatches, SubCommand};
 use env_logger::Builder;
 use log::{LevelFilter, Record};
 use std::env;
-use std::io::Write;
+use std::fs::File;
+use std::io::{Read, Write};
+use std::path::Path;
+use std::process;
 use t_rex_core::core::grid::Extent;
+use t_rex_core::mvt::tile::Tile;
+use t_rex_core::mvt::tile_decoder::{decode_tile, tile_summary, TileGeoref};
 use t_rex_webserver as webserver;
 
 fn init_logger() {
@@ -99,6 +105,82 @@ fn generate(args: &ArgMatches) {
     );
 }
 
+/// Log error and terminate with exit code 1
+fn exit_with_error(msg: &str) -> ! {
+    error!("{}", msg);
+    process::exit(1)
+}
+
+fn inspect(args: &ArgMatches) {
+    let tilearg = args.value_of("TILE").unwrap();
+    let geojson = args.value_of("geojson").map_or(false, |s| {
+        s.parse::<bool>()
+            .unwrap_or_else(|_| exit_with_error("Error parsing 'geojson' as boolean value"))
+    });
+    let mut data = Vec::new();
+    let mut tile_extent = None;
+    let mut srid = 3857;
+    if Path::new(tilearg).is_file() {
+        let mut f = File::open(tilearg).unwrap_or_else(|err| {
+            exit_with_error(&format!("Error opening tile file - {}", err))
+        });
+        f.read_to_end(&mut data).unwrap_or_else(|err| {
+            exit_with_error(&format!("Error reading tile file - {}", err))
+        });
+    } else {
+        // [tileset/]z/x/y[.pbf]
+        let parts: Vec<&str> = tilearg.trim_right_matches(".pbf").split("/").collect();
+        if parts.len() < 3 || args.value_of("config").is_none() {
+            exit_with_error("Tile file not found or --config missing for z/x/y tile address");
+        }
+        let n = parts.len();
+        let zoom = parts[n - 3]
+            .parse::<u8>()
+            .unwrap_or_else(|_| exit_with_error("Error parsing zoom level"));
+        let xtile = parts[n - 2]
+            .parse::<u32>()
+            .unwrap_or_else(|_| exit_with_error("Error parsing x tile coordinate"));
+        let ytile = parts[n - 1]
+            .parse::<u32>()
+            .unwrap_or_else(|_| exit_with_error("Error parsing y tile coordinate"));
+        let config = webserver::server::config_from_args(&args);
+        let mut service = webserver::server::service_from_args(&config, &args);
+        let tileset = args
+            .value_of("tileset")
+            .map(|s| s.to_string())
+            .or(if n > 3 {
+                Some(parts[n - 4].to_string())
+            } else {
+                None
+            })
+            .unwrap_or_else(|| exit_with_error("Tileset name missing"));
+        service.prepare_feature_queries();
+        data = service
+            .tile_cached(&tileset, xtile, ytile, zoom, true)
+            .unwrap_or_else(|err| exit_with_error(&format!("Error creating tile - {}", err)));
+        srid = service.grid.srid;
+        tile_extent = Some(if srid == 3857 {
+            service.grid.tile_extent_xyz(xtile, ytile, zoom)
+        } else {
+            service.grid.tile_extent(xtile, ytile, zoom)
+        });
+    }
+    let mvt_tile = Tile::read_bytes(&data)
+        .unwrap_or_else(|err| exit_with_error(&format!("Error decoding tile - {}", err)));
+    if geojson {
+        let georef = tile_extent.as_ref().map(|extent| TileGeoref {
+            extent: extent,
+            reverse_y: true,
+            srid: srid,
+        });
+        for layer in decode_tile(&mvt_tile) {
+            println!("{}", layer.to_geojson(georef.as_ref()));
+        }
+    } else {
+        println!("{}", tile_summary(&mvt_tile));
+    }
+}
+
 fn main() {
     init_logger();
 
@@ -109,7 +191,7 @@ fn main() {
         .about("vector tile server specialized on publishing MVT tiles from your own data")
         .subcommand(SubCommand::with_name("serve")
                         .args_from_usage("--dbconn=[SPEC] 'PostGIS connection postgresql://USER@HOST/DBNAME'
-                                              --datasource=[FILE_OR_GDAL_DS] 'GDAL datasource specification'
+                                              --datasource=[FILE_OR_GDAL_DS] 'GDAL datasource specification or GeoPackage, GeoJSON, FlatGeobuf, MBTiles, CSV file'
                                               --qgs=[FILE] 'QGIS project file'
                                               --simplify=[true|false] 'Simplify geometries'
                                               --clip=[true|false] 'Clip geometries'
@@ -121,7 +203,7 @@ fn main() {
                         .about("Start web server and serve MVT vector tiles"))
         .subcommand(SubCommand::with_name("genconfig")
                         .args_from_usage("--dbconn=[SPEC] 'PostGIS connection postgresql://USER@HOST/DBNAME'
-                                              --datasource=[FILE_OR_GDAL_DS] 'GDAL datasource specification'
+                                              --datasource=[FILE_OR_GDAL_DS] 'GDAL datasource specification or GeoPackage, GeoJSON, FlatGeobuf, MBTiles, CSV file'
                                               --qgs=[FILE] 'QGIS project file'
                                               --simplify=[true|false] 'Simplify geometries'
                                               --clip=[true|false] 'Clip geometries'")
@@ -137,7 +219,13 @@ fn main() {
                                               --nodeno=[NUM] 'Number of this nodes (0 <= n < nodes)'
                                               --progress=[true|false] 'Show progress bar'
                                               --overwrite=[false|true] 'Overwrite previously cached tiles'")
-                        .about("Generate tiles for cache"));
+                        .about("Generate tiles for cache"))
+        .subcommand(SubCommand::with_name("inspect")
+                        .args_from_usage("-c, --config=[FILE] 'Load from custom config file'
+                                              --tileset=[NAME] 'Tileset name'
+                                              --geojson=[false|true] 'Output layers as GeoJSON'
+                                              <TILE> 'Tile file, cache path or z/x/y of tileset'")
+                        .about("Decode MVT tile and show its content"));
 
     match app.get_matches_from_safe_borrow(env::args()) {
         //app.get_matches() prohibits later call of app.print_help()
@@ -148,6 +236,7 @@ fn main() {
             ("serve", Some(sub_m)) => webserver::server::webserver(sub_m.clone()),
             ("genconfig", Some(sub_m)) => println!("{}", webserver::server::gen_config(sub_m)),
             ("generate", Some(sub_m)) => generate(sub_m),
+            ("inspect", Some(sub_m)) => inspect(sub_m),
             _ => {
                 let _ = app.print_help();
                 println!("");
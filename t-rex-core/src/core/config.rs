@@ -6,6 +6,8 @@
 use core::grid::Extent;
 use serde::Deserialize;
 use std;
+use std::collections::HashMap;
+use std::env;
 use std::fs::File;
 use std::io::prelude::*;
 use toml::Value;
@@ -51,9 +53,35 @@ pub struct DatasourceCfg {
     pub default: Option<bool>,
     // Postgis
     pub dbconn: Option<String>,
+    /// Maximal number of pool connections (Default: 10)
     pub pool: Option<u16>,
+    /// Seconds to wait for a pool connection (Default: 30)
+    pub connection_timeout: Option<u64>,
+    /// Seconds until idle pool connections are closed (Default: 600)
+    pub idle_timeout: Option<u64>,
+    /// Seconds until queries are cancelled (Default: no limit)
+    pub statement_timeout: Option<u64>,
+    /// TLS mode (disable, prefer, require)
+    pub tls_mode: Option<String>,
     // GDAL
     pub path: Option<String>,
+    // GeoPackage
+    pub gpkg: Option<String>,
+    // GeoJSON / FlatGeobuf
+    pub geojson: Option<String>,
+    pub fgb: Option<String>,
+    // Vector tiles
+    pub mbtiles: Option<String>,
+    pub tiles: Option<String>,
+    pub maxzoom: Option<u8>,
+    // CSV
+    pub csv: Option<String>,
+    pub delimiter: Option<String>,
+    pub x_field: Option<String>,
+    pub y_field: Option<String>,
+    pub wkt_field: Option<String>,
+    pub srid: Option<i32>,
+    pub types: Option<HashMap<String, String>>,
 }
 
 #[derive(Deserialize, Debug)]
@@ -94,10 +122,14 @@ pub struct TilesetCfg {
     pub name: String,
     pub extent: Option<Extent>,
     pub minzoom: Option<u8>,
+    /// Highest generated zoom level. Tiles above are derived from parent tiles.
     pub maxzoom: Option<u8>,
     pub center: Option<(f64, f64)>,
     pub start_zoom: Option<u8>,
     pub attribution: Option<String>,
+    /// Tile width and height in pixels, grid tile size times a power of two, e.g. 512
+    /// (Default: grid tile size). Zoom levels of layers refer to grid zoom levels.
+    pub tile_size: Option<u32>,
     #[serde(rename = "layer")]
     pub layers: Vec<LayerCfg>,
     // Inline style
@@ -109,6 +141,79 @@ pub struct LayerQueryCfg {
     pub minzoom: Option<u8>,
     pub maxzoom: Option<u8>,
     pub sql: Option<String>,
+    /// Attributes included in tiles (Default: all)
+    pub fields: Option<Vec<String>>,
+    /// Attributes excluded from tiles
+    #[serde(default)]
+    pub exclude_fields: Vec<String>,
+    /// Attribute renaming (e.g. `rename = { name_de = "name" }`)
+    #[serde(default)]
+    pub rename: HashMap<String, String>,
+    /// Override of layer `buffer_size`
+    pub buffer_size: Option<u32>,
+    /// Override of layer `tile_size`
+    pub tile_size: Option<u32>,
+    /// Override of layer `simplify`
+    pub simplify: Option<bool>,
+    /// Override of layer `tolerance`
+    pub tolerance: Option<f64>,
+    /// Override of layer `query_limit`
+    pub query_limit: Option<u32>,
+    /// Override of layer `min_area`
+    pub min_area: Option<f64>,
+    /// Override of layer `min_length`
+    pub min_length: Option<f64>,
+}
+
+#[derive(Deserialize, Debug)]
+pub struct LayerClusterCfg {
+    /// Cluster radius in pixels (Default: 40)
+    pub radius: Option<u32>,
+    /// Maximal zoom level with clustering (Default: 22)
+    pub maxzoom: Option<u8>,
+    /// Attributes aggregated over clustered points
+    #[serde(default)]
+    pub aggregate: Vec<ClusterAggregateCfg>,
+}
+
+#[derive(Deserialize, Debug)]
+pub struct ClusterAggregateCfg {
+    pub field: String,
+    /// Aggregate function (sum, min, max)
+    pub function: String,
+    /// Attribute name of aggregated value (Default: <field>_<function>)
+    pub name: Option<String>,
+}
+
+#[derive(Deserialize, Debug)]
+pub struct LayerThinCfg {
+    /// Minimal distance between points in pixels (Default: 4)
+    pub distance: Option<u32>,
+    /// Numeric attribute ranking points to keep, e.g. "population", name after query `rename` (Default: input order)
+    pub rank: Option<String>,
+    /// Maximal zoom level with thinning (Default: 22)
+    pub maxzoom: Option<u8>,
+}
+
+#[derive(Deserialize, Debug)]
+pub struct LayerLabelCfg {
+    /// Emit label point instead of polygon (Default: false)
+    pub replace: Option<bool>,
+    /// Name of separate layer for label points (e.g. "lakes_label")
+    pub layer: Option<String>,
+    /// Precision of label placement in tile coordinates (Default: 1.0)
+    pub precision: Option<f64>,
+}
+
+#[derive(Deserialize, Debug)]
+pub struct LayerParamCfg {
+    /// Parameter name in request query string and SQL (`!name!`)
+    pub name: String,
+    /// Parameter type (int, float, text, bool)
+    #[serde(rename = "type")]
+    pub param_type: String,
+    /// Value of missing parameter (Default: NULL)
+    pub default: Option<Value>,
 }
 
 #[derive(Deserialize, Debug)]
@@ -120,6 +225,8 @@ pub struct LayerCfg {
     /// Spatial reference system (PostGIS SRID)
     pub srid: Option<i32>,
     pub fid_field: Option<String>,
+    /// Feature ID generation (column, hash, attributes, sequence)
+    pub fid_mode: Option<String>,
     // Input for derived queries
     pub table_name: Option<String>,
     pub query_limit: Option<u32>,
@@ -130,8 +237,34 @@ pub struct LayerCfg {
     pub tile_size: Option<u32>,
     /// Simplify geometry (lines and polygons)
     pub simplify: Option<bool>,
+    /// Simplification tolerance in pixels (Default: 0.5)
+    pub tolerance: Option<f64>,
     /// Tile buffer size in pixels (None: no clipping)
     pub buffer_size: Option<u32>,
+    /// Drop polygons smaller than `min_area` square pixels
+    pub min_area: Option<f64>,
+    /// Drop lines shorter than `min_length` pixels
+    pub min_length: Option<f64>,
+    /// Point clustering
+    pub cluster: Option<LayerClusterCfg>,
+    /// Drop points closer than `distance` pixels
+    pub thin: Option<LayerThinCfg>,
+    /// Merge lines and polygons with equal attributes
+    pub merge: Option<bool>,
+    /// Label points of polygons
+    pub label_point: Option<LayerLabelCfg>,
+    /// Feature order within tile ("<field> [ASC|DESC]", field name after query `rename`)
+    pub order_by: Option<String>,
+    /// Explicit order of `order_by` field values (e.g. ["residential", "primary", "motorway"])
+    pub order_values: Option<Vec<String>>,
+    /// Derive tiles above `maxzoom` from the tile at `maxzoom` (Default: false)
+    pub overzoom: Option<bool>,
+    /// Encode layer in PostGIS with ST_AsMVT (Default: false).
+    /// Attribute selection, feature ordering and generalization options are not supported.
+    pub st_asmvt: Option<bool>,
+    /// Request parameters bound in SQL queries
+    #[serde(default)]
+    pub params: Vec<LayerParamCfg>,
     // Inline style
     pub style: Option<Value>,
 }
@@ -151,6 +284,7 @@ pub struct CacheFileCfg {
 pub struct WebserverCfg {
     pub bind: Option<String>,
     pub port: Option<u16>,
+    /// Number of threads for tile requests and limit of concurrent datasource queries (Default: 4)
     pub threads: Option<u8>,
     // Cache-Control headers set by web server
     // https://developer.mozilla.org/en-US/docs/Web/HTTP/Headers/Cache-Control#Expiration
@@ -198,10 +332,131 @@ pub fn read_config<'a, T: Deserialize<'a>>(path: &str) -> Result<T, String> {
     parse_config(config_toml, path)
 }
 
+/// Replace `${VAR}` and `${VAR:-default}` with environment variable values.
+/// `$${` is replaced by a literal `${`.
+pub fn interpolate_env(text: &str) -> Result<String, String> {
+    let mut result = String::with_capacity(text.len());
+    let mut rest = text;
+    while let Some(start) = rest.find('$') {
+        result.push_str(&rest[..start]);
+        rest = &rest[start..];
+        if rest.starts_with("$${") {
+            result.push_str("${");
+            rest = &rest[3..];
+            continue;
+        }
+        if !rest.starts_with("${") {
+            result.push('$');
+            rest = &rest[1..];
+            continue;
+        }
+        let expr_len = match rest.find('}') {
+            Some(len) => len,
+            None => return Err(format!("Unterminated variable `{}`", rest)),
+        };
+        let expr = &rest[2..expr_len];
+        let (name, default) = match expr.find(":-") {
+            Some(pos) => (&expr[..pos], Some(&expr[pos + 2..])),
+            None => (expr, None),
+        };
+        // Like in shells, the default also replaces empty values
+        let value = env::var(name)
+            .ok()
+            .filter(|value| !value.is_empty() || default.is_none());
+        match value.or(default.map(String::from)) {
+            Some(value) => result.push_str(&value),
+            None => return Err(format!("Environment variable `{}` is not set", name)),
+        }
+        rest = &rest[expr_len + 1..];
+    }
+    result.push_str(rest);
+    Ok(result)
+}
+
+/// Length of a basic TOML string starting with `delim` (up to the end of text if unterminated)
+fn basic_string_len(text: &str, delim: &str) -> usize {
+    let bytes = text.as_bytes();
+    let mut i = delim.len();
+    while i < bytes.len() {
+        if bytes[i..].starts_with(delim.as_bytes()) {
+            return i + delim.len();
+        }
+        if bytes[i] == b'\\' {
+            i += 2;
+            continue;
+        }
+        if delim.len() == 1 && bytes[i] == b'\n' {
+            return i;
+        }
+        i += 1;
+    }
+    text.len()
+}
+
+/// Interpolate unquoted environment variables like `port = ${PORT}` in the raw TOML text.
+/// Strings are interpolated after parsing, comments are skipped.
+fn interpolate_unquoted(text: &str) -> Result<String, String> {
+    let mut result = String::with_capacity(text.len());
+    let mut rest = text;
+    while let Some(start) = rest.find(|c| c == '"' || c == '\'' || c == '#' || c == '$') {
+        result.push_str(&rest[..start]);
+        rest = &rest[start..];
+        let len = if rest.starts_with("\"\"\"") {
+            basic_string_len(rest, "\"\"\"")
+        } else if rest.starts_with('"') {
+            basic_string_len(rest, "\"")
+        } else if rest.starts_with("'''") {
+            rest[3..].find("'''").map_or(rest.len(), |pos| pos + 6)
+        } else if rest.starts_with('\'') {
+            rest[1..].find('\'').map_or(rest.len(), |pos| pos + 2)
+        } else if rest.starts_with('#') {
+            rest.find('\n').unwrap_or(rest.len())
+        } else if rest.starts_with("${") || rest.starts_with("$${") {
+            let line = &rest[..rest.find('\n').unwrap_or(rest.len())];
+            let len = line.find('}').map_or(line.len(), |pos| pos + 1);
+            result.push_str(&interpolate_env(&rest[..len])?);
+            rest = &rest[len..];
+            continue;
+        } else {
+            1
+        };
+        result.push_str(&rest[..len]);
+        rest = &rest[len..];
+    }
+    result.push_str(rest);
+    Ok(result)
+}
+
+/// Interpolate environment variables in all string values
+fn interpolate_values(value: &mut Value) -> Result<(), String> {
+    match value {
+        &mut Value::String(ref mut text) => {
+            let interpolated = interpolate_env(text)?;
+            *text = interpolated;
+        }
+        &mut Value::Array(ref mut values) => {
+            for value in values.iter_mut() {
+                interpolate_values(value)?;
+            }
+        }
+        &mut Value::Table(ref mut table) => {
+            for (_, value) in table.iter_mut() {
+                interpolate_values(value)?;
+            }
+        }
+        _ => {}
+    }
+    Ok(())
+}
+
 /// Parse the configuration into an config struct.
 pub fn parse_config<'a, T: Deserialize<'a>>(config_toml: String, path: &str) -> Result<T, String> {
-    config_toml
+    let mut config = interpolate_unquoted(&config_toml)
+        .map_err(|err| format!("{} - {}", path, err))?
         .parse::<Value>()
-        .and_then(|cfg| cfg.try_into::<T>())
+        .map_err(|err| format!("{} - {}", path, err))?;
+    interpolate_values(&mut config).map_err(|err| format!("{} - {}", path, err))?;
+    config
+        .try_into::<T>()
         .map_err(|err| format!("{} - {}", path, err))
 }
@@ -3,6 +3,7 @@
 // Licensed under the MIT License. See LICENSE file in the project root for full license information.
 //
 
+use core::config::interpolate_env;
 use core::config::read_config;
 use core::config::ApplicationCfg;
 use core::config::DEFAULT_CONFIG;
@@ -81,3 +82,55 @@ fn test_datasource_compatibility() {
     // assert_eq!(config.datasource[0].dbconn,
     //            Some("postgresql://pi@localhost/natural_earth_vectors".to_string()));
 }
+
+#[test]
+fn test_env_interpolation() {
+    use core::parse_config;
+    use std::env;
+
+    env::set_var("T_REX_TEST_DBCONN", "postgresql://user@db/osm");
+    env::set_var("T_REX_TEST_EMPTY", "");
+    env::remove_var("T_REX_TEST_UNSET");
+    assert_eq!(
+        interpolate_env("${T_REX_TEST_DBCONN}?port=${T_REX_TEST_UNSET:-5432}"),
+        Ok("postgresql://user@db/osm?port=5432".to_string())
+    );
+    assert_eq!(
+        interpolate_env("${T_REX_TEST_EMPTY:-default}/${T_REX_TEST_EMPTY}$$/$${T_REX_TEST_DBCONN}"),
+        Ok("default/$$/${T_REX_TEST_DBCONN}".to_string())
+    );
+    assert_eq!(
+        interpolate_env("${T_REX_TEST_UNSET}"),
+        Err("Environment variable `T_REX_TEST_UNSET` is not set".to_string())
+    );
+    assert_eq!(
+        interpolate_env("${T_REX_TEST_UNSET"),
+        Err("Unterminated variable `${T_REX_TEST_UNSET`".to_string())
+    );
+
+    // Only string values are interpolated, comments are ignored and values need no escaping
+    env::set_var("T_REX_TEST_BIND", "0.0.0.0\" # \\");
+    let toml = DEFAULT_CONFIG.replace(
+        "bind = \"127.0.0.1\"",
+        "bind = \"${T_REX_TEST_BIND}\" # ${T_REX_TEST_UNSET}",
+    );
+    let config: ApplicationCfg = parse_config(toml, "").unwrap();
+    assert_eq!(config.webserver.bind, Some("0.0.0.0\" # \\".to_string()));
+    let toml = DEFAULT_CONFIG.replace("bind = \"127.0.0.1\"", "bind = \"${T_REX_TEST_UNSET}\"");
+    let config: Result<ApplicationCfg, _> = parse_config(toml, "config.toml");
+    assert_eq!(
+        config.err(),
+        Some("config.toml - Environment variable `T_REX_TEST_UNSET` is not set".to_string())
+    );
+
+    // Unquoted variables are inserted as TOML values
+    env::set_var("T_REX_TEST_PORT", "8080");
+    let toml = DEFAULT_CONFIG
+        .replace("port = 6767", "port = ${T_REX_TEST_PORT}")
+        .replace("threads = 4", "threads = ${T_REX_TEST_UNSET:-2} # '${T_REX_TEST_UNSET}'")
+        .replace("bind = \"127.0.0.1\"", "bind = 'a\\${T_REX_TEST_PORT}' # \"");
+    let config: ApplicationCfg = parse_config(toml, "").unwrap();
+    assert_eq!(config.webserver.port, Some(8080));
+    assert_eq!(config.webserver.threads, Some(2));
+    assert_eq!(config.webserver.bind, Some("a\\8080".to_string()));
+}
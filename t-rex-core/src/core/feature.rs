@@ -4,6 +4,9 @@
 //
 
 use core::geom::GeometryType;
+use mvt::vector_tile;
+use protobuf::Message;
+use serde_json;
 
 /// Supported feature attribute value types
 #[derive(Clone, PartialEq, Debug)]
@@ -17,6 +20,82 @@ pub enum FeatureAttrValType {
     Bool(bool),
 }
 
+impl FeatureAttrValType {
+    /// MVT value to FeatureAttrValType
+    pub fn from_mvt_value(value: &vector_tile::Tile_Value) -> Option<FeatureAttrValType> {
+        if value.has_string_value() {
+            Some(FeatureAttrValType::String(
+                value.get_string_value().to_string(),
+            ))
+        } else if value.has_float_value() {
+            Some(FeatureAttrValType::Float(value.get_float_value()))
+        } else if value.has_double_value() {
+            Some(FeatureAttrValType::Double(value.get_double_value()))
+        } else if value.has_int_value() {
+            Some(FeatureAttrValType::Int(value.get_int_value()))
+        } else if value.has_uint_value() {
+            Some(FeatureAttrValType::UInt(value.get_uint_value()))
+        } else if value.has_sint_value() {
+            Some(FeatureAttrValType::SInt(value.get_sint_value()))
+        } else if value.has_bool_value() {
+            Some(FeatureAttrValType::Bool(value.get_bool_value()))
+        } else {
+            None
+        }
+    }
+    /// FeatureAttrValType to JSON value
+    pub fn to_json(&self) -> serde_json::Value {
+        match self {
+            &FeatureAttrValType::String(ref v) => json!(v),
+            &FeatureAttrValType::Float(v) => json!(v),
+            &FeatureAttrValType::Double(v) => json!(v),
+            &FeatureAttrValType::Int(v) => json!(v),
+            &FeatureAttrValType::UInt(v) => json!(v),
+            &FeatureAttrValType::SInt(v) => json!(v),
+            &FeatureAttrValType::Bool(v) => json!(v),
+        }
+    }
+    /// FeatureAttrValType to MVT value
+    pub fn mvt_value(&self) -> vector_tile::Tile_Value {
+        let mut mvt_value = vector_tile::Tile_Value::new();
+        match self {
+            &FeatureAttrValType::String(ref v) => {
+                mvt_value.set_string_value(v.clone());
+            }
+            &FeatureAttrValType::Double(v) => {
+                mvt_value.set_double_value(v);
+            }
+            &FeatureAttrValType::Float(v) => {
+                mvt_value.set_float_value(v);
+            }
+            &FeatureAttrValType::Int(v) => {
+                mvt_value.set_int_value(v);
+            }
+            &FeatureAttrValType::UInt(v) => {
+                mvt_value.set_uint_value(v);
+            }
+            &FeatureAttrValType::SInt(v) => {
+                mvt_value.set_sint_value(v);
+            }
+            &FeatureAttrValType::Bool(v) => {
+                mvt_value.set_bool_value(v);
+            }
+        }
+        mvt_value
+    }
+    /// Numeric value and whether it is an integer
+    pub fn numeric_value(&self) -> Option<(f64, bool)> {
+        match self {
+            &FeatureAttrValType::Float(v) => Some((v as f64, false)),
+            &FeatureAttrValType::Double(v) => Some((v, false)),
+            &FeatureAttrValType::Int(v) => Some((v as f64, true)),
+            &FeatureAttrValType::UInt(v) => Some((v as f64, true)),
+            &FeatureAttrValType::SInt(v) => Some((v as f64, true)),
+            _ => None,
+        }
+    }
+}
+
 pub trait Feature {
     fn fid(&self) -> Option<u64>;
     fn attributes(&self) -> Vec<FeatureAttr>; //TODO: return tuples
@@ -0,0 +1,109 @@
+//
+// Copyright (c) Pirmin Kalberer. All rights reserved.
+// Licensed under the MIT License. See LICENSE file in the project root for full license information.
+//
+
+//! GeoJSON output of features
+
+use core::feature::Feature;
+use core::geom::{self, GeometryType};
+use core::grid::web_mercator_to_wgs84;
+use core::layer::LayerQuery;
+use serde_json;
+
+/// Grid SRS with coordinates convertible to WGS84 (Web Mercator or WGS84)
+pub fn geojson_srid_supported(srid: i32) -> bool {
+    srid == 3857 || srid == 4326
+}
+
+/// Coordinates in WGS84 if grid SRS is Web Mercator
+fn json_point(p: &geom::Point, srid: i32) -> serde_json::Value {
+    let (x, y) = if srid == 3857 {
+        web_mercator_to_wgs84(p.x, p.y)
+    } else {
+        (p.x, p.y)
+    };
+    json!([x, y])
+}
+
+fn json_coords(points: &[geom::Point], srid: i32) -> serde_json::Value {
+    json!(points.iter().map(|p| json_point(p, srid)).collect::<Vec<_>>())
+}
+
+fn json_rings(rings: &[geom::LineString], srid: i32) -> serde_json::Value {
+    json!(
+        rings
+            .iter()
+            .map(|r| json_coords(&r.points, srid))
+            .collect::<Vec<_>>()
+    )
+}
+
+/// GeoJSON geometry of a feature geometry in grid SRS
+pub fn geometry_to_geojson(geom: &GeometryType, srid: i32) -> serde_json::Value {
+    match geom {
+        &GeometryType::Point(ref g) => json!({
+            "type": "Point",
+            "coordinates": json_point(g, srid)
+        }),
+        &GeometryType::MultiPoint(ref g) => json!({
+            "type": "MultiPoint",
+            "coordinates": json_coords(&g.points, srid)
+        }),
+        &GeometryType::LineString(ref g) => json!({
+            "type": "LineString",
+            "coordinates": json_coords(&g.points, srid)
+        }),
+        &GeometryType::MultiLineString(ref g) => json!({
+            "type": "MultiLineString",
+            "coordinates": json_rings(&g.lines, srid)
+        }),
+        &GeometryType::Polygon(ref g) => json!({
+            "type": "Polygon",
+            "coordinates": json_rings(&g.rings, srid)
+        }),
+        &GeometryType::MultiPolygon(ref g) => json!({
+            "type": "MultiPolygon",
+            "coordinates": g.polygons
+                .iter()
+                .map(|p| json_rings(&p.rings, srid))
+                .collect::<Vec<_>>()
+        }),
+        &GeometryType::GeometryCollection(_) => {
+            warn!("GeometryCollection not supported");
+            serde_json::Value::Null
+        }
+    }
+}
+
+/// GeoJSON Feature with attributes filtered by `query`
+pub fn feature_to_geojson(
+    feature: &Feature,
+    query: Option<&LayerQuery>,
+    srid: i32,
+) -> serde_json::Value {
+    let mut attributes = feature.attributes();
+    if let Some(query) = query {
+        attributes = query.filter_attributes(attributes);
+    }
+    let mut properties = serde_json::Map::new();
+    for attr in attributes {
+        properties.insert(attr.key, attr.value.to_json());
+    }
+    let geometry = match feature.geometry() {
+        Ok(ref geom) => geometry_to_geojson(geom, srid),
+        Err(e) => {
+            warn!("{}", e);
+            serde_json::Value::Null
+        }
+    };
+    let mut json = json!({
+        "type": "Feature",
+        "geometry": geometry,
+        "properties": properties
+    });
+    if let Some(id) = feature.fid() {
+        json["id"] = json!(id);
+    }
+    json
+}
@@ -0,0 +1,43 @@
+//
+// Copyright (c) Pirmin Kalberer. All rights reserved.
+// Licensed under the MIT License. See LICENSE file in the project root for full license information.
+//
+
+use core::feature::{FeatureAttr, FeatureAttrValType, FeatureStruct};
+use core::geojson::feature_to_geojson;
+use core::geom::{GeometryType, Point};
+use core::layer::LayerQuery;
+
+#[test]
+fn test_feature_to_geojson() {
+    let feature = FeatureStruct {
+        fid: Some(42),
+        attributes: vec![
+            FeatureAttr {
+                key: "name".to_string(),
+                value: FeatureAttrValType::String("Bern".to_string()),
+            },
+            FeatureAttr {
+                key: "pop".to_string(),
+                value: FeatureAttrValType::Int(133_115),
+            },
+        ],
+        geometry: GeometryType::Point(Point::new(0.0, 0.0, None)),
+    };
+    assert_eq!(
+        feature_to_geojson(&feature, None, 3857),
+        json!({
+            "type": "Feature",
+            "id": 42,
+            "geometry": {"type": "Point", "coordinates": [0.0, 0.0]},
+            "properties": {"name": "Bern", "pop": 133115}
+        })
+    );
+
+    let query = LayerQuery {
+        fields: Some(vec!["name".to_string()]),
+        ..Default::default()
+    };
+    let json = feature_to_geojson(&feature, Some(&query), 2056);
+    assert_eq!(json["properties"], json!({"name": "Bern"}));
+}
@@ -8,6 +8,7 @@ use core::enum_serializer::EnumString;
 use core::Config;
 use serde;
 use serde::de::{Deserialize, Deserializer};
+use std::f64;
 use std::fmt;
 
 #[derive(PartialEq, Deserialize, Clone, Debug)]
@@ -27,7 +28,7 @@ pub struct ExtentInt {
     pub maxy: u32,
 }
 
-#[derive(PartialEq, Debug)]
+#[derive(PartialEq, Clone, Debug)]
 pub enum Origin {
     TopLeft,
     BottomLeft, //TopRight, BottomRight
@@ -51,7 +52,7 @@ impl EnumString<Origin> for Origin {
 
 enum_string_serialization!(Origin OriginVisitor);
 
-#[derive(PartialEq, Debug)]
+#[derive(PartialEq, Clone, Debug)]
 pub enum Unit {
     Meters,
     Degrees,
@@ -78,8 +79,26 @@ impl EnumString<Unit> for Unit {
 
 enum_string_serialization!(Unit UnitVisitor);
 
+/// Convert Web Mercator coordinates (EPSG:3857) to WGS84 longitude/latitude
+pub fn web_mercator_to_wgs84(x: f64, y: f64) -> (f64, f64) {
+    const EARTH_RADIUS: f64 = 6378137.0;
+    let lon = (x / EARTH_RADIUS).to_degrees();
+    let lat = (2.0 * (y / EARTH_RADIUS).exp().atan() - f64::consts::FRAC_PI_2).to_degrees();
+    (lon, lat)
+}
+
+/// Convert WGS84 longitude/latitude to Web Mercator coordinates (EPSG:3857)
+pub fn wgs84_to_web_mercator(lon: f64, lat: f64) -> (f64, f64) {
+    const EARTH_RADIUS: f64 = 6378137.0;
+    const MAX_LAT: f64 = 85.0511287798066;
+    let lat = lat.max(-MAX_LAT).min(MAX_LAT);
+    let x = lon.to_radians() * EARTH_RADIUS;
+    let y = (f64::consts::FRAC_PI_4 + lat.to_radians() / 2.0).tan().ln() * EARTH_RADIUS;
+    (x, y)
+}
+
 // Credits: MapCache by Thomas Bonfort (http://mapserver.org/mapcache/)
-#[derive(Deserialize, Debug)]
+#[derive(Deserialize, Clone, Debug)]
 pub struct Grid {
     /// The width and height of an individual tile, in pixels.
     width: u16,
@@ -192,6 +211,29 @@ impl Grid {
     pub fn maxzoom(&self) -> u8 {
         self.nlevels() - 1
     }
+    /// Tile width in pixels
+    pub fn tile_width(&self) -> u16 {
+        self.width
+    }
+    /// Grid with `factor` times more pixels per tile covering the same tile extents
+    pub fn scaled(&self, factor: u16) -> Grid {
+        let mut grid = self.clone();
+        grid.width *= factor;
+        grid.height *= factor;
+        grid.resolutions = self.resolutions
+            .iter()
+            .map(|res| res / factor as f64)
+            .collect();
+        grid
+    }
+    /// Grid with tiles at zoom level z+`dz` covering the extents of tiles at zoom level z
+    /// (assumes resolutions halving with each zoom level)
+    pub fn with_zoom_offset(&self, dz: u8) -> Grid {
+        let mut grid = self.clone();
+        grid.width <<= dz;
+        grid.height <<= dz;
+        grid
+    }
     pub fn pixel_width(&self, zoom: u8) -> f64 {
         self.resolutions[zoom as usize] //TODO: assumes grid unit 'm'
     }
@@ -223,6 +265,20 @@ impl Grid {
             },
         }
     }
+    /// Tile x and y of a tile extent in TMS adressing scheme
+    pub fn tile_index(&self, extent: &Extent, zoom: u8) -> (u32, u32) {
+        let res = self.resolutions[zoom as usize];
+        let unitwidth = self.width as f64 * res;
+        let unitheight = self.height as f64 * res;
+        let centerx = (extent.minx + extent.maxx) / 2.0;
+        let centery = (extent.miny + extent.maxy) / 2.0;
+        let x = ((centerx - self.extent.minx) / unitwidth).floor();
+        let y = match self.origin {
+            Origin::BottomLeft => ((centery - self.extent.miny) / unitheight).floor(),
+            Origin::TopLeft => ((self.extent.maxy - centery) / unitheight).floor(),
+        };
+        (x.max(0.0) as u32, y.max(0.0) as u32)
+    }
     /// reverse y tile for XYZ adressing scheme
     pub fn ytile_from_xyz(&self, ytile: u32, zoom: u8) -> u32 {
         let res = self.resolutions[zoom as usize];
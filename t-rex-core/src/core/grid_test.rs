@@ -192,6 +192,46 @@ fn test_grid_calculations() {
     );
 }
 
+#[test]
+fn test_scaled_grid() {
+    let grid = Grid::web_mercator();
+    let grid512 = grid.scaled(2);
+    assert_eq!(grid512.tile_width(), 512);
+    assert!((grid512.pixel_width(10) - grid.pixel_width(11)).abs() < 1e-9);
+    let (ext512, ext) = (
+        grid512.tile_extent(486, 332, 10),
+        grid.tile_extent(486, 332, 10),
+    );
+    assert!((ext512.minx - ext.minx).abs() < 1e-6 && (ext512.maxy - ext.maxy).abs() < 1e-6);
+    assert_eq!(grid512.ytile_from_xyz(691, 10), grid.ytile_from_xyz(691, 10));
+}
+
+#[test]
+fn test_tile_index() {
+    let grid = Grid::web_mercator();
+    let extent = grid.tile_extent(486, 332, 10);
+    assert_eq!(grid.tile_index(&extent, 10), (486, 332));
+    assert_eq!(grid.tile_index(&grid.tile_extent(0, 0, 0), 0), (0, 0));
+    let grid = Grid::wgs84();
+    assert_eq!(grid.tile_index(&grid.tile_extent(3, 1, 2), 2), (3, 1));
+    let grid512 = Grid::web_mercator().scaled(2);
+    assert_eq!(grid512.tile_index(&extent, 10), (486, 332));
+}
+
+#[test]
+fn test_zoom_offset_grid() {
+    let grid = Grid::web_mercator();
+    let grid512 = grid.with_zoom_offset(1);
+    assert_eq!(grid512.tile_width(), 512);
+    assert_eq!(grid512.pixel_width(11), grid.pixel_width(11));
+    let (ext512, ext) = (
+        grid512.tile_extent(486, 332, 11),
+        grid.tile_extent(486, 332, 10),
+    );
+    assert!((ext512.minx - ext.minx).abs() < 1e-6 && (ext512.maxy - ext.maxy).abs() < 1e-6);
+    assert!((ext512.maxx - ext.maxx).abs() < 1e-6 && (ext512.miny - ext.miny).abs() < 1e-6);
+}
+
 #[test]
 fn test_grid_from_config() {
     use core::parse_config;
@@ -3,19 +3,160 @@
 // Licensed under the MIT License. See LICENSE file in the project root for full license information.
 //
 
-use core::config::LayerCfg;
+use core::config::{LayerCfg, LayerClusterCfg, LayerLabelCfg, LayerParamCfg, LayerThinCfg};
+use core::feature::FeatureAttr;
 use core::Config;
 use service::glstyle_converter::toml_style_to_gljson;
-use std::collections::HashMap;
+use std::collections::{BTreeMap, HashMap};
+use toml::Value;
 
-#[derive(Debug)]
+#[derive(Clone, Default, Debug)]
 pub struct LayerQuery {
     pub minzoom: Option<u8>,
     pub maxzoom: Option<u8>,
     pub sql: Option<String>,
+    /// Attributes included in tiles (None: all)
+    pub fields: Option<Vec<String>>,
+    /// Attributes excluded from tiles
+    pub exclude_fields: Vec<String>,
+    /// Attribute renaming
+    pub rename: HashMap<String, String>,
+    /// Zoom level dependent layer settings
+    pub buffer_size: Option<u32>,
+    pub tile_size: Option<u32>,
+    pub simplify: Option<bool>,
+    pub tolerance: Option<f64>,
+    pub query_limit: Option<u32>,
+    pub min_area: Option<f64>,
+    pub min_length: Option<f64>,
+}
+
+/// Feature ID generation
+#[derive(PartialEq, Clone, Copy, Debug)]
+pub enum FidMode {
+    /// Integer value of `fid_field`
+    Column,
+    /// Hash of `fid_field` value (e.g. string or UUID)
+    Hash,
+    /// Hash of all attributes
+    Attributes,
+    /// Sequential number within tile
+    Sequence,
+}
+
+impl Default for FidMode {
+    fn default() -> FidMode {
+        FidMode::Column
+    }
+}
+
+impl FidMode {
+    fn from_config(mode: &str) -> Result<Self, String> {
+        match mode {
+            "column" => Ok(FidMode::Column),
+            "hash" => Ok(FidMode::Hash),
+            "attributes" => Ok(FidMode::Attributes),
+            "sequence" => Ok(FidMode::Sequence),
+            _ => Err(format!("Unknown fid_mode `{}`", mode)),
+        }
+    }
+}
+
+/// Aggregate function for clustered attributes
+#[derive(PartialEq, Clone, Copy, Debug)]
+pub enum AggregateFunc {
+    Sum,
+    Min,
+    Max,
+}
+
+#[derive(Clone, Debug)]
+pub struct ClusterAggregate {
+    pub field: String,
+    pub func: AggregateFunc,
+    /// Name of aggregated attribute
+    pub name: String,
+}
+
+#[derive(Clone, Debug)]
+pub struct LayerCluster {
+    /// Cluster radius in pixels
+    pub radius: u32,
+    pub maxzoom: Option<u8>,
+    pub aggregates: Vec<ClusterAggregate>,
+}
+
+#[derive(Clone, Debug)]
+pub struct LayerThin {
+    /// Minimal distance between points in pixels
+    pub distance: u32,
+    /// Attribute ranking points to keep (highest value first)
+    pub rank: Option<String>,
+    pub maxzoom: Option<u8>,
+}
+
+#[derive(Clone, Debug)]
+pub struct LayerLabel {
+    /// Emit label point instead of polygon
+    pub replace: bool,
+    /// Name of separate label layer
+    pub layer: Option<String>,
+    /// Precision in tile coordinates
+    pub precision: f64,
+}
+
+/// Sort order of features within a tile
+#[derive(Clone, Debug)]
+pub struct FeatureOrder {
+    pub field: String,
+    pub descending: bool,
+    /// Explicit order of field values
+    pub values: Vec<String>,
+}
+
+/// Names of built-in SQL query variables
+const RESERVED_PARAMS: &'static [&'static str] = &[
+    "bbox",
+    "zoom",
+    "pixel_width",
+    "scale_denominator",
+    "tile_x",
+    "tile_y",
+    "buffer_size",
+    "tile_extent",
+    "srid",
+    "mvt_buffer",
+];
+
+/// Type of a request parameter
+#[derive(PartialEq, Clone, Copy, Debug)]
+pub enum ParamType {
+    Int,
+    Float,
+    Text,
+    Bool,
+}
+
+/// Value of a request parameter
+#[derive(PartialEq, Clone, Debug)]
+pub enum ParamValue {
+    Int(i64),
+    Float(f64),
+    Text(String),
+    Bool(bool),
+    Null,
 }
 
-#[derive(Default, Debug)]
+/// Request query string parameter bound in SQL queries (`!name!`)
+#[derive(Clone, Debug)]
+pub struct LayerParam {
+    pub name: String,
+    pub param_type: ParamType,
+    /// Value of missing parameter
+    pub default: ParamValue,
+}
+
+#[derive(Clone, Default, Debug)]
 pub struct Layer {
     pub name: String,
     pub datasource: Option<String>,
@@ -24,6 +165,8 @@ pub struct Layer {
     /// Spatial reference system (PostGIS SRID)
     pub srid: Option<i32>,
     pub fid_field: Option<String>,
+    /// Feature ID generation
+    pub fid_mode: FidMode,
     // Input for derived queries
     pub table_name: Option<String>,
     pub query_limit: Option<u32>,
@@ -33,8 +176,30 @@ pub struct Layer {
     pub tile_size: u32,
     /// Simplify geometry (lines and polygons)
     pub simplify: bool,
+    /// Simplification tolerance in pixels (None: half a pixel)
+    pub tolerance: Option<f64>,
     /// Tile buffer size in pixels (None: no clipping)
     pub buffer_size: Option<u32>,
+    /// Minimal polygon area in square pixels
+    pub min_area: Option<f64>,
+    /// Minimal line length in pixels
+    pub min_length: Option<f64>,
+    /// Point clustering
+    pub cluster: Option<LayerCluster>,
+    /// Density based point thinning
+    pub thin: Option<LayerThin>,
+    /// Merge lines and polygons with equal attributes
+    pub merge: bool,
+    /// Label points of polygons
+    pub label_point: Option<LayerLabel>,
+    /// Feature order within tile
+    pub order_by: Option<FeatureOrder>,
+    /// Derive tiles above maxzoom from parent tiles
+    pub overzoom: bool,
+    /// Encode layer in PostGIS with ST_AsMVT
+    pub st_asmvt: bool,
+    /// Request parameters bound in SQL queries
+    pub params: Vec<LayerParam>,
     // Inline style
     pub style: Option<String>,
 }
@@ -46,6 +211,192 @@ impl LayerQuery {
     pub fn maxzoom(&self) -> u8 {
         self.maxzoom.unwrap_or(22)
     }
+    /// Query has field selection or renaming settings
+    pub fn has_attribute_settings(&self) -> bool {
+        self.fields.is_some() || !self.exclude_fields.is_empty() || !self.rename.is_empty()
+    }
+    /// Query has attribute or zoom level dependent layer settings
+    pub fn has_settings(&self) -> bool {
+        self.has_attribute_settings()
+            || self.buffer_size.is_some()
+            || self.tile_size.is_some()
+            || self.simplify.is_some()
+            || self.tolerance.is_some()
+            || self.query_limit.is_some()
+            || self.min_area.is_some()
+            || self.min_length.is_some()
+    }
+    /// Attribute `name` is kept by field selection and renaming, if present in the feature
+    pub fn keeps_attribute(&self, name: &str) -> bool {
+        let selected = |key: &str| {
+            self.fields
+                .as_ref()
+                .map_or(true, |fields| fields.iter().any(|field| field == key))
+                && !self.exclude_fields.iter().any(|field| field == key)
+        };
+        match self.rename.iter().find(|&(_, to)| to == name) {
+            Some((from, _)) => selected(from),
+            None => !self.rename.contains_key(name) && selected(name),
+        }
+    }
+    /// Apply field selection and renaming to feature attributes
+    pub fn filter_attributes(&self, attributes: Vec<FeatureAttr>) -> Vec<FeatureAttr> {
+        attributes
+            .into_iter()
+            .filter(|attr| {
+                self.fields
+                    .as_ref()
+                    .map_or(true, |fields| fields.contains(&attr.key))
+                    && !self.exclude_fields.contains(&attr.key)
+            })
+            .map(|attr| match self.rename.get(&attr.key) {
+                Some(name) => FeatureAttr {
+                    key: name.clone(),
+                    value: attr.value,
+                },
+                None => attr,
+            })
+            .collect()
+    }
+}
+
+impl LayerCluster {
+    fn from_config(cfg: &LayerClusterCfg) -> Result<Self, String> {
+        let mut aggregates = Vec::new();
+        for agg in &cfg.aggregate {
+            let func = match agg.function.as_str() {
+                "sum" => AggregateFunc::Sum,
+                "min" => AggregateFunc::Min,
+                "max" => AggregateFunc::Max,
+                _ => {
+                    return Err(format!(
+                        "Unknown cluster aggregate function `{}`",
+                        agg.function
+                    ))
+                }
+            };
+            aggregates.push(ClusterAggregate {
+                field: agg.field.clone(),
+                func: func,
+                name: agg.name
+                    .clone()
+                    .unwrap_or(format!("{}_{}", agg.field, agg.function)),
+            });
+        }
+        Ok(LayerCluster {
+            radius: cfg.radius.unwrap_or(40),
+            maxzoom: cfg.maxzoom,
+            aggregates: aggregates,
+        })
+    }
+    pub fn maxzoom(&self) -> u8 {
+        self.maxzoom.unwrap_or(22)
+    }
+}
+
+impl LayerThin {
+    fn from_config(cfg: &LayerThinCfg) -> Self {
+        LayerThin {
+            distance: cfg.distance.unwrap_or(4),
+            rank: cfg.rank.clone(),
+            maxzoom: cfg.maxzoom,
+        }
+    }
+    pub fn maxzoom(&self) -> u8 {
+        self.maxzoom.unwrap_or(22)
+    }
+}
+
+impl LayerLabel {
+    fn from_config(cfg: &LayerLabelCfg) -> Self {
+        LayerLabel {
+            replace: cfg.replace.unwrap_or(false),
+            layer: cfg.layer.clone(),
+            precision: cfg.precision.unwrap_or(1.0),
+        }
+    }
+}
+
+impl FeatureOrder {
+    /// Parse "<field> [ASC|DESC]"
+    fn from_config(order_by: &str, values: &Option<Vec<String>>) -> Result<Self, String> {
+        let parts: Vec<&str> = order_by.split_whitespace().collect();
+        let descending = match parts.len() {
+            1 => false,
+            2 if parts[1].eq_ignore_ascii_case("asc") => false,
+            2 if parts[1].eq_ignore_ascii_case("desc") => true,
+            _ => return Err(format!("Invalid order_by specification `{}`", order_by)),
+        };
+        Ok(FeatureOrder {
+            field: parts[0].to_string(),
+            descending: descending,
+            values: values.clone().unwrap_or(Vec::new()),
+        })
+    }
+}
+
+impl ParamType {
+    fn from_config(param_type: &str) -> Result<Self, String> {
+        match param_type {
+            "int" => Ok(ParamType::Int),
+            "float" => Ok(ParamType::Float),
+            "text" => Ok(ParamType::Text),
+            "bool" => Ok(ParamType::Bool),
+            _ => Err(format!("Unknown parameter type `{}`", param_type)),
+        }
+    }
+}
+
+impl LayerParam {
+    fn from_config(cfg: &LayerParamCfg) -> Result<Self, String> {
+        if cfg.name.is_empty()
+            || !cfg.name.chars().all(|c| c.is_ascii_alphanumeric() || c == '_')
+        {
+            return Err(format!("Invalid parameter name `{}`", cfg.name));
+        }
+        if RESERVED_PARAMS.contains(&cfg.name.as_str()) {
+            return Err(format!("Reserved parameter name `{}`", cfg.name));
+        }
+        let param_type = ParamType::from_config(&cfg.param_type)?;
+        let default = match cfg.default {
+            Some(ref value) => match (param_type, value) {
+                (ParamType::Int, &Value::Integer(v)) => ParamValue::Int(v),
+                (ParamType::Float, &Value::Integer(v)) => ParamValue::Float(v as f64),
+                (ParamType::Float, &Value::Float(v)) => ParamValue::Float(v),
+                (ParamType::Text, &Value::String(ref v)) => ParamValue::Text(v.clone()),
+                (ParamType::Bool, &Value::Boolean(v)) => ParamValue::Bool(v),
+                _ => {
+                    return Err(format!(
+                        "Invalid default value of parameter `{}`",
+                        cfg.name
+                    ))
+                }
+            },
+            None => ParamValue::Null,
+        };
+        Ok(LayerParam {
+            name: cfg.name.clone(),
+            param_type: param_type,
+            default: default,
+        })
+    }
+    /// Typed value of request parameter
+    pub fn parse(&self, value: &str) -> Result<ParamValue, String> {
+        let parsed = match self.param_type {
+            ParamType::Int => value.parse::<i64>().ok().map(ParamValue::Int),
+            ParamType::Float => value
+                .parse::<f64>()
+                .ok()
+                .and_then(|v| if v.is_finite() { Some(v) } else { None })
+                .map(ParamValue::Float),
+            ParamType::Text => Some(ParamValue::Text(value.to_string())),
+            ParamType::Bool => value.parse::<bool>().ok().map(ParamValue::Bool),
+        };
+        parsed.ok_or(format!(
+            "Invalid value `{}` of parameter `{}`",
+            value, self.name
+        ))
+    }
 }
 
 impl Layer {
@@ -64,8 +415,11 @@ impl Layer {
     }
     // SQL query for zoom level
     pub fn query(&self, level: u8) -> Option<&String> {
+        // Queries with settings only don't hide SQL of other queries,
+        // whereas an empty query selects the automatic table query
         let mut queries = self.query
             .iter()
+            .filter(|ref q| q.sql.is_some() || !q.has_settings())
             .map(|ref q| {
                 (
                     q.minzoom(),
@@ -81,6 +435,149 @@ impl Layer {
             .find(|ref q| level >= q.0 && level <= q.1);
         query.and_then(|ref q| q.2)
     }
+    /// Query with attribute settings for zoom level
+    pub fn attribute_query(&self, level: u8) -> Option<&LayerQuery> {
+        self.query
+            .iter()
+            .filter(|q| q.has_attribute_settings() && level >= q.minzoom() && level <= q.maxzoom())
+            .max_by_key(|q| q.minzoom())
+    }
+    /// Setting of the most specific query for zoom level
+    fn query_setting<T, F>(&self, level: u8, setting: F) -> Option<T>
+    where
+        F: Fn(&LayerQuery) -> Option<T>,
+    {
+        self.query
+            .iter()
+            .filter(|q| level >= q.minzoom() && level <= q.maxzoom())
+            .filter_map(|q| setting(q).map(|value| (q.minzoom(), value)))
+            .max_by_key(|&(minzoom, _)| minzoom)
+            .map(|(_, value)| value)
+    }
+    /// Tile buffer size for zoom level
+    pub fn buffer_size(&self, level: u8) -> Option<u32> {
+        self.query_setting(level, |q| q.buffer_size)
+            .or(self.buffer_size)
+    }
+    /// Tile size for zoom level
+    pub fn tile_size(&self, level: u8) -> u32 {
+        self.query_setting(level, |q| q.tile_size)
+            .unwrap_or(self.tile_size)
+    }
+    /// Geometry simplification for zoom level
+    pub fn simplify(&self, level: u8) -> bool {
+        self.query_setting(level, |q| q.simplify)
+            .unwrap_or(self.simplify)
+    }
+    /// Simplification tolerance for zoom level
+    pub fn tolerance(&self, level: u8) -> Option<f64> {
+        self.query_setting(level, |q| q.tolerance)
+            .or(self.tolerance)
+    }
+    /// Feature limit for zoom level
+    pub fn query_limit(&self, level: u8) -> Option<u32> {
+        self.query_setting(level, |q| q.query_limit)
+            .or(self.query_limit)
+    }
+    /// Check that an attribute used by a layer setting is kept by the attribute settings of all queries
+    fn check_attribute(&self, setting: &str, name: &str) -> Result<(), String> {
+        match self.query.iter().find(|q| !q.keeps_attribute(name)) {
+            Some(q) => Err(format!(
+                "Layer `{}`: {} attribute `{}` is removed or renamed in query for zoom levels {}-{}",
+                self.name,
+                setting,
+                name,
+                q.minzoom(),
+                q.maxzoom()
+            )),
+            None => Ok(()),
+        }
+    }
+    /// Minimal polygon area for zoom level
+    pub fn min_area(&self, level: u8) -> Option<f64> {
+        self.query_setting(level, |q| q.min_area)
+            .or(self.min_area)
+    }
+    /// Minimal line length for zoom level
+    pub fn min_length(&self, level: u8) -> Option<f64> {
+        self.query_setting(level, |q| q.min_length)
+            .or(self.min_length)
+    }
+    /// Layer with settings of zoom level
+    pub fn zoom_layer(&self, level: u8) -> Layer {
+        let mut layer = self.clone();
+        layer.buffer_size = self.buffer_size(level);
+        layer.tile_size = self.tile_size(level);
+        layer.simplify = self.simplify(level);
+        layer.tolerance = self.tolerance(level);
+        layer.query_limit = self.query_limit(level);
+        layer.min_area = self.min_area(level);
+        layer.min_length = self.min_length(level);
+        layer
+    }
+    /// Configured options which are not applied to layers encoded with ST_AsMVT
+    fn st_asmvt_unsupported(&self) -> Vec<&'static str> {
+        let mut options = Vec::new();
+        if self.query.iter().any(|q| q.fields.is_some()) {
+            options.push("fields");
+        }
+        if self.query.iter().any(|q| !q.exclude_fields.is_empty()) {
+            options.push("exclude_fields");
+        }
+        if self.query.iter().any(|q| !q.rename.is_empty()) {
+            options.push("rename");
+        }
+        if self.fid_mode != FidMode::Column {
+            options.push("fid_mode");
+        }
+        if self.order_by.is_some() {
+            options.push("order_by");
+        }
+        if self.cluster.is_some() {
+            options.push("cluster");
+        }
+        if self.thin.is_some() {
+            options.push("thin");
+        }
+        if self.merge {
+            options.push("merge");
+        }
+        if self.label_point.is_some() {
+            options.push("label_point");
+        }
+        if self.min_area.is_some() || self.query.iter().any(|q| q.min_area.is_some()) {
+            options.push("min_area");
+        }
+        if self.min_length.is_some() || self.query.iter().any(|q| q.min_length.is_some()) {
+            options.push("min_length");
+        }
+        options
+    }
+    /// Point clustering settings for zoom level
+    pub fn cluster(&self, level: u8) -> Option<&LayerCluster> {
+        self.cluster
+            .as_ref()
+            .and_then(|c| if level <= c.maxzoom() { Some(c) } else { None })
+    }
+    /// Point thinning settings for zoom level
+    pub fn thin(&self, level: u8) -> Option<&LayerThin> {
+        self.thin
+            .as_ref()
+            .and_then(|t| if level <= t.maxzoom() { Some(t) } else { None })
+    }
+    /// Values of request parameters (default of missing parameters)
+    pub fn param_values(
+        &self,
+        params: &BTreeMap<String, String>,
+    ) -> Result<Vec<ParamValue>, String> {
+        self.params
+            .iter()
+            .map(|param| match params.get(&param.name) {
+                Some(value) => param.parse(value),
+                None => Ok(param.default.clone()),
+            })
+            .collect()
+    }
     /// Layer properties needed e.g. for metadata.json
     pub fn metadata(&self) -> HashMap<&str, String> {
         //TODO: return Zoom-Level Array
@@ -105,6 +602,16 @@ impl<'a> Config<'a, LayerCfg> for Layer {
                 minzoom: lq.minzoom,
                 maxzoom: lq.maxzoom,
                 sql: lq.sql.clone(),
+                fields: lq.fields.clone(),
+                exclude_fields: lq.exclude_fields.clone(),
+                rename: lq.rename.clone(),
+                buffer_size: lq.buffer_size,
+                tile_size: lq.tile_size,
+                simplify: lq.simplify,
+                tolerance: lq.tolerance,
+                query_limit: lq.query_limit,
+                min_area: lq.min_area,
+                min_length: lq.min_length,
             })
             .collect();
         let style = match layer_cfg.style {
@@ -114,21 +621,68 @@ impl<'a> Config<'a, LayerCfg> for Layer {
             }
             None => None,
         };
-        Ok(Layer {
+        let fid_mode = match layer_cfg.fid_mode {
+            Some(ref mode) => FidMode::from_config(mode)?,
+            None => FidMode::default(),
+        };
+        let order_by = match layer_cfg.order_by {
+            Some(ref order_by) => Some(FeatureOrder::from_config(
+                order_by,
+                &layer_cfg.order_values,
+            )?),
+            None => None,
+        };
+        let cluster = match layer_cfg.cluster {
+            Some(ref cluster) => Some(LayerCluster::from_config(cluster)?),
+            None => None,
+        };
+        let params = layer_cfg
+            .params
+            .iter()
+            .map(LayerParam::from_config)
+            .collect::<Result<Vec<_>, _>>()?;
+        let layer = Layer {
             name: layer_cfg.name.clone(),
             datasource: layer_cfg.datasource.clone(), //TODO: inherit from parents if None?
             geometry_field: layer_cfg.geometry_field.clone(),
             geometry_type: layer_cfg.geometry_type.clone(),
             srid: layer_cfg.srid,
             fid_field: layer_cfg.fid_field.clone(),
+            fid_mode: fid_mode,
             table_name: layer_cfg.table_name.clone(),
             query_limit: layer_cfg.query_limit,
             query: queries,
             tile_size: layer_cfg.tile_size.unwrap_or(4096),
             simplify: layer_cfg.simplify.unwrap_or(false),
+            tolerance: layer_cfg.tolerance,
             buffer_size: layer_cfg.buffer_size,
+            min_area: layer_cfg.min_area,
+            min_length: layer_cfg.min_length,
+            cluster: cluster,
+            thin: layer_cfg.thin.as_ref().map(LayerThin::from_config),
+            merge: layer_cfg.merge.unwrap_or(false),
+            label_point: layer_cfg.label_point.as_ref().map(LayerLabel::from_config),
+            order_by: order_by,
+            overzoom: layer_cfg.overzoom.unwrap_or(false),
+            st_asmvt: layer_cfg.st_asmvt.unwrap_or(false),
+            params: params,
             style: style,
-        })
+        };
+        if let Some(ref order) = layer.order_by {
+            layer.check_attribute("order_by", &order.field)?;
+        }
+        if let Some(rank) = layer.thin.as_ref().and_then(|thin| thin.rank.as_ref()) {
+            layer.check_attribute("thin rank", rank)?;
+        }
+        let unsupported = layer.st_asmvt_unsupported();
+        if layer.st_asmvt && !unsupported.is_empty() {
+            return Err(format!(
+                "Layer `{}`: {} not supported with `st_asmvt`",
+                layer.name,
+                unsupported.join(", ")
+            ));
+        }
+        Ok(layer)
     }
 
     fn gen_config() -> String {
@@ -123,6 +123,375 @@ fn test_toml_decode() {
     );
 }
 
+#[test]
+fn test_attribute_selection() {
+    use core::feature::{FeatureAttr, FeatureAttrValType};
+
+    let toml = r#"
+        #[[tileset.layer]]
+        name = "roads"
+        table_name = "roads"
+        [[query]]
+        sql = "SELECT * FROM roads"
+        [[query]]
+        maxzoom = 9
+        fields = ["class", "name_de"]
+        rename = { name_de = "name" }
+        [[query]]
+        minzoom = 14
+        exclude_fields = ["osm_id"]
+        "#;
+    let cfg = layer_from_config(toml).unwrap();
+    // Queries without SQL don't hide SQL of other queries
+    assert_eq!(cfg.query(5), Some(&"SELECT * FROM roads".to_string()));
+    assert_eq!(cfg.query(14), Some(&"SELECT * FROM roads".to_string()));
+    assert!(cfg.attribute_query(12).is_none());
+
+    let attrs = || {
+        vec![
+            FeatureAttr {
+                key: "class".to_string(),
+                value: FeatureAttrValType::String("primary".to_string()),
+            },
+            FeatureAttr {
+                key: "name_de".to_string(),
+                value: FeatureAttrValType::String("Hauptstrasse".to_string()),
+            },
+            FeatureAttr {
+                key: "osm_id".to_string(),
+                value: FeatureAttrValType::Int(42),
+            },
+        ]
+    };
+    let filtered = cfg.attribute_query(5).unwrap().filter_attributes(attrs());
+    assert_eq!(
+        filtered.iter().map(|a| a.key.as_str()).collect::<Vec<_>>(),
+        vec!["class", "name"]
+    );
+    let filtered = cfg.attribute_query(14).unwrap().filter_attributes(attrs());
+    assert_eq!(
+        filtered.iter().map(|a| a.key.as_str()).collect::<Vec<_>>(),
+        vec!["class", "name_de"]
+    );
+}
+
+#[test]
+fn test_zoom_settings() {
+    let toml = r#"
+        #[[tileset.layer]]
+        name = "roads"
+        table_name = "roads"
+        buffer_size = 10
+        simplify = true
+        query_limit = 1000
+        [[query]]
+        maxzoom = 8
+        buffer_size = 40
+        tolerance = 2.0
+        query_limit = 500
+        [[query]]
+        minzoom = 14
+        simplify = false
+        tile_size = 8192
+        "#;
+    let cfg = layer_from_config(toml).unwrap();
+    assert_eq!(cfg.buffer_size(5), Some(40));
+    assert_eq!(cfg.buffer_size(10), Some(10));
+    assert_eq!(cfg.tolerance(5), Some(2.0));
+    assert_eq!(cfg.tolerance(10), None);
+    assert_eq!(cfg.query_limit(8), Some(500));
+    assert_eq!(cfg.simplify(12), true);
+    assert_eq!(cfg.simplify(14), false);
+    assert_eq!(cfg.tile_size(12), 4096);
+    assert_eq!(cfg.tile_size(16), 8192);
+
+    let zoom_layer = cfg.zoom_layer(16);
+    assert_eq!(zoom_layer.tile_size, 8192);
+    assert_eq!(zoom_layer.simplify, false);
+    assert_eq!(zoom_layer.query_limit, Some(1000));
+}
+
+#[test]
+fn test_query_without_sql() {
+    let toml = r#"
+        #[[tileset.layer]]
+        name = "roads"
+        table_name = "roads"
+        [[query]]
+        sql = "SELECT * FROM roads_gen"
+        [[query]]
+        minzoom = 10
+        buffer_size = 10
+        [[query]]
+        minzoom = 14
+        "#;
+    let cfg = layer_from_config(toml).unwrap();
+    assert_eq!(cfg.query(8), Some(&"SELECT * FROM roads_gen".to_string()));
+    // Query with settings only
+    assert_eq!(cfg.query(12), Some(&"SELECT * FROM roads_gen".to_string()));
+    // Query without SQL selects the automatic table query
+    assert_eq!(cfg.query(14), None);
+}
+
+#[test]
+fn test_fid_mode_config() {
+    use core::layer::FidMode;
+
+    let toml = r#"
+        #[[tileset.layer]]
+        name = "places"
+        fid_field = "uuid"
+        fid_mode = "hash"
+        "#;
+    let cfg = layer_from_config(toml).unwrap();
+    assert_eq!(cfg.fid_mode, FidMode::Hash);
+
+    let toml = r#"
+        #[[tileset.layer]]
+        name = "places"
+        "#;
+    let cfg = layer_from_config(toml).unwrap();
+    assert_eq!(cfg.fid_mode, FidMode::Column);
+
+    let toml = r#"
+        #[[tileset.layer]]
+        name = "places"
+        fid_mode = "uuid"
+        "#;
+    assert_eq!(
+        layer_from_config(toml).err(),
+        Some("Unknown fid_mode `uuid`".to_string())
+    );
+}
+
+#[test]
+fn test_order_config() {
+    let toml = r#"
+        #[[tileset.layer]]
+        name = "roads"
+        order_by = "z_order DESC"
+        "#;
+    let cfg = layer_from_config(toml).unwrap();
+    let order = cfg.order_by.unwrap();
+    assert_eq!(order.field, "z_order");
+    assert!(order.descending);
+
+    let toml = r#"
+        #[[tileset.layer]]
+        name = "roads"
+        order_by = "class"
+        order_values = ["residential", "primary", "motorway"]
+        "#;
+    let cfg = layer_from_config(toml).unwrap();
+    let order = cfg.order_by.unwrap();
+    assert!(!order.descending);
+    assert_eq!(order.values.len(), 3);
+
+    let toml = r#"
+        #[[tileset.layer]]
+        name = "roads"
+        order_by = "class upwards"
+        "#;
+    assert_eq!(
+        layer_from_config(toml).err(),
+        Some("Invalid order_by specification `class upwards`".to_string())
+    );
+
+    // Features are sorted by attributes after selection and renaming
+    let toml = r#"
+        #[[tileset.layer]]
+        name = "roads"
+        order_by = "name"
+        [[query]]
+        fields = ["class", "name_de"]
+        rename = { name_de = "name" }
+        "#;
+    assert!(layer_from_config(toml).is_ok());
+    let toml = r#"
+        #[[tileset.layer]]
+        name = "roads"
+        order_by = "z_order DESC"
+        [[query]]
+        minzoom = 10
+        exclude_fields = ["z_order"]
+        "#;
+    assert_eq!(
+        layer_from_config(toml).err(),
+        Some(
+            "Layer `roads`: order_by attribute `z_order` is removed or renamed in query for zoom levels 10-22"
+                .to_string()
+        )
+    );
+    let toml = r#"
+        #[[tileset.layer]]
+        name = "roads"
+        order_by = "name_de"
+        [[query]]
+        rename = { name_de = "name" }
+        "#;
+    assert!(layer_from_config(toml).is_err());
+}
+
+#[test]
+fn test_cluster_config() {
+    use core::layer::AggregateFunc;
+
+    let toml = r#"
+        #[[tileset.layer]]
+        name = "assets"
+        geometry_type = "POINT"
+        [cluster]
+        radius = 50
+        maxzoom = 11
+        [[cluster.aggregate]]
+        field = "capacity"
+        function = "sum"
+        [[cluster.aggregate]]
+        field = "capacity"
+        function = "max"
+        name = "max_capacity"
+        "#;
+    let cfg = layer_from_config(toml).unwrap();
+    let cluster = cfg.cluster.as_ref().unwrap();
+    assert_eq!(cluster.radius, 50);
+    assert_eq!(cluster.aggregates[0].name, "capacity_sum");
+    assert_eq!(cluster.aggregates[0].func, AggregateFunc::Sum);
+    assert_eq!(cluster.aggregates[1].name, "max_capacity");
+    assert!(cfg.cluster(11).is_some());
+    assert!(cfg.cluster(12).is_none());
+
+    let toml = r#"
+        #[[tileset.layer]]
+        name = "assets"
+        [cluster]
+        [[cluster.aggregate]]
+        field = "capacity"
+        function = "avg"
+        "#;
+    assert_eq!(
+        layer_from_config(toml).err(),
+        Some("Unknown cluster aggregate function `avg`".to_string())
+    );
+}
+
+#[test]
+fn test_thin_config() {
+    let toml = r#"
+        #[[tileset.layer]]
+        name = "places"
+        geometry_type = "POINT"
+        [thin]
+        distance = 16
+        rank = "population"
+        maxzoom = 8
+        "#;
+    let cfg = layer_from_config(toml).unwrap();
+    let thin = cfg.thin.as_ref().unwrap();
+    assert_eq!(thin.distance, 16);
+    assert_eq!(thin.rank, Some("population".to_string()));
+    assert!(cfg.thin(8).is_some());
+    assert!(cfg.thin(9).is_none());
+
+    let toml = r#"
+        #[[tileset.layer]]
+        name = "places"
+        [thin]
+        "#;
+    let cfg = layer_from_config(toml).unwrap();
+    assert_eq!(cfg.thin(22).unwrap().distance, 4);
+
+    let toml = r#"
+        #[[tileset.layer]]
+        name = "places"
+        [thin]
+        rank = "population"
+        [[query]]
+        fields = ["name"]
+        "#;
+    assert_eq!(
+        layer_from_config(toml).err(),
+        Some(
+            "Layer `places`: thin rank attribute `population` is removed or renamed in query for zoom levels 0-22"
+                .to_string()
+        )
+    );
+}
+
+#[test]
+fn test_params_config() {
+    use core::layer::{ParamType, ParamValue};
+    use std::collections::BTreeMap;
+
+    let toml = r#"
+        #[[tileset.layer]]
+        name = "buildings"
+        params = [{name = "year", type = "int", default = 2020},
+                  {name = "min_height", type = "float"},
+                  {name = "category", type = "text", default = "residential"}]
+        "#;
+    let cfg = layer_from_config(toml).unwrap();
+    assert_eq!(cfg.params.len(), 3);
+    assert_eq!(cfg.params[0].param_type, ParamType::Int);
+    assert_eq!(cfg.params[0].default, ParamValue::Int(2020));
+    assert_eq!(cfg.params[1].default, ParamValue::Null);
+
+    let mut params = BTreeMap::new();
+    assert_eq!(
+        cfg.param_values(&params),
+        Ok(vec![
+            ParamValue::Int(2020),
+            ParamValue::Null,
+            ParamValue::Text("residential".to_string()),
+        ])
+    );
+    params.insert("year".to_string(), "2015".to_string());
+    params.insert("min_height".to_string(), "12.5".to_string());
+    params.insert("category".to_string(), "'; DROP TABLE buildings".to_string());
+    assert_eq!(
+        cfg.param_values(&params),
+        Ok(vec![
+            ParamValue::Int(2015),
+            ParamValue::Float(12.5),
+            ParamValue::Text("'; DROP TABLE buildings".to_string()),
+        ])
+    );
+    params.insert("year".to_string(), "2015 OR 1=1".to_string());
+    assert_eq!(
+        cfg.param_values(&params),
+        Err("Invalid value `2015 OR 1=1` of parameter `year`".to_string())
+    );
+
+    let toml = r#"
+        #[[tileset.layer]]
+        name = "buildings"
+        params = [{name = "year", type = "date"}]
+        "#;
+    assert_eq!(
+        layer_from_config(toml).err(),
+        Some("Unknown parameter type `date`".to_string())
+    );
+
+    let toml = r#"
+        #[[tileset.layer]]
+        name = "buildings"
+        params = [{name = "year", type = "int", default = "2020"}]
+        "#;
+    assert_eq!(
+        layer_from_config(toml).err(),
+        Some("Invalid default value of parameter `year`".to_string())
+    );
+
+    let toml = r#"
+        #[[tileset.layer]]
+        name = "buildings"
+        params = [{name = "zoom", type = "int"}]
+        "#;
+    assert_eq!(
+        layer_from_config(toml).err(),
+        Some("Reserved parameter name `zoom`".to_string())
+    );
+}
+
 #[test]
 fn test_layers_from_config() {
     use core::config::TilesetCfg;
@@ -171,3 +540,29 @@ fn test_layers_from_config() {
         Some(" - missing field `name`".to_string())
     );
 }
+
+#[test]
+fn test_st_asmvt_config() {
+    let toml = r#"
+        #[[tileset.layer]]
+        name = "points"
+        st_asmvt = true
+        buffer_size = 10
+        "#;
+    assert!(layer_from_config(toml).unwrap().st_asmvt);
+
+    let toml = r#"
+        #[[tileset.layer]]
+        name = "points"
+        st_asmvt = true
+        min_area = 2.0
+        [cluster]
+        radius = 20
+        [[query]]
+        rename = { name = "label" }
+        "#;
+    assert_eq!(
+        layer_from_config(toml).err(),
+        Some("Layer `points`: rename, cluster, min_area not supported with `st_asmvt`".to_string())
+    );
+}
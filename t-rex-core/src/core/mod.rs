@@ -7,6 +7,7 @@
 mod enum_serializer;
 pub mod config;
 pub mod feature;
+pub mod geojson;
 pub mod geom;
 pub mod grid;
 pub mod layer;
@@ -17,6 +18,8 @@ pub use self::config::{parse_config, read_config, ApplicationCfg, Config};
 #[cfg(test)]
 mod config_test;
 #[cfg(test)]
+mod geojson_test;
+#[cfg(test)]
 mod geom_test;
 #[cfg(test)]
 mod grid_test;
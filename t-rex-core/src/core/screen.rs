@@ -7,7 +7,7 @@
 
 use std::vec::Vec;
 
-#[derive(PartialEq, Debug)]
+#[derive(PartialEq, Eq, Hash, Clone, Copy, Debug)]
 pub struct Point {
     pub x: i32,
     pub y: i32,
@@ -19,27 +19,88 @@ impl Point {
     }
 }
 
-#[derive(PartialEq, Debug)]
+#[derive(PartialEq, Clone, Debug)]
 pub struct MultiPoint {
     pub points: Vec<Point>,
 }
 
-#[derive(PartialEq, Debug)]
+#[derive(PartialEq, Clone, Debug)]
 pub struct LineString {
     pub points: Vec<Point>,
 }
 
-#[derive(PartialEq, Debug)]
+#[derive(PartialEq, Clone, Debug)]
 pub struct MultiLineString {
     pub lines: Vec<LineString>,
 }
 
-#[derive(PartialEq, Debug)]
+#[derive(PartialEq, Clone, Debug)]
 pub struct Polygon {
     pub rings: Vec<LineString>,
 }
 
-#[derive(PartialEq, Debug)]
+#[derive(PartialEq, Clone, Debug)]
 pub struct MultiPolygon {
     pub polygons: Vec<Polygon>,
 }
+
+/// Screen geometry of any type
+#[derive(PartialEq, Clone, Debug)]
+pub enum Geometry {
+    Point(Point),
+    MultiPoint(MultiPoint),
+    LineString(LineString),
+    MultiLineString(MultiLineString),
+    Polygon(Polygon),
+    MultiPolygon(MultiPolygon),
+}
+
+/// Signed area of a ring (positive for exterior rings in screen coordinates)
+pub fn ring_area(ring: &LineString) -> f64 {
+    let pts = &ring.points;
+    if pts.len() < 3 {
+        return 0.0;
+    }
+    let mut sum = 0.0;
+    for i in 0..pts.len() {
+        let p1 = &pts[i];
+        let p2 = &pts[(i + 1) % pts.len()];
+        sum += p1.x as f64 * p2.y as f64 - p2.x as f64 * p1.y as f64;
+    }
+    sum / 2.0
+}
+
+fn polygon_area(polygon: &Polygon) -> f64 {
+    let mut rings = polygon.rings.iter().map(|r| ring_area(r).abs());
+    let exterior = rings.next().unwrap_or(0.0);
+    exterior - rings.sum::<f64>()
+}
+
+/// Area of polygons (zero for other geometry types)
+pub fn geometry_area(geom: &Geometry) -> f64 {
+    match geom {
+        &Geometry::Polygon(ref p) => polygon_area(p),
+        &Geometry::MultiPolygon(ref mp) => mp.polygons.iter().map(polygon_area).sum(),
+        _ => 0.0,
+    }
+}
+
+fn line_length(line: &LineString) -> f64 {
+    line.points
+        .windows(2)
+        .map(|w| {
+            let dx = (w[1].x - w[0].x) as f64;
+            let dy = (w[1].y - w[0].y) as f64;
+            (dx * dx + dy * dy).sqrt()
+        })
+        .sum()
+}
+
+/// Length of lines (zero for other geometry types)
+pub fn geometry_length(geom: &Geometry) -> f64 {
+    match geom {
+        &Geometry::LineString(ref l) => line_length(l),
+        &Geometry::MultiLineString(ref ml) => ml.lines.iter().map(line_length).sum(),
+        _ => 0.0,
+    }
+}
@@ -0,0 +1,737 @@
+//
+// Copyright (c) Pirmin Kalberer. All rights reserved.
+// Licensed under the MIT License. See LICENSE file in the project root for full license information.
+//
+
+//! CSV/TSV file datasource with in-memory spatial index
+
+use core::config::DatasourceCfg;
+use core::feature::{Feature, FeatureAttr, FeatureAttrValType};
+use core::geom::{self, GeometryType};
+use core::grid::Extent;
+use core::grid::Grid;
+use core::layer::Layer;
+use core::Config;
+use datasource::datasource::{attribute_fid, layer_bbox, FeatureCount};
+use datasource::geojson_file::geometry_extent;
+use datasource::rtree::PackedRTree;
+use datasource::transform::Transform;
+use datasource::DatasourceInput;
+use std::collections::HashMap;
+use std::fs::{self, File};
+use std::io::Read;
+use std::path::Path;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Mutex, RwLock, TryLockError};
+use std::thread;
+use std::time::{Duration, Instant, SystemTime};
+
+/// Node size of spatial index
+const INDEX_NODE_SIZE: usize = 16;
+
+/// Column names used for coordinates if not configured
+const X_FIELDS: &[&str] = &["x", "lon", "lng", "long", "longitude", "easting"];
+const Y_FIELDS: &[&str] = &["y", "lat", "latitude", "northing"];
+const WKT_FIELDS: &[&str] = &["wkt", "geom", "geometry", "the_geom"];
+
+/// Records of CSV text. Fields may be quoted with `"`.
+pub fn parse_csv(text: &str, delimiter: char) -> Vec<Vec<String>> {
+    let mut records = Vec::new();
+    let mut record = Vec::new();
+    let mut field = String::new();
+    let mut quoted = false;
+    let mut chars = text.chars().peekable();
+    while let Some(c) = chars.next() {
+        if quoted {
+            match c {
+                '"' if chars.peek() == Some(&'"') => {
+                    chars.next();
+                    field.push('"');
+                }
+                '"' => quoted = false,
+                c => field.push(c),
+            }
+        } else {
+            match c {
+                '"' if field.is_empty() => quoted = true,
+                '\r' => {}
+                '\n' => {
+                    record.push(field.clone());
+                    field.clear();
+                    records.push(record.clone());
+                    record.clear();
+                }
+                c if c == delimiter => {
+                    record.push(field.clone());
+                    field.clear();
+                }
+                c => field.push(c),
+            }
+        }
+    }
+    if !field.is_empty() || !record.is_empty() {
+        record.push(field);
+        records.push(record);
+    }
+    records
+        .into_iter()
+        .filter(|r| !(r.len() == 1 && r[0].trim().is_empty()))
+        .collect()
+}
+
+/// Nested coordinate lists of WKT text
+#[derive(Debug)]
+enum WktNode {
+    Coord(f64, f64),
+    List(Vec<WktNode>),
+}
+
+fn wkt_list(tokens: &[&str], pos: &mut usize) -> Result<WktNode, String> {
+    // Opening parenthesis already consumed
+    let mut items = Vec::new();
+    let mut coord = Vec::new();
+    while *pos < tokens.len() {
+        let token = tokens[*pos];
+        *pos += 1;
+        match token {
+            "(" => items.push(wkt_list(tokens, pos)?),
+            "," | ")" => {
+                if !coord.is_empty() {
+                    if coord.len() < 2 {
+                        return Err("Invalid WKT coordinate".to_string());
+                    }
+                    items.push(WktNode::Coord(coord[0], coord[1]));
+                    coord.clear();
+                }
+                if token == ")" {
+                    return Ok(WktNode::List(items));
+                }
+            }
+            number => coord.push(number
+                .parse::<f64>()
+                .map_err(|_| format!("Invalid WKT number '{}'", number))?),
+        }
+    }
+    Err("Unbalanced parentheses in WKT".to_string())
+}
+
+fn wkt_points(node: &WktNode, srid: Option<i32>) -> Result<Vec<geom::Point>, String> {
+    match node {
+        &WktNode::List(ref items) => items
+            .iter()
+            .map(|item| match item {
+                &WktNode::Coord(x, y) => Ok(geom::Point::new(x, y, srid)),
+                // MULTIPOINT ((x y), ...)
+                &WktNode::List(ref inner) => match inner.first() {
+                    Some(&WktNode::Coord(x, y)) => Ok(geom::Point::new(x, y, srid)),
+                    _ => Err("Invalid WKT point".to_string()),
+                },
+            })
+            .collect(),
+        &WktNode::Coord(x, y) => Ok(vec![geom::Point::new(x, y, srid)]),
+    }
+}
+
+fn wkt_lines(node: &WktNode, srid: Option<i32>) -> Result<Vec<geom::LineString>, String> {
+    match node {
+        &WktNode::List(ref items) => items
+            .iter()
+            .map(|item| {
+                Ok(geom::LineString {
+                    points: wkt_points(item, srid)?,
+                    srid: srid,
+                })
+            })
+            .collect(),
+        _ => Err("Invalid WKT line list".to_string()),
+    }
+}
+
+/// Geometry of WKT or EWKT text
+pub fn wkt_to_geometry(wkt: &str, srid: Option<i32>) -> Result<GeometryType, String> {
+    // Strip EWKT SRID prefix
+    let wkt = match wkt.find(';') {
+        Some(pos) if wkt[..pos].trim().to_uppercase().starts_with("SRID=") => &wkt[pos + 1..],
+        _ => wkt,
+    };
+    let spaced = wkt.replace("(", " ( ")
+        .replace(")", " ) ")
+        .replace(",", " , ");
+    let tokens: Vec<&str> = spaced.split_whitespace().collect();
+    let geom_type = tokens
+        .first()
+        .ok_or("Empty WKT".to_string())?
+        .to_uppercase();
+    // Skip dimension qualifiers (Z, M, ZM)
+    let start = match tokens.iter().position(|&t| t == "(") {
+        Some(pos) => pos,
+        None => return Err(format!("Empty or invalid WKT geometry {}", geom_type)),
+    };
+    let mut pos = start + 1;
+    let node = wkt_list(&tokens, &mut pos)?;
+    match geom_type.as_str() {
+        "POINT" => wkt_points(&node, srid)?
+            .into_iter()
+            .next()
+            .map(GeometryType::Point)
+            .ok_or("Empty WKT point".to_string()),
+        "MULTIPOINT" => Ok(GeometryType::MultiPoint(geom::MultiPoint {
+            points: wkt_points(&node, srid)?,
+            srid: srid,
+        })),
+        "LINESTRING" => Ok(GeometryType::LineString(geom::LineString {
+            points: wkt_points(&node, srid)?,
+            srid: srid,
+        })),
+        "MULTILINESTRING" => Ok(GeometryType::MultiLineString(geom::MultiLineString {
+            lines: wkt_lines(&node, srid)?,
+            srid: srid,
+        })),
+        "POLYGON" => Ok(GeometryType::Polygon(geom::Polygon {
+            rings: wkt_lines(&node, srid)?,
+            srid: srid,
+        })),
+        "MULTIPOLYGON" => match node {
+            WktNode::List(ref polygons) => Ok(GeometryType::MultiPolygon(geom::MultiPolygon {
+                polygons: polygons
+                    .iter()
+                    .map(|p| {
+                        Ok(geom::Polygon {
+                            rings: wkt_lines(p, srid)?,
+                            srid: srid,
+                        })
+                    })
+                    .collect::<Result<_, String>>()?,
+                srid: srid,
+            })),
+            _ => Err("Invalid WKT multipolygon".to_string()),
+        },
+        t => Err(format!("Unsupported geometry type {}", t)),
+    }
+}
+
+/// Attribute type of a CSV column
+#[derive(PartialEq, Clone, Copy, Debug)]
+enum ColumnType {
+    String,
+    Integer,
+    Double,
+    Bool,
+}
+
+impl ColumnType {
+    fn from_name(name: &str) -> Result<ColumnType, String> {
+        match name {
+            "string" => Ok(ColumnType::String),
+            "integer" | "int" => Ok(ColumnType::Integer),
+            "double" | "float" | "number" => Ok(ColumnType::Double),
+            "bool" | "boolean" => Ok(ColumnType::Bool),
+            t => Err(format!("Unknown column type '{}'", t)),
+        }
+    }
+    fn name(&self) -> &'static str {
+        match self {
+            &ColumnType::String => "string",
+            &ColumnType::Integer => "integer",
+            &ColumnType::Double => "double",
+            &ColumnType::Bool => "bool",
+        }
+    }
+    fn parse_bool(value: &str) -> Option<bool> {
+        match value.to_lowercase().as_str() {
+            "true" => Some(true),
+            "false" => Some(false),
+            _ => None,
+        }
+    }
+    /// Attribute value. Empty values and parse errors are missing values.
+    fn value(&self, value: &str) -> Option<FeatureAttrValType> {
+        let value = value.trim();
+        if value.is_empty() {
+            return None;
+        }
+        match self {
+            &ColumnType::String => Some(FeatureAttrValType::String(value.to_string())),
+            &ColumnType::Integer => value.parse().ok().map(FeatureAttrValType::Int),
+            &ColumnType::Double => value.parse().ok().map(FeatureAttrValType::Double),
+            &ColumnType::Bool => Self::parse_bool(value).map(FeatureAttrValType::Bool),
+        }
+    }
+    /// Most specific type of all non-empty values
+    fn detect<'a, I: Iterator<Item = &'a str>>(values: I) -> ColumnType {
+        let mut types = vec![ColumnType::Integer, ColumnType::Double, ColumnType::Bool];
+        let mut empty = true;
+        for value in values.map(|v| v.trim()).filter(|v| !v.is_empty()) {
+            empty = false;
+            types.retain(|t| match t {
+                &ColumnType::Integer => value.parse::<i64>().is_ok(),
+                &ColumnType::Double => value.parse::<f64>().is_ok(),
+                &ColumnType::Bool => Self::parse_bool(value).is_some(),
+                &ColumnType::String => true,
+            });
+        }
+        if empty {
+            return ColumnType::String;
+        }
+        types.first().cloned().unwrap_or(ColumnType::String)
+    }
+}
+
+/// Geometry source columns
+#[derive(Clone, Debug)]
+pub enum GeometryColumns {
+    XY(String, String),
+    Wkt(String),
+}
+
+/// CSV layout and geometry settings
+#[derive(Clone, Debug)]
+pub struct CsvOptions {
+    pub delimiter: char,
+    /// Geometry columns detected by name if not set
+    pub geometry: Option<GeometryColumns>,
+    pub srid: i32,
+    /// Attribute types by column name. Detected from values if not set.
+    pub types: HashMap<String, String>,
+}
+
+impl CsvOptions {
+    /// Default options for file (tab delimited for .tsv/.tab files)
+    pub fn for_path(path: &str) -> CsvOptions {
+        let lower = path.to_lowercase();
+        CsvOptions {
+            delimiter: if lower.ends_with(".tsv") || lower.ends_with(".tab") {
+                '\t'
+            } else {
+                ','
+            },
+            geometry: None,
+            srid: 4326,
+            types: HashMap::new(),
+        }
+    }
+}
+
+struct CsvFeature {
+    fid: u64,
+    attributes: Vec<FeatureAttr>,
+    geometry: GeometryType,
+}
+
+/// Features of a CSV file with spatial index
+struct CsvData {
+    /// Modification time and size of the loaded file
+    file_state: Option<(SystemTime, u64)>,
+    columns: Vec<(String, ColumnType)>,
+    features: Vec<CsvFeature>,
+    index: PackedRTree,
+}
+
+fn file_state(path: &str) -> Option<(SystemTime, u64)> {
+    let metadata = fs::metadata(path).ok()?;
+    Some((metadata.modified().ok()?, metadata.len()))
+}
+
+fn find_column(header: &[String], name: &str) -> Result<usize, String> {
+    header
+        .iter()
+        .position(|col| col == name)
+        .ok_or(format!("Column '{}' not found", name))
+}
+
+/// Column positions of geometry, detected by name if not configured
+fn geometry_positions(
+    header: &[String],
+    geometry: &Option<GeometryColumns>,
+) -> Result<(Option<usize>, Option<(usize, usize)>), String> {
+    let detect = |names: &[&str]| {
+        header
+            .iter()
+            .position(|col| names.contains(&col.to_lowercase().as_str()))
+    };
+    match geometry {
+        &Some(GeometryColumns::Wkt(ref wkt)) => Ok((Some(find_column(header, wkt)?), None)),
+        &Some(GeometryColumns::XY(ref x, ref y)) => Ok((
+            None,
+            Some((find_column(header, x)?, find_column(header, y)?)),
+        )),
+        &None => match (detect(X_FIELDS), detect(Y_FIELDS), detect(WKT_FIELDS)) {
+            (Some(x), Some(y), _) => Ok((None, Some((x, y)))),
+            (_, _, Some(wkt)) => Ok((Some(wkt), None)),
+            _ => Err("No coordinate or WKT columns found".to_string()),
+        },
+    }
+}
+
+impl CsvData {
+    fn read(path: &str, options: &CsvOptions) -> Result<CsvData, String> {
+        let file_state = file_state(path);
+        let mut text = String::new();
+        File::open(path)
+            .and_then(|mut f| f.read_to_string(&mut text))
+            .map_err(|e| format!("{}: {}", path, e))?;
+        let mut data = Self::parse(&text, options).map_err(|e| format!("{}: {}", path, e))?;
+        data.file_state = file_state;
+        Ok(data)
+    }
+    fn parse(text: &str, options: &CsvOptions) -> Result<CsvData, String> {
+        let mut records = parse_csv(text.trim_start_matches('\u{feff}'), options.delimiter);
+        if records.is_empty() {
+            return Err("Missing header".to_string());
+        }
+        let header: Vec<String> = records
+            .remove(0)
+            .into_iter()
+            .map(|col| col.trim().to_string())
+            .collect();
+        let (wkt_pos, xy_pos) = geometry_positions(&header, &options.geometry)?;
+        let mut columns = Vec::new();
+        for (i, name) in header.iter().enumerate() {
+            if Some(i) == wkt_pos || xy_pos.map_or(false, |(x, y)| i == x || i == y) {
+                continue;
+            }
+            let column_type = match options.types.get(name) {
+                Some(type_name) => ColumnType::from_name(type_name)?,
+                None => ColumnType::detect(
+                    records
+                        .iter()
+                        .filter_map(|r| r.get(i))
+                        .map(|v| v.as_str()),
+                ),
+            };
+            columns.push((i, name.clone(), column_type));
+        }
+
+        let srid = Some(options.srid);
+        let mut features = Vec::new();
+        let mut extents = Vec::new();
+        for (row, record) in records.iter().enumerate() {
+            let field = |i: usize| record.get(i).map_or("", |v| v.trim());
+            let geometry = match (wkt_pos, xy_pos) {
+                (Some(wkt), _) => wkt_to_geometry(field(wkt), srid),
+                (_, Some((x, y))) => match (field(x).parse::<f64>(), field(y).parse::<f64>()) {
+                    (Ok(x), Ok(y)) => Ok(GeometryType::Point(geom::Point::new(x, y, srid))),
+                    _ => Err("Invalid coordinates".to_string()),
+                },
+                _ => unreachable!(),
+            };
+            let geometry = match geometry {
+                Ok(geometry) => geometry,
+                Err(e) => {
+                    // Header is line 1
+                    warn!("Skipping record #{}: {}", row + 2, e);
+                    continue;
+                }
+            };
+            let extent = match geometry_extent(&geometry) {
+                Some(extent) => extent,
+                None => continue,
+            };
+            let attributes = columns
+                .iter()
+                .filter_map(|&(i, ref name, column_type)| {
+                    column_type.value(field(i)).map(|value| FeatureAttr {
+                        key: name.clone(),
+                        value: value,
+                    })
+                })
+                .collect();
+            features.push(CsvFeature {
+                fid: row as u64 + 1,
+                attributes: attributes,
+                geometry: geometry,
+            });
+            extents.push(extent);
+        }
+        Ok(CsvData {
+            file_state: None,
+            columns: columns
+                .into_iter()
+                .map(|(_, name, column_type)| (name, column_type))
+                .collect(),
+            features: features,
+            index: PackedRTree::build(&extents, INDEX_NODE_SIZE),
+        })
+    }
+}
+
+struct CsvRecord<'a> {
+    layer: &'a Layer,
+    feature: &'a CsvFeature,
+    grid_srid: i32,
+    transform: Transform,
+}
+
+impl<'a> Feature for CsvRecord<'a> {
+    fn fid(&self) -> Option<u64> {
+        match self.layer.fid_field {
+            Some(ref fid_field) => attribute_fid(&self.feature.attributes, fid_field),
+            None => Some(self.feature.fid),
+        }
+    }
+    fn attributes(&self) -> Vec<FeatureAttr> {
+        self.feature.attributes.clone()
+    }
+    fn geometry(&self) -> Result<GeometryType, String> {
+        self.transform
+            .geometry(&self.feature.geometry, Some(self.grid_srid))
+    }
+}
+
+/// Minimal interval between checks for file modifications
+const RELOAD_CHECK_INTERVAL_MS: u64 = 1000;
+
+pub struct CsvDatasource {
+    pub path: String,
+    pub options: CsvOptions,
+    data: Arc<RwLock<Option<Arc<CsvData>>>>,
+    /// Time of last check for file modifications
+    checked: Mutex<Option<Instant>>,
+    /// Set while the file is reloaded in background
+    reloading: Arc<AtomicBool>,
+}
+
+impl CsvDatasource {
+    pub fn new(path: &str) -> CsvDatasource {
+        CsvDatasource::with_options(path, CsvOptions::for_path(path))
+    }
+    pub fn with_options(path: &str, options: CsvOptions) -> CsvDatasource {
+        CsvDatasource {
+            path: path.to_string(),
+            options: options,
+            data: Arc::new(RwLock::new(None)),
+            checked: Mutex::new(None),
+            reloading: Arc::new(AtomicBool::new(false)),
+        }
+    }
+    /// Layer name derived from file name
+    fn layer_name(&self) -> String {
+        Path::new(&self.path)
+            .file_stem()
+            .map_or("csv".to_string(), |stem| stem.to_string_lossy().to_string())
+    }
+    /// Apply function on loaded data or read file for a single call
+    fn with_data<T, F>(&self, f: F) -> Option<T>
+    where
+        F: Fn(&CsvData) -> T,
+    {
+        if let Some(data) = self.loaded_data() {
+            return Some(f(&data));
+        }
+        match CsvData::read(&self.path, &self.options) {
+            Ok(ref data) => Some(f(data)),
+            Err(e) => {
+                error!("{}", e);
+                None
+            }
+        }
+    }
+    /// Currently loaded data
+    fn loaded_data(&self) -> Option<Arc<CsvData>> {
+        self.data.read().unwrap_or_else(|e| e.into_inner()).clone()
+    }
+    /// Reload data in background if file was modified since loading.
+    /// Only one caller checks at a time and at most once per interval, all use loaded data.
+    fn reload_modified(&self) {
+        let mut checked = match self.checked.try_lock() {
+            Ok(checked) => checked,
+            Err(TryLockError::Poisoned(e)) => e.into_inner(),
+            Err(TryLockError::WouldBlock) => return,
+        };
+        let interval = Duration::from_millis(RELOAD_CHECK_INTERVAL_MS);
+        if checked.map_or(false, |time| time.elapsed() < interval) {
+            return;
+        }
+        *checked = Some(Instant::now());
+        let current = file_state(&self.path);
+        if current.is_none() || self.loaded_data().map(|d| d.file_state) == Some(current) {
+            return;
+        }
+        if self.reloading.swap(true, Ordering::SeqCst) {
+            return;
+        }
+        let path = self.path.clone();
+        let options = self.options.clone();
+        let data = self.data.clone();
+        let reloading = self.reloading.clone();
+        // Parse file outside of the request and without blocking readers of loaded data
+        thread::spawn(move || {
+            match CsvData::read(&path, &options) {
+                Ok(new_data) => {
+                    info!("{}: {} features reloaded", path, new_data.features.len());
+                    *data.write().unwrap_or_else(|e| e.into_inner()) = Some(Arc::new(new_data));
+                }
+                // Keep previous data, e.g. while file is being written
+                Err(e) => error!("{}", e),
+            }
+            reloading.store(false, Ordering::SeqCst);
+        });
+    }
+}
+
+impl DatasourceInput for CsvDatasource {
+    /// New instance with features loaded into memory
+    fn connected(&self) -> Result<CsvDatasource, String> {
+        let data = CsvData::read(&self.path, &self.options)?;
+        info!("{}: {} features loaded", self.path, data.features.len());
+        Ok(CsvDatasource {
+            path: self.path.clone(),
+            options: self.options.clone(),
+            data: Arc::new(RwLock::new(Some(Arc::new(data)))),
+            checked: Mutex::new(Some(Instant::now())),
+            reloading: Arc::new(AtomicBool::new(false)),
+        })
+    }
+    fn detect_layers(&self, _detect_geometry_types: bool) -> Vec<Layer> {
+        let mut layer = Layer::new(&self.layer_name());
+        layer.table_name = Some(layer.name.clone());
+        layer.srid = Some(self.options.srid);
+        layer.geometry_type = self.with_data(|data| {
+            let points = data.features.iter().all(|f| match f.geometry {
+                GeometryType::Point(_) => true,
+                _ => false,
+            });
+            let geometry_type = if points { "POINT" } else { "GEOMETRY" };
+            geometry_type.to_string()
+        });
+        vec![layer]
+    }
+    /// Return column names and configured or detected types
+    fn detect_data_columns(&self, _layer: &Layer, _sql: Option<&String>) -> Vec<(String, String)> {
+        self.with_data(|data| {
+            data.columns
+                .iter()
+                .map(|&(ref name, column_type)| (name.clone(), column_type.name().to_string()))
+                .collect()
+        }).unwrap_or(Vec::new())
+    }
+    /// Projected extent
+    fn extent_from_wgs84(&self, extent: &Extent, dest_srid: i32) -> Option<Extent> {
+        Transform::new(4326, dest_srid)
+            .map(|t| t.extent(extent))
+            .map_err(|e| warn!("{}", e))
+            .ok()
+    }
+    /// Detect extent of layer (in WGS84)
+    fn layer_extent(&self, layer: &Layer) -> Option<Extent> {
+        let srid = layer.srid.unwrap_or(self.options.srid);
+        let transform = Transform::new(srid, 4326)
+            .map_err(|e| warn!("Layer '{}': {}", layer.name, e))
+            .ok()?;
+        self.with_data(|data| data.index.extent())
+            .and_then(|extent| extent)
+            .map(|extent| transform.extent(&extent))
+    }
+    fn prepare_queries(&mut self, layer: &Layer, grid_srid: i32) {
+        let srid = layer.srid.unwrap_or(self.options.srid);
+        if srid != grid_srid {
+            info!(
+                "Layer '{}': Reprojecting geometry from SRID {} to {}",
+                layer.name, srid, grid_srid
+            );
+        }
+    }
+    fn retrieve_features<F>(
+        &self,
+        layer: &Layer,
+        extent: &Extent,
+        zoom: u8,
+        grid: &Grid,
+        mut read: F,
+    ) where
+        F: FnMut(&Feature),
+    {
+        self.reload_modified();
+        let data = match self.loaded_data() {
+            Some(data) => data,
+            None => return,
+        };
+        let srid = layer.srid.unwrap_or(self.options.srid);
+        let transform = match Transform::new(srid, grid.srid) {
+            Ok(transform) => transform,
+            Err(e) => {
+                error!("Layer '{}': {}", layer.name, e);
+                return;
+            }
+        };
+        let bbox = layer_bbox(layer, extent, zoom, grid, srid);
+        let mut count = FeatureCount::new(layer, zoom);
+        for idx in data.index.search(&bbox) {
+            let feature = CsvRecord {
+                layer: layer,
+                feature: &data.features[idx as usize],
+                grid_srid: grid.srid,
+                transform: transform,
+            };
+            read(&feature);
+            if !count.add() {
+                break;
+            }
+        }
+    }
+}
+
+impl<'a> Config<'a, DatasourceCfg> for CsvDatasource {
+    fn from_config(ds_cfg: &DatasourceCfg) -> Result<Self, String> {
+        let path = ds_cfg.csv.as_ref().or(ds_cfg.path.as_ref()).unwrap();
+        let mut options = CsvOptions::for_path(path);
+        if let Some(ref delimiter) = ds_cfg.delimiter {
+            let mut chars = delimiter.chars();
+            match (chars.next(), chars.next()) {
+                (Some(c), None) => options.delimiter = c,
+                _ => return Err(format!("Invalid delimiter '{}'", delimiter)),
+            }
+        }
+        options.geometry = match (&ds_cfg.wkt_field, &ds_cfg.x_field, &ds_cfg.y_field) {
+            (&Some(ref wkt), _, _) => Some(GeometryColumns::Wkt(wkt.clone())),
+            (_, &Some(ref x), &Some(ref y)) => Some(GeometryColumns::XY(x.clone(), y.clone())),
+            (_, &None, &None) => None,
+            _ => return Err("Both x_field and y_field required".to_string()),
+        };
+        if let Some(srid) = ds_cfg.srid {
+            options.srid = srid;
+        }
+        if let Some(ref types) = ds_cfg.types {
+            for (name, type_name) in types {
+                ColumnType::from_name(type_name)?;
+                options.types.insert(name.clone(), type_name.clone());
+            }
+        }
+        Ok(CsvDatasource::with_options(path, options))
+    }
+
+    fn gen_config() -> String {
+        let toml = r#"
+[[datasource]]
+name = "csv"
+# CSV or TSV file loaded into memory and reloaded on changes
+csv = "<filename>"
+# Coordinate columns (default: detected by column name) or WKT geometry column
+#x_field = "lon"
+#y_field = "lat"
+#wkt_field = "wkt"
+#srid = 4326
+#delimiter = ";"
+# Attribute types (string, integer, double, bool). Default: detected from values
+#types = { population = "integer" }
+"#;
+        toml.to_string()
+    }
+    fn gen_runtime_config(&self) -> String {
+        let geometry = match self.options.geometry {
+            Some(GeometryColumns::XY(ref x, ref y)) => {
+                format!("x_field = \"{}\"\ny_field = \"{}\"\n", x, y)
+            }
+            Some(GeometryColumns::Wkt(ref wkt)) => format!("wkt_field = \"{}\"\n", wkt),
+            None => String::new(),
+        };
+        format!(
+            r#"
+[[datasource]]
+csv = "{}"
+{}srid = {}
+"#,
+            self.path, geometry, self.options.srid
+        )
+    }
+}
@@ -0,0 +1,186 @@
+//
+// Copyright (c) Pirmin Kalberer. All rights reserved.
+// Licensed under the MIT License. See LICENSE file in the project root for full license information.
+//
+
+use core::feature::FeatureAttrValType;
+use core::geom::GeometryType;
+use core::grid::{Extent, Grid};
+use core::layer::Layer;
+use datasource::csv_file::{parse_csv, wkt_to_geometry, CsvDatasource, CsvOptions,
+                           GeometryColumns};
+use datasource::DatasourceInput;
+use std::env;
+use std::fs::File;
+use std::io::Write;
+use std::thread;
+use std::time::Duration;
+
+fn write_file(name: &str, content: &str) -> String {
+    let path = env::temp_dir().join(name);
+    File::create(&path)
+        .unwrap()
+        .write_all(content.as_bytes())
+        .unwrap();
+    path.to_str().unwrap().to_string()
+}
+
+const PLACES: &str = "name,lon,lat,population,capital
+Zurich,8.5,47.3,400000,false
+\"St. Gallen, SG\",9.5,47.5,75000,
+Bern,7.4,46.9,130000,true
+";
+
+fn retrieve_names(ds: &CsvDatasource, layer: &Layer, extent: &Extent) -> Vec<String> {
+    let grid = Grid::wgs84();
+    let mut names = Vec::new();
+    ds.retrieve_features(layer, extent, 10, &grid, |feat| {
+        let attrs = feat.attributes();
+        match attrs.iter().find(|attr| attr.key == "name").unwrap().value {
+            FeatureAttrValType::String(ref name) => names.push(name.clone()),
+            _ => panic!("String expected"),
+        }
+        assert!(feat.geometry().is_ok());
+    });
+    names
+}
+
+#[test]
+fn test_parse_csv() {
+    assert_eq!(
+        parse_csv("a;b\n\"x;\"\"y\"\"\";2\r\n\n", ';'),
+        vec![vec!["a", "b"], vec!["x;\"y\"", "2"]]
+    );
+    assert_eq!(parse_csv("a\tb\n1\t", '\t'), vec![vec!["a", "b"], vec!["1", ""]]);
+}
+
+#[test]
+fn test_wkt() {
+    match wkt_to_geometry("POINT (8.5 47.3)", Some(4326)) {
+        Ok(GeometryType::Point(p)) => assert_eq!((p.x, p.y, p.srid), (8.5, 47.3, Some(4326))),
+        _ => panic!("Point expected"),
+    }
+    match wkt_to_geometry("SRID=4326;MULTIPOINT Z ((1 2 3), (3 4 5))", None) {
+        Ok(GeometryType::MultiPoint(g)) => assert_eq!(g.points[1].y, 4.0),
+        _ => panic!("MultiPoint expected"),
+    }
+    match wkt_to_geometry("POLYGON((0 0,10 0,10 10,0 0),(1 1,2 1,2 2,1 1))", None) {
+        Ok(GeometryType::Polygon(g)) => assert_eq!(g.rings[1].points.len(), 4),
+        _ => panic!("Polygon expected"),
+    }
+    match wkt_to_geometry("MULTIPOLYGON (((0 0, 1 0, 1 1, 0 0)), ((5 5, 6 5, 6 6, 5 5)))", None) {
+        Ok(GeometryType::MultiPolygon(g)) => assert_eq!(g.polygons[1].rings[0].points[0].x, 5.0),
+        _ => panic!("MultiPolygon expected"),
+    }
+    assert!(wkt_to_geometry("POINT EMPTY", None).is_err());
+    assert!(wkt_to_geometry("LINESTRING (0 0, 1 x)", None).is_err());
+    assert!(wkt_to_geometry("LINESTRING (0 0, 1 1", None).is_err());
+}
+
+#[test]
+fn test_detect_layers() {
+    let ds = CsvDatasource::new(&write_file("t_rex_places.csv", PLACES));
+    let layers = ds.detect_layers(true);
+    assert_eq!(layers.len(), 1);
+    assert_eq!(layers[0].name, "t_rex_places");
+    assert_eq!(layers[0].geometry_type, Some("POINT".to_string()));
+    assert_eq!(layers[0].srid, Some(4326));
+    assert_eq!(
+        ds.detect_data_columns(&layers[0], None),
+        vec![
+            ("name".to_string(), "string".to_string()),
+            ("population".to_string(), "integer".to_string()),
+            ("capital".to_string(), "bool".to_string()),
+        ]
+    );
+    let extent = ds.layer_extent(&layers[0]).unwrap();
+    assert_eq!(
+        (extent.minx, extent.miny, extent.maxx, extent.maxy),
+        (7.4, 46.9, 9.5, 47.5)
+    );
+
+    let mut options = CsvOptions::for_path("places.csv");
+    options.types.insert("population".to_string(), "string".to_string());
+    let ds = CsvDatasource::with_options(&write_file("t_rex_types.csv", PLACES), options);
+    assert_eq!(
+        ds.detect_data_columns(&layers[0], None)[1],
+        ("population".to_string(), "string".to_string())
+    );
+}
+
+#[test]
+fn test_retrieve_features() {
+    let ds = CsvDatasource::new(&write_file("t_rex_retrieve.csv", PLACES)).connected().unwrap();
+    let mut layer = Layer::new("places");
+    let extent = Extent {
+        minx: 8.0,
+        miny: 47.0,
+        maxx: 10.0,
+        maxy: 48.0,
+    };
+    assert_eq!(
+        retrieve_names(&ds, &layer, &extent),
+        vec!["Zurich".to_string(), "St. Gallen, SG".to_string()]
+    );
+
+    layer.query_limit = Some(1);
+    assert_eq!(retrieve_names(&ds, &layer, &extent).len(), 1);
+}
+
+#[test]
+fn test_wkt_tsv() {
+    let tsv = "id\tname\twkt\n1\tLake\tPOLYGON ((8.5 47.2, 8.7 47.2, 8.7 47.3, 8.5 47.2))\n";
+    let mut options = CsvOptions::for_path("lakes.tsv");
+    options.geometry = Some(GeometryColumns::Wkt("wkt".to_string()));
+    let ds = CsvDatasource::with_options(&write_file("t_rex_lakes.tsv", tsv), options);
+    let layers = ds.detect_layers(true);
+    assert_eq!(layers[0].geometry_type, Some("GEOMETRY".to_string()));
+    let ds = ds.connected().unwrap();
+    let mut layer = Layer::new("lakes");
+    layer.fid_field = Some("id".to_string());
+    let grid = Grid::web_mercator();
+    let mut fids = Vec::new();
+    ds.retrieve_features(&layer, &grid.tile_extent(0, 0, 0), 0, &grid, |feat| {
+        match feat.geometry() {
+            Ok(GeometryType::Polygon(p)) => {
+                assert!(p.rings[0].points[0].x > 940000.0);
+                assert_eq!(p.srid, Some(3857));
+            }
+            _ => panic!("Polygon expected"),
+        }
+        fids.push(feat.fid());
+    });
+    assert_eq!(fids, vec![Some(1)]);
+}
+
+#[test]
+fn test_reload() {
+    let path = write_file("t_rex_reload.csv", "name,x,y\nA,1,1\n");
+    let ds = CsvDatasource::new(&path).connected().unwrap();
+    let layer = Layer::new("reload");
+    let extent = Extent {
+        minx: 0.0,
+        miny: 0.0,
+        maxx: 10.0,
+        maxy: 10.0,
+    };
+    assert_eq!(retrieve_names(&ds, &layer, &extent), vec!["A".to_string()]);
+    write_file("t_rex_reload.csv", "name,x,y\nA,1,1\nBB,2,2\n");
+    // Modifications are checked at most once per second
+    assert_eq!(retrieve_names(&ds, &layer, &extent), vec!["A".to_string()]);
+    thread::sleep(Duration::from_millis(1100));
+    // Reloaded in background, requests meanwhile return the loaded data
+    let mut names = retrieve_names(&ds, &layer, &extent);
+    for _ in 0..100 {
+        if names.len() == 2 {
+            break;
+        }
+        thread::sleep(Duration::from_millis(10));
+        names = retrieve_names(&ds, &layer, &extent);
+    }
+    assert_eq!(names, vec!["A".to_string(), "BB".to_string()]);
+
+    // Missing file
+    let ds = CsvDatasource::new(&format!("{}.missing", path));
+    assert!(ds.connected().is_err());
+}
@@ -4,15 +4,18 @@
 //
 
 use core::config::DatasourceCfg;
-use core::feature::Feature;
+use core::feature::{Feature, FeatureAttr, FeatureAttrValType};
 use core::grid::Extent;
 use core::grid::Grid;
-use core::layer::Layer;
+use core::layer::{Layer, ParamValue};
 use core::Config;
+use datasource::transform::Transform;
 
 pub trait DatasourceInput {
-    /// New instance with connected pool
-    fn connected(&self) -> Self;
+    /// New instance with connected pool or connection error
+    fn connected(&self) -> Result<Self, String>
+    where
+        Self: Sized;
     fn detect_layers(&self, detect_geometry_types: bool) -> Vec<Layer>;
     /// Return column field names and Rust compatible type conversion - without geometry column
     fn detect_data_columns(&self, layer: &Layer, sql: Option<&String>) -> Vec<(String, String)>;
@@ -23,12 +26,109 @@ pub trait DatasourceInput {
     fn retrieve_features<F>(&self, layer: &Layer, extent: &Extent, zoom: u8, grid: &Grid, read: F)
     where
         F: FnMut(&Feature);
+    /// Retrieve features with values of the layer request parameters or datasource error
+    fn retrieve_features_with_params<F>(
+        &self,
+        layer: &Layer,
+        extent: &Extent,
+        zoom: u8,
+        grid: &Grid,
+        _params: &[ParamValue],
+        read: F,
+    ) -> Result<(), String>
+    where
+        F: FnMut(&Feature),
+    {
+        self.retrieve_features(layer, extent, zoom, grid, read);
+        Ok(())
+    }
+    /// Layer encoded as vector tile by the datasource (None: not supported)
+    fn retrieve_mvt_layer(
+        &self,
+        _layer: &Layer,
+        _extent: &Extent,
+        _zoom: u8,
+        _grid: &Grid,
+        _params: &[ParamValue],
+    ) -> Result<Option<Vec<u8>>, String> {
+        Ok(None)
+    }
+}
+
+/// Tile extent extended by the layer buffer of the zoom level
+pub fn buffered_extent(layer: &Layer, extent: &Extent, zoom: u8, grid: &Grid) -> Extent {
+    match layer.buffer_size(zoom) {
+        Some(pixels) => {
+            let buf = f64::from(pixels) * grid.pixel_width(zoom);
+            Extent {
+                minx: extent.minx - buf,
+                miny: extent.miny - buf,
+                maxx: extent.maxx + buf,
+                maxy: extent.maxy + buf,
+            }
+        }
+        None => extent.clone(),
+    }
+}
+
+/// Buffered tile extent in layer SRS for spatial filtering
+pub fn layer_bbox(layer: &Layer, extent: &Extent, zoom: u8, grid: &Grid, srid: i32) -> Extent {
+    Transform::new(grid.srid, srid)
+        .unwrap_or(Transform::Identity)
+        .extent(&buffered_extent(layer, extent, zoom, grid))
+}
+
+/// Feature id from a non-negative integer attribute
+pub fn attribute_fid(attributes: &[FeatureAttr], fid_field: &str) -> Option<u64> {
+    attributes
+        .iter()
+        .find(|attr| attr.key == fid_field)
+        .and_then(|attr| match attr.value {
+            FeatureAttrValType::Int(v) if v >= 0 => Some(v as u64),
+            FeatureAttrValType::UInt(v) => Some(v),
+            _ => None,
+        })
+}
+
+/// Number of features read by a layer query, limited by the layer `query_limit`
+pub struct FeatureCount<'a> {
+    layer: &'a Layer,
+    limit: Option<u32>,
+    count: u32,
+}
+
+impl<'a> FeatureCount<'a> {
+    pub fn new(layer: &'a Layer, zoom: u8) -> FeatureCount<'a> {
+        FeatureCount {
+            layer: layer,
+            limit: layer.query_limit(zoom),
+            count: 0,
+        }
+    }
+    /// Count a feature. Returns false when the query limit is reached.
+    pub fn add(&mut self) -> bool {
+        self.count += 1;
+        if self.limit == Some(self.count) {
+            info!(
+                "Feature count limited (name={}, query_limit={})",
+                self.layer.name, self.count
+            );
+            return false;
+        }
+        true
+    }
+}
+
+impl<'a> Drop for FeatureCount<'a> {
+    fn drop(&mut self) {
+        debug!("Feature count: {}", self.count);
+    }
 }
 
 pub struct DummyDatasource;
 
 impl DatasourceInput for DummyDatasource {
-    fn connected(&self) -> DummyDatasource {
+    fn connected(&self) -> Result<DummyDatasource, String> {
         unimplemented!();
     }
     fn detect_layers(&self, _detect_geometry_types: bool) -> Vec<Layer> {
@@ -0,0 +1,787 @@
+//
+// Copyright (c) Pirmin Kalberer. All rights reserved.
+// Licensed under the MIT License. See LICENSE file in the project root for full license information.
+//
+
+//! FlatGeobuf file datasource using the packed Hilbert R-tree of the file
+
+use core::config::DatasourceCfg;
+use core::feature::{Feature, FeatureAttr, FeatureAttrValType};
+use core::geom::{self, GeometryType};
+use core::grid::Extent;
+use core::grid::Grid;
+use core::layer::Layer;
+use core::Config;
+use datasource::datasource::{attribute_fid, layer_bbox, FeatureCount};
+use datasource::rtree::PackedRTree;
+use datasource::transform::Transform;
+use datasource::DatasourceInput;
+use std::fs::File;
+use std::io::{BufReader, Read, Seek, SeekFrom};
+use std::path::Path;
+
+const MAGIC: [u8; 3] = [b'f', b'g', b'b'];
+
+fn read_u16(buf: &[u8], pos: usize) -> Result<u16, String> {
+    buf.get(pos..pos + 2)
+        .map(|b| b[0] as u16 | (b[1] as u16) << 8)
+        .ok_or("Unexpected end of FlatGeobuf buffer".to_string())
+}
+
+fn read_u32(buf: &[u8], pos: usize) -> Result<u32, String> {
+    buf.get(pos..pos + 4)
+        .map(|b| (0..4).fold(0, |v, i| v | (b[i] as u32) << (8 * i)))
+        .ok_or("Unexpected end of FlatGeobuf buffer".to_string())
+}
+
+fn read_u64(buf: &[u8], pos: usize) -> Result<u64, String> {
+    buf.get(pos..pos + 8)
+        .map(|b| (0..8).fold(0, |v, i| v | (b[i] as u64) << (8 * i)))
+        .ok_or("Unexpected end of FlatGeobuf buffer".to_string())
+}
+
+fn read_f64(buf: &[u8], pos: usize) -> Result<f64, String> {
+    read_u64(buf, pos).map(f64::from_bits)
+}
+
+/// FlatBuffers table
+#[derive(Clone, Copy)]
+struct Table<'a> {
+    buf: &'a [u8],
+    pos: usize,
+}
+
+impl<'a> Table<'a> {
+    /// Root table of a buffer
+    fn root(buf: &'a [u8]) -> Result<Table<'a>, String> {
+        Ok(Table {
+            buf: buf,
+            pos: read_u32(buf, 0)? as usize,
+        })
+    }
+    /// Absolute position of field
+    fn field(&self, id: usize) -> Result<Option<usize>, String> {
+        let vtable = (self.pos as i64 - read_u32(self.buf, self.pos)? as i32 as i64) as usize;
+        let vtable_len = read_u16(self.buf, vtable)? as usize;
+        let entry = 4 + 2 * id;
+        if entry + 2 > vtable_len {
+            return Ok(None);
+        }
+        match read_u16(self.buf, vtable + entry)? {
+            0 => Ok(None),
+            offset => Ok(Some(self.pos + offset as usize)),
+        }
+    }
+    fn indirect(&self, pos: usize) -> Result<usize, String> {
+        Ok(pos + read_u32(self.buf, pos)? as usize)
+    }
+    fn u8(&self, id: usize) -> Result<Option<u8>, String> {
+        Ok(match self.field(id)? {
+            Some(pos) => Some(*self.buf
+                .get(pos)
+                .ok_or("Unexpected end of FlatGeobuf buffer".to_string())?),
+            None => None,
+        })
+    }
+    fn u16(&self, id: usize) -> Result<Option<u16>, String> {
+        Ok(match self.field(id)? {
+            Some(pos) => Some(read_u16(self.buf, pos)?),
+            None => None,
+        })
+    }
+    fn i32(&self, id: usize) -> Result<Option<i32>, String> {
+        Ok(match self.field(id)? {
+            Some(pos) => Some(read_u32(self.buf, pos)? as i32),
+            None => None,
+        })
+    }
+    fn u64(&self, id: usize) -> Result<Option<u64>, String> {
+        Ok(match self.field(id)? {
+            Some(pos) => Some(read_u64(self.buf, pos)?),
+            None => None,
+        })
+    }
+    fn table(&self, id: usize) -> Result<Option<Table<'a>>, String> {
+        Ok(match self.field(id)? {
+            Some(pos) => Some(Table {
+                buf: self.buf,
+                pos: self.indirect(pos)?,
+            }),
+            None => None,
+        })
+    }
+    /// Start position and length of vector
+    fn vector(&self, id: usize) -> Result<Option<(usize, usize)>, String> {
+        Ok(match self.field(id)? {
+            Some(pos) => {
+                let start = self.indirect(pos)?;
+                Some((start + 4, read_u32(self.buf, start)? as usize))
+            }
+            None => None,
+        })
+    }
+    fn bytes(&self, id: usize) -> Result<&'a [u8], String> {
+        match self.vector(id)? {
+            Some((start, len)) => self.buf
+                .get(start..start + len)
+                .ok_or("Unexpected end of FlatGeobuf buffer".to_string()),
+            None => Ok(&[]),
+        }
+    }
+    fn string(&self, id: usize) -> Result<Option<String>, String> {
+        let bytes = self.bytes(id)?;
+        Ok(match self.field(id)? {
+            Some(_) => Some(String::from_utf8_lossy(bytes).to_string()),
+            None => None,
+        })
+    }
+    fn f64_vec(&self, id: usize) -> Result<Vec<f64>, String> {
+        match self.vector(id)? {
+            Some((start, len)) => (0..len).map(|i| read_f64(self.buf, start + 8 * i)).collect(),
+            None => Ok(Vec::new()),
+        }
+    }
+    fn u32_vec(&self, id: usize) -> Result<Vec<u32>, String> {
+        match self.vector(id)? {
+            Some((start, len)) => (0..len).map(|i| read_u32(self.buf, start + 4 * i)).collect(),
+            None => Ok(Vec::new()),
+        }
+    }
+    fn tables(&self, id: usize) -> Result<Vec<Table<'a>>, String> {
+        match self.vector(id)? {
+            Some((start, len)) => (0..len)
+                .map(|i| {
+                    Ok(Table {
+                        buf: self.buf,
+                        pos: self.indirect(start + 4 * i)?,
+                    })
+                })
+                .collect(),
+            None => Ok(Vec::new()),
+        }
+    }
+}
+
+/// Column types of FlatGeobuf schema
+#[derive(PartialEq, Clone, Copy, Debug)]
+enum ColumnType {
+    Byte,
+    UByte,
+    Bool,
+    Short,
+    UShort,
+    Int,
+    UInt,
+    Long,
+    ULong,
+    Float,
+    Double,
+    String,
+    Json,
+    DateTime,
+    Binary,
+}
+
+impl ColumnType {
+    fn from_u8(t: u8) -> Result<ColumnType, String> {
+        use self::ColumnType::*;
+        let types = [
+            Byte, UByte, Bool, Short, UShort, Int, UInt, Long, ULong, Float, Double, String, Json,
+            DateTime, Binary,
+        ];
+        types
+            .get(t as usize)
+            .cloned()
+            .ok_or(format!("Unknown column type {}", t))
+    }
+    fn type_name(&self) -> &'static str {
+        match self {
+            &ColumnType::Bool => "bool",
+            &ColumnType::Float | &ColumnType::Double => "double",
+            &ColumnType::String | &ColumnType::Json | &ColumnType::DateTime => "string",
+            &ColumnType::Binary => "binary",
+            _ => "integer",
+        }
+    }
+}
+
+#[derive(Clone, Debug)]
+struct Column {
+    name: String,
+    column_type: ColumnType,
+}
+
+/// File header
+#[derive(Clone, Debug)]
+struct Header {
+    name: Option<String>,
+    geometry_type: u8,
+    /// Number of coordinate dimensions besides x/y
+    extra_dims: (bool, bool),
+    columns: Vec<Column>,
+    features_count: u64,
+    index_node_size: u16,
+    srid: Option<i32>,
+    /// Position of feature data
+    features_offset: u64,
+    extent: Option<Extent>,
+}
+
+fn read_columns(table: &Table) -> Result<Vec<Column>, String> {
+    table
+        .tables(7)?
+        .iter()
+        .map(|col| {
+            Ok(Column {
+                name: col.string(0)?.unwrap_or(String::new()),
+                column_type: ColumnType::from_u8(col.u8(1)?.unwrap_or(0))?,
+            })
+        })
+        .collect()
+}
+
+/// Read `len` bytes without allocating more than the remaining input
+fn read_buf<R: Read>(reader: &mut R, len: u64) -> Result<Vec<u8>, String> {
+    let mut buf = Vec::new();
+    reader
+        .take(len)
+        .read_to_end(&mut buf)
+        .map_err(|e| e.to_string())?;
+    if (buf.len() as u64) < len {
+        return Err("Unexpected end of FlatGeobuf file".to_string());
+    }
+    Ok(buf)
+}
+
+impl Header {
+    /// Read header of a file with `file_len` bytes
+    fn read<R: Read>(reader: &mut R, file_len: u64) -> Result<Header, String> {
+        let mut start = [0; 12];
+        reader.read_exact(&mut start).map_err(|e| e.to_string())?;
+        if start[0..3] != MAGIC || start[4..7] != MAGIC {
+            return Err("Invalid FlatGeobuf file".to_string());
+        }
+        let header_len = read_u32(&start, 8)? as u64;
+        if 12 + header_len > file_len {
+            return Err(format!("Invalid FlatGeobuf header size {}", header_len));
+        }
+        let buf = read_buf(reader, header_len)?;
+        let header = Table::root(&buf)?;
+        // Each feature has at least a 4 byte size prefix
+        let features_count = header.u64(8)?.unwrap_or(0);
+        if features_count > file_len / 4 {
+            return Err(format!("Invalid FlatGeobuf feature count {}", features_count));
+        }
+        let envelope = header.f64_vec(1)?;
+        let srid = match header.table(10)? {
+            Some(crs) => crs.i32(1)?.filter(|&code| code != 0),
+            None => None,
+        };
+        Ok(Header {
+            name: header.string(0)?,
+            geometry_type: header.u8(2)?.unwrap_or(0),
+            extra_dims: (
+                header.u8(3)?.unwrap_or(0) != 0,
+                header.u8(4)?.unwrap_or(0) != 0,
+            ),
+            columns: read_columns(&header)?,
+            features_count: features_count,
+            index_node_size: header.u16(9)?.unwrap_or(16),
+            srid: srid,
+            features_offset: 12 + header_len,
+            extent: if envelope.len() >= 4 {
+                Some(Extent {
+                    minx: envelope[0],
+                    miny: envelope[1],
+                    maxx: envelope[2],
+                    maxy: envelope[3],
+                })
+            } else {
+                None
+            },
+        })
+    }
+    fn has_index(&self) -> bool {
+        self.index_node_size > 0 && self.features_count > 0
+    }
+    fn index_size(&self) -> u64 {
+        if self.has_index() {
+            PackedRTree::size(self.features_count as usize, self.index_node_size as usize) as u64
+        } else {
+            0
+        }
+    }
+}
+
+fn read_properties(
+    bytes: &[u8],
+    columns: &[Column],
+) -> Result<Vec<(usize, Option<FeatureAttrValType>)>, String> {
+    let mut values = Vec::new();
+    let mut pos = 0;
+    while pos + 2 <= bytes.len() {
+        let idx = read_u16(bytes, pos)? as usize;
+        pos += 2;
+        let column = columns
+            .get(idx)
+            .ok_or(format!("Invalid column index {}", idx))?;
+        let fixed = |len: usize| -> Result<&[u8], String> {
+            bytes
+                .get(pos..pos + len)
+                .ok_or("Unexpected end of properties".to_string())
+        };
+        let (value, len) = match column.column_type {
+            ColumnType::Byte => (Some(FeatureAttrValType::Int(fixed(1)?[0] as i8 as i64)), 1),
+            ColumnType::UByte => (Some(FeatureAttrValType::Int(fixed(1)?[0] as i64)), 1),
+            ColumnType::Bool => (Some(FeatureAttrValType::Bool(fixed(1)?[0] != 0)), 1),
+            ColumnType::Short => (
+                Some(FeatureAttrValType::Int(read_u16(bytes, pos)? as i16 as i64)),
+                2,
+            ),
+            ColumnType::UShort => (
+                Some(FeatureAttrValType::Int(read_u16(bytes, pos)? as i64)),
+                2,
+            ),
+            ColumnType::Int => (
+                Some(FeatureAttrValType::Int(read_u32(bytes, pos)? as i32 as i64)),
+                4,
+            ),
+            ColumnType::UInt => (
+                Some(FeatureAttrValType::Int(read_u32(bytes, pos)? as i64)),
+                4,
+            ),
+            ColumnType::Long => (
+                Some(FeatureAttrValType::Int(read_u64(bytes, pos)? as i64)),
+                8,
+            ),
+            ColumnType::ULong => (
+                Some(FeatureAttrValType::UInt(read_u64(bytes, pos)?)),
+                8,
+            ),
+            ColumnType::Float => (
+                Some(FeatureAttrValType::Float(f32::from_bits(read_u32(
+                    bytes, pos,
+                )?))),
+                4,
+            ),
+            ColumnType::Double => (
+                Some(FeatureAttrValType::Double(read_f64(bytes, pos)?)),
+                8,
+            ),
+            ColumnType::String | ColumnType::Json | ColumnType::DateTime => {
+                let len = read_u32(bytes, pos)? as usize;
+                let text = bytes
+                    .get(pos + 4..pos + 4 + len)
+                    .ok_or("Unexpected end of properties".to_string())?;
+                (
+                    Some(FeatureAttrValType::String(
+                        String::from_utf8_lossy(text).to_string(),
+                    )),
+                    4 + len,
+                )
+            }
+            ColumnType::Binary => (None, 4 + read_u32(bytes, pos)? as usize),
+        };
+        values.push((idx, value));
+        pos += len;
+    }
+    Ok(values)
+}
+
+fn points(xy: &[f64], srid: Option<i32>) -> Vec<geom::Point> {
+    xy.chunks(2)
+        .filter(|c| c.len() == 2)
+        .map(|c| geom::Point::new(c[0], c[1], srid))
+        .collect()
+}
+
+/// Lines of coordinates split at `ends`
+fn lines(xy: &[f64], ends: &[u32], srid: Option<i32>) -> Vec<geom::LineString> {
+    let mut lines = Vec::new();
+    let mut start = 0;
+    let all = [(xy.len() / 2) as u32];
+    let ends = if ends.is_empty() { &all[..] } else { ends };
+    for &end in ends {
+        let end = (end as usize).min(xy.len() / 2);
+        if end > start {
+            lines.push(geom::LineString {
+                points: points(&xy[2 * start..2 * end], srid),
+                srid: srid,
+            });
+        }
+        start = end;
+    }
+    lines
+}
+
+fn read_geometry(
+    table: &Table,
+    default_type: u8,
+    srid: Option<i32>,
+) -> Result<GeometryType, String> {
+    let geometry_type = match table.u8(6)?.unwrap_or(0) {
+        0 => default_type,
+        t => t,
+    };
+    let xy = table.f64_vec(1)?;
+    let ends = table.u32_vec(0)?;
+    match geometry_type {
+        1 => points(&xy, srid)
+            .into_iter()
+            .next()
+            .map(GeometryType::Point)
+            .ok_or("Empty point".to_string()),
+        2 => Ok(GeometryType::LineString(geom::LineString {
+            points: points(&xy, srid),
+            srid: srid,
+        })),
+        3 => Ok(GeometryType::Polygon(geom::Polygon {
+            rings: lines(&xy, &ends, srid),
+            srid: srid,
+        })),
+        4 => Ok(GeometryType::MultiPoint(geom::MultiPoint {
+            points: points(&xy, srid),
+            srid: srid,
+        })),
+        5 => Ok(GeometryType::MultiLineString(geom::MultiLineString {
+            lines: lines(&xy, &ends, srid),
+            srid: srid,
+        })),
+        6 => {
+            let mut polygons = Vec::new();
+            for part in table.tables(7)? {
+                match read_geometry(&part, 3, srid)? {
+                    GeometryType::Polygon(p) => polygons.push(p),
+                    _ => return Err("Expected Polygon in MultiPolygon".to_string()),
+                }
+            }
+            Ok(GeometryType::MultiPolygon(geom::MultiPolygon {
+                polygons: polygons,
+                srid: srid,
+            }))
+        }
+        t => Err(format!("Unsupported FlatGeobuf geometry type {}", t)),
+    }
+}
+
+/// Decoded feature
+struct FgbFeature<'a> {
+    layer: &'a Layer,
+    header: &'a Header,
+    /// Sequence number of the feature in the file
+    fid: u64,
+    buf: Vec<u8>,
+    grid_srid: i32,
+    transform: Transform,
+}
+
+impl<'a> Feature for FgbFeature<'a> {
+    fn fid(&self) -> Option<u64> {
+        match self.layer.fid_field {
+            Some(ref fid_field) => attribute_fid(&self.attributes(), fid_field),
+            None => Some(self.fid),
+        }
+    }
+    fn attributes(&self) -> Vec<FeatureAttr> {
+        let properties = Table::root(&self.buf).and_then(|feature| {
+            let columns = feature.tables(2).and_then(|cols| {
+                cols.iter()
+                    .map(|col| {
+                        Ok(Column {
+                            name: col.string(0)?.unwrap_or(String::new()),
+                            column_type: ColumnType::from_u8(col.u8(1)?.unwrap_or(0))?,
+                        })
+                    })
+                    .collect::<Result<Vec<Column>, String>>()
+            })?;
+            let columns = if columns.is_empty() {
+                self.header.columns.clone()
+            } else {
+                columns
+            };
+            let values = read_properties(feature.bytes(1)?, &columns)?;
+            Ok(values
+                .into_iter()
+                .filter_map(|(idx, value)| {
+                    value.map(|value| FeatureAttr {
+                        key: columns[idx].name.clone(),
+                        value: value,
+                    })
+                })
+                .collect())
+        });
+        match properties {
+            Ok(attrs) => attrs,
+            Err(e) => {
+                warn!("Layer '{}': {}", self.layer.name, e);
+                Vec::new()
+            }
+        }
+    }
+    fn geometry(&self) -> Result<GeometryType, String> {
+        let feature = Table::root(&self.buf)?;
+        let geometry = feature
+            .table(0)?
+            .ok_or("Feature without geometry".to_string())?;
+        if self.header.extra_dims != (false, false) {
+            debug!("Ignoring z/m coordinates");
+        }
+        let geom = read_geometry(&geometry, self.header.geometry_type, Some(self.grid_srid))?;
+        if self.transform == Transform::Identity {
+            return Ok(geom);
+        }
+        self.transform.geometry(&geom, Some(self.grid_srid))
+    }
+}
+
+pub struct FgbDatasource {
+    pub path: String,
+    header: Option<Header>,
+    index: Option<PackedRTree>,
+}
+
+impl FgbDatasource {
+    pub fn new(path: &str) -> FgbDatasource {
+        FgbDatasource {
+            path: path.to_string(),
+            header: None,
+            index: None,
+        }
+    }
+    fn open(&self) -> Result<(BufReader<File>, Header), String> {
+        let file = File::open(&self.path).map_err(|e| format!("{}: {}", self.path, e))?;
+        let file_len = file
+            .metadata()
+            .map_err(|e| format!("{}: {}", self.path, e))?
+            .len();
+        let mut reader = BufReader::new(file);
+        let header =
+            Header::read(&mut reader, file_len).map_err(|e| format!("{}: {}", self.path, e))?;
+        if header.features_offset + header.index_size() > file_len {
+            return Err(format!("{}: Invalid FlatGeobuf index size", self.path));
+        }
+        Ok((reader, header))
+    }
+    fn read_index(&self, reader: &mut BufReader<File>, header: &Header) -> Result<PackedRTree, String> {
+        let buf = read_buf(reader, header.index_size())?;
+        Ok(PackedRTree::from_bytes(
+            &buf,
+            header.features_count as usize,
+            header.index_node_size as usize,
+        ))
+    }
+    fn header(&self) -> Option<Header> {
+        match self.header {
+            Some(ref header) => Some(header.clone()),
+            None => self.open().map_err(|e| error!("{}", e)).ok().map(|(_, h)| h),
+        }
+    }
+    /// Read size prefixed feature buffer at current position
+    fn read_feature(reader: &mut BufReader<File>) -> Result<Option<Vec<u8>>, String> {
+        let mut len = [0; 4];
+        if reader.read_exact(&mut len).is_err() {
+            return Ok(None);
+        }
+        let buf = read_buf(reader, read_u32(&len, 0)? as u64)?;
+        Ok(Some(buf))
+    }
+}
+
+impl DatasourceInput for FgbDatasource {
+    /// New instance with header and spatial index loaded into memory
+    fn connected(&self) -> Result<FgbDatasource, String> {
+        let mut ds = FgbDatasource::new(&self.path);
+        let (mut reader, header) = self.open()?;
+        if header.has_index() {
+            let index = self.read_index(&mut reader, &header)
+                .map_err(|e| format!("{}: {}", self.path, e))?;
+            ds.index = Some(index);
+        }
+        ds.header = Some(header);
+        Ok(ds)
+    }
+    fn detect_layers(&self, _detect_geometry_types: bool) -> Vec<Layer> {
+        let header = match self.header() {
+            Some(header) => header,
+            None => return Vec::new(),
+        };
+        let name = header.name.clone().unwrap_or_else(|| {
+            Path::new(&self.path)
+                .file_stem()
+                .map_or("fgb".to_string(), |stem| stem.to_string_lossy().to_string())
+        });
+        let mut layer = Layer::new(&name);
+        layer.table_name = Some(name);
+        layer.geometry_type = Some(
+            match header.geometry_type {
+                1 => "POINT",
+                2 => "LINESTRING",
+                3 => "POLYGON",
+                4 => "MULTIPOINT",
+                5 => "MULTILINESTRING",
+                6 => "MULTIPOLYGON",
+                _ => "GEOMETRY",
+            }.to_string(),
+        );
+        layer.srid = header.srid;
+        vec![layer]
+    }
+    /// Return column names and types of the file schema
+    fn detect_data_columns(&self, _layer: &Layer, _sql: Option<&String>) -> Vec<(String, String)> {
+        self.header().map_or(Vec::new(), |header| {
+            header
+                .columns
+                .iter()
+                .map(|col| (col.name.clone(), col.column_type.type_name().to_string()))
+                .collect()
+        })
+    }
+    /// Projected extent
+    fn extent_from_wgs84(&self, extent: &Extent, dest_srid: i32) -> Option<Extent> {
+        Transform::new(4326, dest_srid)
+            .map(|t| t.extent(extent))
+            .map_err(|e| warn!("{}", e))
+            .ok()
+    }
+    /// Detect extent of layer (in WGS84)
+    fn layer_extent(&self, layer: &Layer) -> Option<Extent> {
+        let header = self.header()?;
+        let srid = layer.srid.or(header.srid)?;
+        let extent = header.extent?;
+        match Transform::new(srid, 4326) {
+            Ok(transform) => Some(transform.extent(&extent)),
+            Err(e) => {
+                warn!("Layer '{}': {}", layer.name, e);
+                None
+            }
+        }
+    }
+    fn prepare_queries(&mut self, layer: &Layer, grid_srid: i32) {
+        let srid = layer
+            .srid
+            .or(self.header.as_ref().and_then(|h| h.srid))
+            .unwrap_or(grid_srid);
+        if srid != grid_srid {
+            info!(
+                "Layer '{}': Reprojecting geometry from SRID {} to {}",
+                layer.name, srid, grid_srid
+            );
+        }
+        if self.index.is_none() {
+            warn!(
+                "Layer '{}': No spatial index - reading all features of each tile",
+                layer.name
+            );
+        }
+    }
+    fn retrieve_features<F>(
+        &self,
+        layer: &Layer,
+        extent: &Extent,
+        zoom: u8,
+        grid: &Grid,
+        mut read: F,
+    ) where
+        F: FnMut(&Feature),
+    {
+        let header = match self.header {
+            Some(ref header) => header,
+            None => return,
+        };
+        let srid = layer.srid.or(header.srid).unwrap_or(grid.srid);
+        let transform = match Transform::new(srid, grid.srid) {
+            Ok(transform) => transform,
+            Err(e) => {
+                error!("Layer '{}': {}", layer.name, e);
+                return;
+            }
+        };
+        let mut reader = match File::open(&self.path) {
+            Ok(file) => BufReader::new(file),
+            Err(e) => {
+                error!("{}: {}", self.path, e);
+                return;
+            }
+        };
+        let data_start = header.features_offset + header.index_size();
+        // Sequence numbers and byte offsets of features relative to feature data
+        let items = match self.index {
+            Some(ref index) => Some(index.search_items(&layer_bbox(layer, extent, zoom, grid, srid))),
+            None => None,
+        };
+        if let Err(e) = reader.seek(SeekFrom::Start(data_start)) {
+            error!("{}: {}", self.path, e);
+            return;
+        }
+
+        let mut count = FeatureCount::new(layer, zoom);
+        let mut idx = 0;
+        loop {
+            let fid = match items {
+                Some(ref items) => {
+                    let (fid, offset) = match items.get(idx) {
+                        Some(&item) => item,
+                        None => break,
+                    };
+                    if let Err(e) = reader.seek(SeekFrom::Start(data_start + offset)) {
+                        error!("{}: {}", self.path, e);
+                        break;
+                    }
+                    fid
+                }
+                None => idx as u64,
+            };
+            let buf = match Self::read_feature(&mut reader) {
+                Ok(Some(buf)) => buf,
+                Ok(None) => break,
+                Err(e) => {
+                    error!("{}: {}", self.path, e);
+                    break;
+                }
+            };
+            let feature = FgbFeature {
+                layer: layer,
+                header: header,
+                fid: fid,
+                buf: buf,
+                grid_srid: grid.srid,
+                transform: transform,
+            };
+            read(&feature);
+            idx += 1;
+            if !count.add() {
+                break;
+            }
+        }
+    }
+}
+
+impl<'a> Config<'a, DatasourceCfg> for FgbDatasource {
+    fn from_config(ds_cfg: &DatasourceCfg) -> Result<Self, String> {
+        let path = ds_cfg
+            .fgb
+            .as_ref()
+            .or(ds_cfg.path.as_ref())
+            .ok_or("FlatGeobuf file name missing".to_string())?;
+        Ok(FgbDatasource::new(path))
+    }
+
+    fn gen_config() -> String {
+        let toml = r#"
+[[datasource]]
+name = "fgb"
+# FlatGeobuf file
+fgb = "<filename>"
+"#;
+        toml.to_string()
+    }
+    fn gen_runtime_config(&self) -> String {
+        format!(
+            r#"
+[[datasource]]
+fgb = "{}"
+"#,
+            self.path
+        )
+    }
+}
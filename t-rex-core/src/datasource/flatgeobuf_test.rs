@@ -0,0 +1,255 @@
+//
+// Copyright (c) Pirmin Kalberer. All rights reserved.
+// Licensed under the MIT License. See LICENSE file in the project root for full license information.
+//
+
+use core::feature::FeatureAttrValType;
+use core::geom::GeometryType;
+use core::grid::{Extent, Grid};
+use core::layer::Layer;
+use datasource::flatgeobuf::FgbDatasource;
+use datasource::DatasourceInput;
+use std::env;
+use std::fs::{File, OpenOptions};
+use std::io::Write;
+
+/// FlatBuffers field value
+enum Val {
+    U8(u8),
+    U16(u16),
+    I32(i32),
+    U64(u64),
+    Bytes(Vec<u8>),
+    F64s(Vec<f64>),
+    Table(Vec<(usize, Val)>),
+    Tables(Vec<Vec<(usize, Val)>>),
+}
+
+fn le_bytes(v: u64, len: usize) -> Vec<u8> {
+    (0..len).map(|i| (v >> (8 * i)) as u8).collect()
+}
+
+fn patch_u32(buf: &mut Vec<u8>, pos: usize, v: u32) {
+    buf[pos..pos + 4].copy_from_slice(&le_bytes(v as u64, 4));
+}
+
+/// Write vtable followed by table and referenced objects. Returns table position.
+fn write_table(buf: &mut Vec<u8>, fields: &[(usize, Val)]) -> usize {
+    let num_fields = fields.iter().map(|&(id, _)| id + 1).max().unwrap_or(0);
+    let vtable = buf.len();
+    buf.extend(vec![0; 4 + 2 * num_fields]);
+    let table = buf.len();
+    buf.extend(le_bytes((table - vtable) as u64, 4));
+    let mut refs = Vec::new();
+    for &(id, ref val) in fields {
+        let pos = buf.len();
+        let entry = vtable + 4 + 2 * id;
+        buf[entry..entry + 2].copy_from_slice(&le_bytes((pos - table) as u64, 2));
+        match val {
+            &Val::U8(v) => buf.push(v),
+            &Val::U16(v) => buf.extend(le_bytes(v as u64, 2)),
+            &Val::I32(v) => buf.extend(le_bytes(v as u32 as u64, 4)),
+            &Val::U64(v) => buf.extend(le_bytes(v, 8)),
+            _ => {
+                buf.extend(vec![0; 4]);
+                refs.push((pos, val));
+            }
+        }
+    }
+    let vtable_len = le_bytes((4 + 2 * num_fields) as u64, 2);
+    buf[vtable..vtable + 2].copy_from_slice(&vtable_len);
+    let table_len = le_bytes((buf.len() - table) as u64, 2);
+    buf[vtable + 2..vtable + 4].copy_from_slice(&table_len);
+    for (pos, val) in refs {
+        let target = write_ref(buf, val);
+        patch_u32(buf, pos, (target - pos) as u32);
+    }
+    table
+}
+
+fn write_ref(buf: &mut Vec<u8>, val: &Val) -> usize {
+    let start = buf.len();
+    match val {
+        &Val::Bytes(ref v) => {
+            buf.extend(le_bytes(v.len() as u64, 4));
+            buf.extend(v);
+            start
+        }
+        &Val::F64s(ref v) => {
+            buf.extend(le_bytes(v.len() as u64, 4));
+            for f in v {
+                buf.extend(le_bytes(f.to_bits(), 8));
+            }
+            start
+        }
+        &Val::Table(ref fields) => write_table(buf, fields),
+        &Val::Tables(ref tables) => {
+            buf.extend(le_bytes(tables.len() as u64, 4));
+            buf.extend(vec![0; 4 * tables.len()]);
+            for (i, fields) in tables.iter().enumerate() {
+                let pos = start + 4 + 4 * i;
+                let target = write_table(buf, fields);
+                patch_u32(buf, pos, (target - pos) as u32);
+            }
+            start
+        }
+        _ => unreachable!(),
+    }
+}
+
+fn finish(fields: Vec<(usize, Val)>) -> Vec<u8> {
+    let mut buf = vec![0; 4];
+    let root = write_table(&mut buf, &fields);
+    patch_u32(&mut buf, 0, root as u32);
+    buf
+}
+
+fn string(s: &str) -> Val {
+    Val::Bytes(s.as_bytes().to_vec())
+}
+
+fn point_feature(x: f64, y: f64, name: &str, population: i32) -> Vec<u8> {
+    let mut properties = le_bytes(0, 2);
+    properties.extend(le_bytes(name.len() as u64, 4));
+    properties.extend(name.as_bytes());
+    properties.extend(le_bytes(1, 2));
+    properties.extend(le_bytes(population as u32 as u64, 4));
+    finish(vec![
+        (0, Val::Table(vec![(1, Val::F64s(vec![x, y]))])),
+        (1, Val::Bytes(properties)),
+    ])
+}
+
+fn node_item(extent: [f64; 4], offset: u64) -> Vec<u8> {
+    let mut item: Vec<u8> = extent.iter().flat_map(|f| le_bytes(f.to_bits(), 8)).collect();
+    item.extend(le_bytes(offset, 8));
+    item
+}
+
+/// Create FlatGeobuf file with two WGS84 points
+fn create_fgb(name: &str, index: bool) -> String {
+    let header = finish(vec![
+        (0, string("places")),
+        (1, Val::F64s(vec![8.5, 47.3, 9.5, 47.5])),
+        (2, Val::U8(1)),
+        (
+            7,
+            Val::Tables(vec![
+                vec![(0, string("name")), (1, Val::U8(11))],
+                vec![(0, string("population")), (1, Val::U8(5))],
+            ]),
+        ),
+        (8, Val::U64(2)),
+        (9, Val::U16(if index { 16 } else { 0 })),
+        (10, Val::Table(vec![(1, Val::I32(4326))])),
+    ]);
+    let features = vec![
+        point_feature(8.5, 47.3, "Zurich", 400000),
+        point_feature(9.5, 47.5, "St. Gallen", 75000),
+    ];
+    let mut data = b"fgb\x03fgb\x00".to_vec();
+    data.extend(le_bytes(header.len() as u64, 4));
+    data.extend(&header);
+    if index {
+        data.extend(node_item([8.5, 47.3, 9.5, 47.5], 1));
+        data.extend(node_item([8.5, 47.3, 8.5, 47.3], 0));
+        let second = 4 + features[0].len() as u64;
+        data.extend(node_item([9.5, 47.5, 9.5, 47.5], second));
+    }
+    for feature in features {
+        data.extend(le_bytes(feature.len() as u64, 4));
+        data.extend(feature);
+    }
+    let path = env::temp_dir().join(name);
+    File::create(&path).unwrap().write_all(&data).unwrap();
+    path.to_str().unwrap().to_string()
+}
+
+#[test]
+fn test_detect_layers() {
+    let ds = FgbDatasource::new(&create_fgb("t_rex_detect.fgb", true));
+    let layers = ds.detect_layers(true);
+    assert_eq!(layers.len(), 1);
+    assert_eq!(layers[0].name, "places");
+    assert_eq!(layers[0].geometry_type, Some("POINT".to_string()));
+    assert_eq!(layers[0].srid, Some(4326));
+
+    let columns = ds.detect_data_columns(&layers[0], None);
+    assert_eq!(
+        columns,
+        vec![
+            ("name".to_string(), "string".to_string()),
+            ("population".to_string(), "integer".to_string()),
+        ]
+    );
+
+    let extent = ds.layer_extent(&layers[0]).unwrap();
+    assert_eq!(extent.minx, 8.5);
+    assert_eq!(extent.maxy, 47.5);
+}
+
+#[test]
+fn test_retrieve_features() {
+    let extent = Extent {
+        minx: 9.0,
+        miny: 47.0,
+        maxx: 10.0,
+        maxy: 48.0,
+    };
+    let grid = Grid::wgs84();
+    for &(name, index) in &[("t_rex_index.fgb", true), ("t_rex_noindex.fgb", false)] {
+        let ds = FgbDatasource::new(&create_fgb(name, index)).connected().unwrap();
+        let mut layer = Layer::new("places");
+        let mut names = Vec::new();
+        let mut fids = Vec::new();
+        ds.retrieve_features(&layer, &extent, 10, &grid, |feat| {
+            fids.push(feat.fid());
+            let attrs = feat.attributes();
+            match attrs.iter().find(|attr| attr.key == "name").unwrap().value {
+                FeatureAttrValType::String(ref name) => names.push(name.clone()),
+                _ => panic!("String expected"),
+            }
+            match feat.geometry().unwrap() {
+                GeometryType::Point(p) => assert_eq!(p.srid, Some(4326)),
+                _ => panic!("Point expected"),
+            }
+        });
+        // Feature ids are sequence numbers with and without spatial index
+        if index {
+            assert_eq!(names, vec!["St. Gallen".to_string()]);
+            assert_eq!(fids, vec![Some(1)]);
+        } else {
+            assert_eq!(names.len(), 2);
+            assert_eq!(fids, vec![Some(0), Some(1)]);
+        }
+
+        layer.query_limit = Some(1);
+        let mut reccnt = 0;
+        ds.retrieve_features(&layer, &grid.extent, 0, &grid, |_| reccnt += 1);
+        assert_eq!(reccnt, 1);
+    }
+}
+
+#[test]
+fn test_invalid_lengths() {
+    // Header size beyond end of file
+    let path = env::temp_dir().join("t_rex_invalid_header.fgb");
+    let mut data = b"fgb\x03fgb\x00".to_vec();
+    data.extend(le_bytes(0xffff_fff0, 4));
+    data.extend(vec![0; 16]);
+    File::create(&path).unwrap().write_all(&data).unwrap();
+    let ds = FgbDatasource::new(path.to_str().unwrap());
+    assert_eq!(ds.detect_layers(true).len(), 0);
+    assert!(ds.connected().is_err());
+
+    // Feature size beyond end of file
+    let path = create_fgb("t_rex_invalid_feature.fgb", false);
+    let mut file = OpenOptions::new().append(true).open(&path).unwrap();
+    file.write_all(&le_bytes(0xffff_fff0, 4)).unwrap();
+    file.write_all(&[0; 8]).unwrap();
+    let ds = FgbDatasource::new(&path).connected().unwrap();
+    let grid = Grid::wgs84();
+    let mut reccnt = 0;
+    ds.retrieve_features(&Layer::new("places"), &grid.extent, 0, &grid, |_| reccnt += 1);
+    assert_eq!(reccnt, 2);
+}
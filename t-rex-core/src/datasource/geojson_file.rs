@@ -0,0 +1,444 @@
+//
+// Copyright (c) Pirmin Kalberer. All rights reserved.
+// Licensed under the MIT License. See LICENSE file in the project root for full license information.
+//
+
+//! GeoJSON file datasource with in-memory spatial index
+
+use core::config::DatasourceCfg;
+use core::feature::{Feature, FeatureAttr, FeatureAttrValType};
+use core::geom::{self, GeometryType};
+use core::grid::Extent;
+use core::grid::Grid;
+use core::layer::Layer;
+use core::Config;
+use datasource::datasource::{attribute_fid, layer_bbox, FeatureCount};
+use datasource::rtree::PackedRTree;
+use datasource::transform::Transform;
+use datasource::DatasourceInput;
+use serde_json;
+use std::fs::File;
+use std::io::BufReader;
+use std::path::Path;
+
+/// GeoJSON coordinates are WGS84 (RFC 7946)
+const GEOJSON_SRID: i32 = 4326;
+
+/// Node size of spatial index
+const INDEX_NODE_SIZE: usize = 16;
+
+struct JsonFeature {
+    fid: Option<u64>,
+    attributes: Vec<FeatureAttr>,
+    geometry: GeometryType,
+}
+
+/// Features of a GeoJSON file with spatial index
+struct GeoJsonData {
+    features: Vec<JsonFeature>,
+    index: PackedRTree,
+}
+
+pub struct GeoJsonDatasource {
+    pub path: String,
+    data: Option<GeoJsonData>,
+}
+
+fn json_point(coords: &serde_json::Value) -> Result<geom::Point, String> {
+    match (
+        coords.get(0).and_then(|v| v.as_f64()),
+        coords.get(1).and_then(|v| v.as_f64()),
+    ) {
+        (Some(x), Some(y)) => Ok(geom::Point::new(x, y, Some(GEOJSON_SRID))),
+        _ => Err(format!("Invalid coordinates {}", coords)),
+    }
+}
+
+fn json_array(coords: &serde_json::Value) -> Result<&Vec<serde_json::Value>, String> {
+    coords
+        .as_array()
+        .ok_or(format!("Invalid coordinates {}", coords))
+}
+
+fn json_line(coords: &serde_json::Value) -> Result<geom::LineString, String> {
+    Ok(geom::LineString {
+        points: json_array(coords)?
+            .iter()
+            .map(json_point)
+            .collect::<Result<_, _>>()?,
+        srid: Some(GEOJSON_SRID),
+    })
+}
+
+fn json_polygon(coords: &serde_json::Value) -> Result<geom::Polygon, String> {
+    Ok(geom::Polygon {
+        rings: json_array(coords)?
+            .iter()
+            .map(json_line)
+            .collect::<Result<_, _>>()?,
+        srid: Some(GEOJSON_SRID),
+    })
+}
+
+/// Geometry of a GeoJSON geometry object
+pub fn json_to_geometry(json: &serde_json::Value) -> Result<GeometryType, String> {
+    let coords = &json["coordinates"];
+    let srid = Some(GEOJSON_SRID);
+    match json["type"].as_str() {
+        Some("Point") => Ok(GeometryType::Point(json_point(coords)?)),
+        Some("MultiPoint") => Ok(GeometryType::MultiPoint(geom::MultiPoint {
+            points: json_array(coords)?
+                .iter()
+                .map(json_point)
+                .collect::<Result<_, _>>()?,
+            srid: srid,
+        })),
+        Some("LineString") => Ok(GeometryType::LineString(json_line(coords)?)),
+        Some("MultiLineString") => Ok(GeometryType::MultiLineString(geom::MultiLineString {
+            lines: json_array(coords)?
+                .iter()
+                .map(json_line)
+                .collect::<Result<_, _>>()?,
+            srid: srid,
+        })),
+        Some("Polygon") => Ok(GeometryType::Polygon(json_polygon(coords)?)),
+        Some("MultiPolygon") => Ok(GeometryType::MultiPolygon(geom::MultiPolygon {
+            polygons: json_array(coords)?
+                .iter()
+                .map(json_polygon)
+                .collect::<Result<_, _>>()?,
+            srid: srid,
+        })),
+        Some(t) => Err(format!("Unsupported geometry type {}", t)),
+        None => Err("Missing geometry type".to_string()),
+    }
+}
+
+/// Attribute value of a GeoJSON property
+fn json_value(value: &serde_json::Value) -> Option<FeatureAttrValType> {
+    match value {
+        &serde_json::Value::Null => None,
+        &serde_json::Value::Bool(v) => Some(FeatureAttrValType::Bool(v)),
+        &serde_json::Value::Number(ref n) => match (n.as_i64(), n.as_u64()) {
+            (Some(v), _) => Some(FeatureAttrValType::Int(v)),
+            (None, Some(v)) => Some(FeatureAttrValType::UInt(v)),
+            _ => n.as_f64().map(FeatureAttrValType::Double),
+        },
+        &serde_json::Value::String(ref v) => Some(FeatureAttrValType::String(v.clone())),
+        v => Some(FeatureAttrValType::String(v.to_string())),
+    }
+}
+
+/// Bounding box of geometry points
+pub fn geometry_extent(geom: &GeometryType) -> Option<Extent> {
+    let mut points: Vec<&geom::Point> = Vec::new();
+    match geom {
+        &GeometryType::Point(ref p) => points.push(p),
+        &GeometryType::MultiPoint(ref g) => points.extend(g.points.iter()),
+        &GeometryType::LineString(ref g) => points.extend(g.points.iter()),
+        &GeometryType::MultiLineString(ref g) => {
+            points.extend(g.lines.iter().flat_map(|l| l.points.iter()))
+        }
+        &GeometryType::Polygon(ref g) => {
+            points.extend(g.rings.iter().flat_map(|r| r.points.iter()))
+        }
+        &GeometryType::MultiPolygon(ref g) => points.extend(
+            g.polygons
+                .iter()
+                .flat_map(|p| p.rings.iter())
+                .flat_map(|r| r.points.iter()),
+        ),
+        &GeometryType::GeometryCollection(_) => {}
+    }
+    let first = points.first()?;
+    let init = Extent {
+        minx: first.x,
+        miny: first.y,
+        maxx: first.x,
+        maxy: first.y,
+    };
+    Some(points.iter().fold(init, |e, p| Extent {
+        minx: e.minx.min(p.x),
+        miny: e.miny.min(p.y),
+        maxx: e.maxx.max(p.x),
+        maxy: e.maxy.max(p.y),
+    }))
+}
+
+/// Type name of attribute value for field detection
+fn value_type(value: &FeatureAttrValType) -> &'static str {
+    match value {
+        &FeatureAttrValType::String(_) => "string",
+        &FeatureAttrValType::Float(_) | &FeatureAttrValType::Double(_) => "double",
+        &FeatureAttrValType::Int(_)
+        | &FeatureAttrValType::UInt(_)
+        | &FeatureAttrValType::SInt(_) => "integer",
+        &FeatureAttrValType::Bool(_) => "bool",
+    }
+}
+
+impl GeoJsonData {
+    fn read(path: &str) -> Result<GeoJsonData, String> {
+        let file = File::open(path).map_err(|e| format!("{}: {}", path, e))?;
+        let json: serde_json::Value = serde_json::from_reader(BufReader::new(file))
+            .map_err(|e| format!("{}: {}", path, e))?;
+        Self::from_json(&json)
+    }
+    fn from_json(json: &serde_json::Value) -> Result<GeoJsonData, String> {
+        let json_features = match json["type"].as_str() {
+            Some("FeatureCollection") => json["features"]
+                .as_array()
+                .ok_or("Invalid FeatureCollection".to_string())?
+                .iter()
+                .collect(),
+            Some("Feature") => vec![json],
+            _ => return Err("GeoJSON Feature or FeatureCollection expected".to_string()),
+        };
+        let mut features = Vec::new();
+        let mut extents = Vec::new();
+        for (i, json_feature) in json_features.iter().enumerate() {
+            if json_feature["geometry"].is_null() {
+                continue;
+            }
+            let geometry = match json_to_geometry(&json_feature["geometry"]) {
+                Ok(geometry) => geometry,
+                Err(e) => {
+                    warn!("Skipping feature #{}: {}", i, e);
+                    continue;
+                }
+            };
+            let extent = match geometry_extent(&geometry) {
+                Some(extent) => extent,
+                None => continue,
+            };
+            let attributes = match json_feature["properties"].as_object() {
+                Some(properties) => properties
+                    .iter()
+                    .filter_map(|(key, value)| {
+                        json_value(value).map(|value| FeatureAttr {
+                            key: key.clone(),
+                            value: value,
+                        })
+                    })
+                    .collect(),
+                None => Vec::new(),
+            };
+            features.push(JsonFeature {
+                fid: json_feature["id"].as_u64(),
+                attributes: attributes,
+                geometry: geometry,
+            });
+            extents.push(extent);
+        }
+        Ok(GeoJsonData {
+            features: features,
+            index: PackedRTree::build(&extents, INDEX_NODE_SIZE),
+        })
+    }
+}
+
+struct GeoJsonFeature<'a> {
+    layer: &'a Layer,
+    feature: &'a JsonFeature,
+    grid_srid: i32,
+    transform: Transform,
+}
+
+impl<'a> Feature for GeoJsonFeature<'a> {
+    fn fid(&self) -> Option<u64> {
+        match self.layer.fid_field {
+            Some(ref fid_field) => attribute_fid(&self.feature.attributes, fid_field),
+            None => self.feature.fid,
+        }
+    }
+    fn attributes(&self) -> Vec<FeatureAttr> {
+        self.feature.attributes.clone()
+    }
+    fn geometry(&self) -> Result<GeometryType, String> {
+        self.transform
+            .geometry(&self.feature.geometry, Some(self.grid_srid))
+    }
+}
+
+impl GeoJsonDatasource {
+    pub fn new(path: &str) -> GeoJsonDatasource {
+        GeoJsonDatasource {
+            path: path.to_string(),
+            data: None,
+        }
+    }
+    /// Layer name derived from file name
+    fn layer_name(&self) -> String {
+        Path::new(&self.path)
+            .file_stem()
+            .map_or("geojson".to_string(), |stem| stem.to_string_lossy().to_string())
+    }
+    /// Apply function on loaded data or read file for a single call
+    fn with_data<T, F>(&self, f: F) -> Option<T>
+    where
+        F: Fn(&GeoJsonData) -> T,
+    {
+        match self.data {
+            Some(ref data) => Some(f(data)),
+            None => match GeoJsonData::read(&self.path) {
+                Ok(ref data) => Some(f(data)),
+                Err(e) => {
+                    error!("{}", e);
+                    None
+                }
+            },
+        }
+    }
+}
+
+impl DatasourceInput for GeoJsonDatasource {
+    /// New instance with features loaded into memory
+    fn connected(&self) -> Result<GeoJsonDatasource, String> {
+        let data = GeoJsonData::read(&self.path)?;
+        info!("{}: {} features loaded", self.path, data.features.len());
+        Ok(GeoJsonDatasource {
+            path: self.path.clone(),
+            data: Some(data),
+        })
+    }
+    fn detect_layers(&self, _detect_geometry_types: bool) -> Vec<Layer> {
+        let mut layer = Layer::new(&self.layer_name());
+        layer.table_name = Some(layer.name.clone());
+        layer.srid = Some(GEOJSON_SRID);
+        layer.geometry_type = self.with_data(|data| {
+            let types: Vec<&str> = data.features
+                .iter()
+                .map(|f| match f.geometry {
+                    GeometryType::Point(_) => "POINT",
+                    GeometryType::MultiPoint(_) => "MULTIPOINT",
+                    GeometryType::LineString(_) => "LINESTRING",
+                    GeometryType::MultiLineString(_) => "MULTILINESTRING",
+                    GeometryType::Polygon(_) => "POLYGON",
+                    GeometryType::MultiPolygon(_) => "MULTIPOLYGON",
+                    GeometryType::GeometryCollection(_) => "GEOMETRYCOLLECTION",
+                })
+                .collect();
+            match types.first() {
+                Some(t) if types.iter().all(|t2| t2 == t) => t.to_string(),
+                _ => "GEOMETRY".to_string(),
+            }
+        });
+        vec![layer]
+    }
+    /// Return property names and inferred types (string, integer, double, bool)
+    fn detect_data_columns(&self, _layer: &Layer, _sql: Option<&String>) -> Vec<(String, String)> {
+        self.with_data(|data| {
+            let mut cols: Vec<(String, String)> = Vec::new();
+            for attr in data.features.iter().flat_map(|f| f.attributes.iter()) {
+                let ty = value_type(&attr.value);
+                match cols.iter().position(|&(ref name, _)| *name == attr.key) {
+                    Some(idx) => {
+                        let merged = match (cols[idx].1.as_str(), ty) {
+                            (t1, t2) if t1 == t2 => None,
+                            ("integer", "double") | ("double", "integer") => Some("double"),
+                            _ => Some("string"),
+                        };
+                        if let Some(merged) = merged {
+                            cols[idx].1 = merged.to_string();
+                        }
+                    }
+                    None => cols.push((attr.key.clone(), ty.to_string())),
+                }
+            }
+            cols
+        }).unwrap_or(Vec::new())
+    }
+    /// Projected extent
+    fn extent_from_wgs84(&self, extent: &Extent, dest_srid: i32) -> Option<Extent> {
+        Transform::new(GEOJSON_SRID, dest_srid)
+            .map(|t| t.extent(extent))
+            .map_err(|e| warn!("{}", e))
+            .ok()
+    }
+    /// Detect extent of layer (in WGS84)
+    fn layer_extent(&self, layer: &Layer) -> Option<Extent> {
+        let srid = layer.srid.unwrap_or(GEOJSON_SRID);
+        let transform = Transform::new(srid, 4326)
+            .map_err(|e| warn!("Layer '{}': {}", layer.name, e))
+            .ok()?;
+        self.with_data(|data| data.index.extent())
+            .and_then(|extent| extent)
+            .map(|extent| transform.extent(&extent))
+    }
+    fn prepare_queries(&mut self, layer: &Layer, grid_srid: i32) {
+        let srid = layer.srid.unwrap_or(GEOJSON_SRID);
+        if srid != grid_srid {
+            info!(
+                "Layer '{}': Reprojecting geometry from SRID {} to {}",
+                layer.name, srid, grid_srid
+            );
+        }
+    }
+    fn retrieve_features<F>(
+        &self,
+        layer: &Layer,
+        extent: &Extent,
+        zoom: u8,
+        grid: &Grid,
+        mut read: F,
+    ) where
+        F: FnMut(&Feature),
+    {
+        let data = match self.data {
+            Some(ref data) => data,
+            None => return,
+        };
+        let srid = layer.srid.unwrap_or(GEOJSON_SRID);
+        let transform = match Transform::new(srid, grid.srid) {
+            Ok(transform) => transform,
+            Err(e) => {
+                error!("Layer '{}': {}", layer.name, e);
+                return;
+            }
+        };
+        let bbox = layer_bbox(layer, extent, zoom, grid, srid);
+        let mut count = FeatureCount::new(layer, zoom);
+        for idx in data.index.search(&bbox) {
+            let feature = GeoJsonFeature {
+                layer: layer,
+                feature: &data.features[idx as usize],
+                grid_srid: grid.srid,
+                transform: transform,
+            };
+            read(&feature);
+            if !count.add() {
+                break;
+            }
+        }
+    }
+}
+
+impl<'a> Config<'a, DatasourceCfg> for GeoJsonDatasource {
+    fn from_config(ds_cfg: &DatasourceCfg) -> Result<Self, String> {
+        let path = ds_cfg
+            .geojson
+            .as_ref()
+            .or(ds_cfg.path.as_ref())
+            .ok_or("GeoJSON file name missing".to_string())?;
+        Ok(GeoJsonDatasource::new(path))
+    }
+
+    fn gen_config() -> String {
+        let toml = r#"
+[[datasource]]
+name = "geojson"
+# GeoJSON file loaded into memory
+geojson = "<filename>"
+"#;
+        toml.to_string()
+    }
+    fn gen_runtime_config(&self) -> String {
+        format!(
+            r#"
+[[datasource]]
+geojson = "{}"
+"#,
+            self.path
+        )
+    }
+}
@@ -0,0 +1,112 @@
+//
+// Copyright (c) Pirmin Kalberer. All rights reserved.
+// Licensed under the MIT License. See LICENSE file in the project root for full license information.
+//
+
+use core::feature::FeatureAttrValType;
+use core::geom::GeometryType;
+use core::grid::{Extent, Grid};
+use core::layer::Layer;
+use datasource::geojson_file::GeoJsonDatasource;
+use datasource::DatasourceInput;
+use std::env;
+use std::fs::File;
+use std::io::Write;
+
+fn create_geojson(name: &str) -> String {
+    let path = env::temp_dir().join(name);
+    let mut file = File::create(&path).unwrap();
+    file.write_all(
+        br#"{"type": "FeatureCollection", "features": [
+  {"type": "Feature", "id": 1, "geometry": {"type": "Point", "coordinates": [8.5, 47.3]},
+   "properties": {"name": "Zurich", "population": 400000, "area": 87, "capital": false}},
+  {"type": "Feature", "id": 2, "geometry": {"type": "Point", "coordinates": [9.5, 47.5]},
+   "properties": {"name": "St. Gallen", "population": 75000, "area": 39.4, "capital": "no"}},
+  {"type": "Feature", "id": 3, "geometry": null, "properties": {"name": "Nowhere"}}
+]}"#,
+    ).unwrap();
+    path.to_str().unwrap().to_string()
+}
+
+#[test]
+fn test_detect_layers() {
+    let ds = GeoJsonDatasource::new(&create_geojson("t_rex_detect.geojson"));
+    let layers = ds.detect_layers(true);
+    assert_eq!(layers.len(), 1);
+    assert_eq!(layers[0].name, "t_rex_detect");
+    assert_eq!(layers[0].geometry_type, Some("POINT".to_string()));
+    assert_eq!(layers[0].srid, Some(4326));
+
+    let columns = ds.detect_data_columns(&layers[0], None);
+    assert_eq!(
+        columns,
+        vec![
+            ("area".to_string(), "double".to_string()),
+            ("capital".to_string(), "string".to_string()),
+            ("name".to_string(), "string".to_string()),
+            ("population".to_string(), "integer".to_string()),
+        ]
+    );
+
+    let extent = ds.layer_extent(&layers[0]).unwrap();
+    assert_eq!(
+        (extent.minx, extent.miny, extent.maxx, extent.maxy),
+        (8.5, 47.3, 9.5, 47.5)
+    );
+}
+
+#[test]
+fn test_retrieve_features() {
+    let ds = GeoJsonDatasource::new(&create_geojson("t_rex_retrieve.geojson")).connected().unwrap();
+    let mut layer = Layer::new("places");
+    layer.srid = Some(4326);
+    let grid = Grid::wgs84();
+    let extent = Extent {
+        minx: 8.0,
+        miny: 47.0,
+        maxx: 9.0,
+        maxy: 48.0,
+    };
+    let mut reccnt = 0;
+    ds.retrieve_features(&layer, &extent, 10, &grid, |feat| {
+        assert_eq!(feat.fid(), Some(1));
+        assert_eq!(
+            feat.attributes()
+                .iter()
+                .find(|attr| attr.key == "population")
+                .map(|attr| attr.value.clone()),
+            Some(FeatureAttrValType::Int(400000))
+        );
+        match feat.geometry() {
+            Ok(GeometryType::Point(p)) => assert_eq!((p.x, p.y), (8.5, 47.3)),
+            _ => panic!("Point expected"),
+        }
+        reccnt += 1;
+    });
+    assert_eq!(reccnt, 1);
+
+    let world = Extent {
+        minx: -180.0,
+        miny: -90.0,
+        maxx: 180.0,
+        maxy: 90.0,
+    };
+    let mut reccnt = 0;
+    ds.retrieve_features(&layer, &world, 10, &grid, |_| reccnt += 1);
+    assert_eq!(reccnt, 2);
+
+    layer.query_limit = Some(1);
+    let mut reccnt = 0;
+    ds.retrieve_features(&layer, &world, 10, &grid, |_| reccnt += 1);
+    assert_eq!(reccnt, 1);
+}
+
+#[test]
+fn test_read_errors() {
+    let path = env::temp_dir().join("t_rex_invalid.geojson");
+    File::create(&path).unwrap().write_all(b"{\"type\": ").unwrap();
+    let ds = GeoJsonDatasource::new(path.to_str().unwrap());
+    assert!(ds.connected().is_err());
+    let ds = GeoJsonDatasource::new(env::temp_dir().join("t_rex_missing.geojson").to_str().unwrap());
+    assert!(ds.connected().is_err());
+}
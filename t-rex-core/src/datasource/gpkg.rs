@@ -0,0 +1,473 @@
+//
+// Copyright (c) Pirmin Kalberer. All rights reserved.
+// Licensed under the MIT License. See LICENSE file in the project root for full license information.
+//
+
+//! GeoPackage datasource reading SQLite files directly
+
+use core::config::DatasourceCfg;
+use core::feature::{Feature, FeatureAttr, FeatureAttrValType};
+use core::geom::GeometryType;
+use core::grid::Extent;
+use core::grid::Grid;
+use core::layer::Layer;
+use core::Config;
+use datasource::datasource::{layer_bbox, FeatureCount};
+use datasource::transform::Transform;
+use datasource::wkb::gpkg_to_geometry;
+use datasource::DatasourceInput;
+use rusqlite::types::{ToSql, Value};
+use rusqlite::{Connection, OpenFlags, Row};
+use std::collections::BTreeMap;
+use std::ops::Deref;
+use std::sync::Mutex;
+
+pub struct GpkgDatasource {
+    pub path: String,
+    // Queries for all layers
+    queries: BTreeMap<String, GpkgQuery>,
+    /// Idle connections reused by queries
+    connections: Mutex<Vec<Connection>>,
+}
+
+/// Connection returned to the idle connections when dropped
+struct PooledConnection<'a> {
+    pool: &'a Mutex<Vec<Connection>>,
+    conn: Option<Connection>,
+}
+
+impl<'a> Deref for PooledConnection<'a> {
+    type Target = Connection;
+    fn deref(&self) -> &Connection {
+        self.conn.as_ref().unwrap()
+    }
+}
+
+impl<'a> Drop for PooledConnection<'a> {
+    fn drop(&mut self) {
+        if let Some(conn) = self.conn.take() {
+            self.pool
+                .lock()
+                .unwrap_or_else(|e| e.into_inner())
+                .push(conn);
+        }
+    }
+}
+
+/// Feature query of a layer
+#[derive(Clone, Debug)]
+struct GpkgQuery {
+    sql: String,
+    geometry_field: String,
+    srid: i32,
+    /// Query has bbox parameters for R-tree filtering
+    spatial_filter: bool,
+}
+
+fn quote_ident(name: &str) -> String {
+    format!("\"{}\"", name.replace("\"", "\"\""))
+}
+
+/// EPSG code of GeoPackage SRS
+fn epsg_code(srs_id: i32, organization: Option<String>, coordsys_id: Option<i32>) -> Option<i32> {
+    match (organization, coordsys_id) {
+        (Some(ref org), Some(code)) if org.to_uppercase() == "EPSG" => Some(code),
+        _ if srs_id > 0 => Some(srs_id),
+        _ => None,
+    }
+}
+
+impl GpkgDatasource {
+    pub fn new(path: &str) -> GpkgDatasource {
+        GpkgDatasource {
+            path: path.to_string(),
+            queries: BTreeMap::new(),
+            connections: Mutex::new(Vec::new()),
+        }
+    }
+    /// Idle connection or new read-only connection
+    fn open(&self) -> Result<PooledConnection, String> {
+        let idle = self.connections
+            .lock()
+            .unwrap_or_else(|e| e.into_inner())
+            .pop();
+        let conn = match idle {
+            Some(conn) => conn,
+            None => Connection::open_with_flags(&self.path, OpenFlags::SQLITE_OPEN_READ_ONLY)
+                .map_err(|e| format!("{}: {}", self.path, e))?,
+        };
+        Ok(PooledConnection {
+            pool: &self.connections,
+            conn: Some(conn),
+        })
+    }
+    /// Geometry column name and EPSG code of a feature table
+    fn geometry_column(conn: &Connection, table: &str) -> Result<(String, Option<i32>), String> {
+        let sql = "SELECT g.column_name, g.srs_id, s.organization, s.organization_coordsys_id \
+                   FROM gpkg_geometry_columns g \
+                   LEFT JOIN gpkg_spatial_ref_sys s ON g.srs_id = s.srs_id \
+                   WHERE g.table_name = ?";
+        conn.query_row(sql, &[&table as &ToSql], |row| {
+            Ok((
+                row.get::<_, String>(0)?,
+                epsg_code(row.get(1)?, row.get(2)?, row.get(3)?),
+            ))
+        }).map_err(|e| format!("Table '{}' not found in gpkg_geometry_columns: {}", table, e))
+    }
+    fn has_table(conn: &Connection, name: &str) -> bool {
+        let sql = "SELECT count(*) FROM sqlite_master WHERE name = ?";
+        conn.query_row(sql, &[&name as &ToSql], |row| row.get::<_, i64>(0))
+            .map(|cnt| cnt > 0)
+            .unwrap_or(false)
+    }
+    fn build_query(&self, layer: &Layer, grid_srid: i32) -> Result<GpkgQuery, String> {
+        let table = layer
+            .table_name
+            .as_ref()
+            .ok_or(format!("Layer '{}': table_name missing", layer.name))?;
+        let conn = self.open()?;
+        let (geometry_field, srid) = Self::geometry_column(&conn, table)?;
+        let geometry_field = layer.geometry_field.clone().unwrap_or(geometry_field);
+        let srid = match layer.srid.or(srid) {
+            Some(srid) => srid,
+            None => {
+                warn!(
+                    "Layer '{}': Unknown SRS - assuming SRID {}",
+                    layer.name, grid_srid
+                );
+                grid_srid
+            }
+        };
+        let rtree = format!("rtree_{}_{}", table, geometry_field);
+        let spatial_filter = Self::has_table(&conn, &rtree);
+        let mut sql = format!("SELECT * FROM {}", quote_ident(table));
+        if spatial_filter {
+            sql.push_str(&format!(
+                " WHERE rowid IN (SELECT id FROM {} WHERE minx <= ? AND maxx >= ? AND miny <= ? AND maxy >= ?)",
+                quote_ident(&rtree)
+            ));
+        } else {
+            warn!(
+                "Layer '{}': No spatial index - reading all features of each tile",
+                layer.name
+            );
+        }
+        Ok(GpkgQuery {
+            sql: sql,
+            geometry_field: geometry_field,
+            srid: srid,
+            spatial_filter: spatial_filter,
+        })
+    }
+}
+
+struct GpkgFeature<'a> {
+    layer: &'a Layer,
+    columns: &'a Vec<String>,
+    geometry_idx: Option<usize>,
+    row: &'a Row<'a>,
+    grid_srid: i32,
+    transform: Transform,
+}
+
+impl<'a> Feature for GpkgFeature<'a> {
+    fn fid(&self) -> Option<u64> {
+        let fid_field = self.layer.fid_field.as_ref()?;
+        let idx = self.columns.iter().position(|col| col == fid_field)?;
+        match self.row.get::<_, Value>(idx) {
+            Ok(Value::Integer(v)) => Some(v as u64),
+            _ => None,
+        }
+    }
+    fn attributes(&self) -> Vec<FeatureAttr> {
+        let mut attrs = Vec::new();
+        for (i, col) in self.columns.iter().enumerate() {
+            if Some(i) == self.geometry_idx {
+                continue;
+            }
+            let val = match self.row.get::<_, Value>(i) {
+                Ok(Value::Text(v)) => Some(FeatureAttrValType::String(v)),
+                Ok(Value::Integer(v)) => Some(FeatureAttrValType::Int(v)),
+                Ok(Value::Real(v)) => Some(FeatureAttrValType::Double(v)),
+                Ok(Value::Null) | Ok(Value::Blob(_)) => None,
+                Err(err) => {
+                    warn!(
+                        "Layer '{}' - skipping field '{}': {}",
+                        self.layer.name, col, err
+                    );
+                    None
+                }
+            };
+            if let Some(val) = val {
+                attrs.push(FeatureAttr {
+                    key: col.clone(),
+                    value: val,
+                });
+            }
+        }
+        attrs
+    }
+    fn geometry(&self) -> Result<GeometryType, String> {
+        let idx = self.geometry_idx
+            .ok_or("Geometry column not found".to_string())?;
+        let blob = match self.row.get::<_, Value>(idx) {
+            Ok(Value::Blob(blob)) => blob,
+            Ok(_) => return Err("Geometry column is not a blob".to_string()),
+            Err(e) => return Err(e.to_string()),
+        };
+        let geom = gpkg_to_geometry(&blob, Some(self.grid_srid))?;
+        if self.transform == Transform::Identity {
+            return Ok(geom);
+        }
+        self.transform.geometry(&geom, Some(self.grid_srid))
+    }
+}
+
+impl DatasourceInput for GpkgDatasource {
+    fn connected(&self) -> Result<GpkgDatasource, String> {
+        self.open()
+            .map_err(|e| format!("Unable to open GeoPackage {}", e))?;
+        Ok(GpkgDatasource::new(&self.path))
+    }
+    fn detect_layers(&self, _detect_geometry_types: bool) -> Vec<Layer> {
+        info!("Detecting layers from gpkg_geometry_columns");
+        let mut layers: Vec<Layer> = Vec::new();
+        let conn = match self.open() {
+            Ok(conn) => conn,
+            Err(e) => {
+                error!("{}", e);
+                return layers;
+            }
+        };
+        let sql = "SELECT c.table_name, g.column_name, g.geometry_type_name, g.srs_id, \
+                   s.organization, s.organization_coordsys_id \
+                   FROM gpkg_contents c \
+                   JOIN gpkg_geometry_columns g ON c.table_name = g.table_name \
+                   LEFT JOIN gpkg_spatial_ref_sys s ON g.srs_id = s.srs_id \
+                   WHERE c.data_type = 'features' ORDER BY c.table_name";
+        let result = conn.prepare(sql).and_then(|mut stmt| {
+            let rows = stmt.query_map(&[] as &[&ToSql], |row| {
+                let table_name: String = row.get(0)?;
+                let mut layer = Layer::new(&table_name);
+                layer.table_name = Some(table_name);
+                layer.geometry_field = Some(row.get(1)?);
+                layer.geometry_type = Some(row.get::<_, String>(2)?.to_uppercase());
+                layer.srid = epsg_code(row.get(3)?, row.get(4)?, row.get(5)?);
+                Ok(layer)
+            })?;
+            rows.collect::<Result<Vec<Layer>, _>>()
+        });
+        match result {
+            Ok(detected) => layers = detected,
+            Err(e) => error!("{}: {}", self.path, e),
+        }
+        layers
+    }
+    /// Return column field names and Rust compatible type conversion - without geometry column
+    fn detect_data_columns(&self, layer: &Layer, _sql: Option<&String>) -> Vec<(String, String)> {
+        let table = match layer.table_name {
+            Some(ref table) => table,
+            None => return Vec::new(),
+        };
+        let conn = match self.open() {
+            Ok(conn) => conn,
+            Err(e) => {
+                error!("{}", e);
+                return Vec::new();
+            }
+        };
+        let geometry_field = layer
+            .geometry_field
+            .clone()
+            .or_else(|| Self::geometry_column(&conn, table).ok().map(|(col, _)| col));
+        let sql = format!("PRAGMA table_info({})", quote_ident(table));
+        let result = conn.prepare(&sql).and_then(|mut stmt| {
+            let rows = stmt.query_map(&[] as &[&ToSql], |row| row.get::<_, String>(1))?;
+            rows.collect::<Result<Vec<String>, _>>()
+        });
+        match result {
+            Ok(cols) => cols.into_iter()
+                .filter(|col| Some(col) != geometry_field.as_ref())
+                .map(|col| (col, "".to_string()))
+                .collect(),
+            Err(e) => {
+                error!("Layer '{}': {}", layer.name, e);
+                Vec::new()
+            }
+        }
+    }
+    /// Projected extent
+    fn extent_from_wgs84(&self, extent: &Extent, dest_srid: i32) -> Option<Extent> {
+        Transform::new(4326, dest_srid)
+            .map(|t| t.extent(extent))
+            .map_err(|e| warn!("{}", e))
+            .ok()
+    }
+    /// Detect extent of layer (in WGS84)
+    fn layer_extent(&self, layer: &Layer) -> Option<Extent> {
+        let table = layer.table_name.as_ref()?;
+        let conn = self.open().map_err(|e| error!("{}", e)).ok()?;
+        let (_, srid) = Self::geometry_column(&conn, table).ok()?;
+        let srid = layer.srid.or(srid)?;
+        let sql = "SELECT min_x, min_y, max_x, max_y FROM gpkg_contents WHERE table_name = ?";
+        let extent = conn.query_row(sql, &[table as &ToSql], |row| {
+            Ok(Extent {
+                minx: row.get(0)?,
+                miny: row.get(1)?,
+                maxx: row.get(2)?,
+                maxy: row.get(3)?,
+            })
+        });
+        match extent {
+            Ok(extent) => match Transform::new(srid, 4326) {
+                Ok(transform) => Some(transform.extent(&extent)),
+                Err(e) => {
+                    warn!("Layer '{}': {}", layer.name, e);
+                    None
+                }
+            },
+            Err(e) => {
+                warn!("Layer '{}': Unable to get extent: {}", layer.name, e);
+                None
+            }
+        }
+    }
+    fn prepare_queries(&mut self, layer: &Layer, grid_srid: i32) {
+        if !layer.query.is_empty() {
+            warn!(
+                "Layer '{}': Custom queries are not supported by GeoPackage datasource",
+                layer.name
+            );
+        }
+        match self.build_query(layer, grid_srid) {
+            Ok(query) => {
+                if query.srid != grid_srid {
+                    info!(
+                        "Layer '{}': Reprojecting geometry from SRID {} to {}",
+                        layer.name, query.srid, grid_srid
+                    );
+                }
+                debug!("Query for layer '{}': {}", layer.name, query.sql);
+                self.queries.insert(layer.name.clone(), query);
+            }
+            Err(e) => error!("Layer '{}': {}", layer.name, e),
+        }
+    }
+    fn retrieve_features<F>(
+        &self,
+        layer: &Layer,
+        extent: &Extent,
+        zoom: u8,
+        grid: &Grid,
+        mut read: F,
+    ) where
+        F: FnMut(&Feature),
+    {
+        let query = match self.queries.get(&layer.name) {
+            Some(query) => query,
+            None => return,
+        };
+        let transform = match Transform::new(query.srid, grid.srid) {
+            Ok(transform) => transform,
+            Err(e) => {
+                error!("Layer '{}': {}", layer.name, e);
+                return;
+            }
+        };
+        let conn = match self.open() {
+            Ok(conn) => conn,
+            Err(e) => {
+                error!("{}", e);
+                return;
+            }
+        };
+
+        let bbox = layer_bbox(layer, extent, zoom, grid, query.srid);
+
+        let query_limit = layer.query_limit(zoom);
+        let sql = match query_limit {
+            Some(limit) => format!("{} LIMIT {}", query.sql, limit),
+            None => query.sql.clone(),
+        };
+        let mut stmt = match conn.prepare(&sql) {
+            Ok(stmt) => stmt,
+            Err(e) => {
+                error!("Layer '{}': {}", layer.name, e);
+                error!("Query: {}", sql);
+                return;
+            }
+        };
+        let columns: Vec<String> = stmt.column_names()
+            .iter()
+            .map(|col| col.to_string())
+            .collect();
+        let geometry_idx = columns.iter().position(|col| *col == query.geometry_field);
+        let params: Vec<&ToSql> = if query.spatial_filter {
+            vec![&bbox.maxx, &bbox.minx, &bbox.maxy, &bbox.miny]
+        } else {
+            Vec::new()
+        };
+        let mut rows = match stmt.query(&params) {
+            Ok(rows) => rows,
+            Err(e) => {
+                error!("Layer '{}': {}", layer.name, e);
+                return;
+            }
+        };
+        debug!("Reading features in layer {}", layer.name);
+        let mut count = FeatureCount::new(layer, zoom);
+        loop {
+            match rows.next() {
+                Ok(Some(row)) => {
+                    let feature = GpkgFeature {
+                        layer: layer,
+                        columns: &columns,
+                        geometry_idx: geometry_idx,
+                        row: row,
+                        grid_srid: grid.srid,
+                        transform: transform,
+                    };
+                    read(&feature);
+                    if !count.add() {
+                        break;
+                    }
+                }
+                Ok(None) => break,
+                Err(e) => {
+                    error!("Layer '{}': {}", layer.name, e);
+                    break;
+                }
+            }
+        }
+    }
+}
+
+impl<'a> Config<'a, DatasourceCfg> for GpkgDatasource {
+    fn from_config(ds_cfg: &DatasourceCfg) -> Result<Self, String> {
+        let path = ds_cfg
+            .gpkg
+            .as_ref()
+            .or(ds_cfg.path.as_ref())
+            .ok_or("GeoPackage file name missing".to_string())?;
+        Ok(GpkgDatasource::new(path))
+    }
+
+    fn gen_config() -> String {
+        let toml = r#"
+[[datasource]]
+name = "gpkg"
+# GeoPackage file
+gpkg = "<filename>"
+"#;
+        toml.to_string()
+    }
+    fn gen_runtime_config(&self) -> String {
+        format!(
+            r#"
+[[datasource]]
+gpkg = "{}"
+"#,
+            self.path
+        )
+    }
+}
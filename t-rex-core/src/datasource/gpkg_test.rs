@@ -0,0 +1,159 @@
+//
+// Copyright (c) Pirmin Kalberer. All rights reserved.
+// Licensed under the MIT License. See LICENSE file in the project root for full license information.
+//
+
+use core::feature::FeatureAttrValType;
+use core::geom::GeometryType;
+use core::grid::{Extent, Grid};
+use core::layer::Layer;
+use datasource::gpkg::GpkgDatasource;
+use datasource::DatasourceInput;
+use rusqlite::types::ToSql;
+use rusqlite::Connection;
+use std::env;
+use std::fs;
+
+/// GeoPackage binary point (little endian, without envelope)
+fn gpkg_point(x: f64, y: f64, srs_id: i32) -> Vec<u8> {
+    let mut gpb = vec![b'G', b'P', 0, 1];
+    gpb.extend((0..4).map(|i| (srs_id >> (8 * i)) as u8));
+    gpb.extend(&[1, 1, 0, 0, 0]);
+    gpb.extend((0..8).map(|i| (x.to_bits() >> (8 * i)) as u8));
+    gpb.extend((0..8).map(|i| (y.to_bits() >> (8 * i)) as u8));
+    gpb
+}
+
+/// Create GeoPackage with WGS84 table `places`
+fn create_gpkg(name: &str, rtree: bool) -> String {
+    let path = env::temp_dir().join(name);
+    let _ = fs::remove_file(&path);
+    let conn = Connection::open(&path).unwrap();
+    conn.execute_batch(
+        "CREATE TABLE gpkg_spatial_ref_sys (srs_name TEXT NOT NULL, srs_id INTEGER PRIMARY KEY,
+           organization TEXT NOT NULL, organization_coordsys_id INTEGER NOT NULL,
+           definition TEXT NOT NULL, description TEXT);
+         INSERT INTO gpkg_spatial_ref_sys VALUES ('WGS 84', 4326, 'EPSG', 4326, '', NULL);
+         CREATE TABLE gpkg_contents (table_name TEXT NOT NULL PRIMARY KEY, data_type TEXT NOT NULL,
+           identifier TEXT, description TEXT DEFAULT '', last_change DATETIME,
+           min_x DOUBLE, min_y DOUBLE, max_x DOUBLE, max_y DOUBLE, srs_id INTEGER);
+         INSERT INTO gpkg_contents VALUES ('places', 'features', 'places', '', NULL, 8.5, 47.3, 9.5, 47.5, 4326);
+         CREATE TABLE gpkg_geometry_columns (table_name TEXT NOT NULL, column_name TEXT NOT NULL,
+           geometry_type_name TEXT NOT NULL, srs_id INTEGER NOT NULL, z TINYINT NOT NULL, m TINYINT NOT NULL);
+         INSERT INTO gpkg_geometry_columns VALUES ('places', 'geom', 'POINT', 4326, 0, 0);
+         CREATE TABLE places (fid INTEGER PRIMARY KEY AUTOINCREMENT, geom POINT, name TEXT, population INTEGER);",
+    ).unwrap();
+    for &(fid, x, y, name, population) in &[
+        (1, 8.5, 47.3, "Zurich", 400000),
+        (2, 9.5, 47.5, "St. Gallen", 75000),
+    ] {
+        conn.execute(
+            "INSERT INTO places VALUES (?, ?, ?, ?)",
+            &[
+                &fid as &ToSql,
+                &gpkg_point(x, y, 4326),
+                &name,
+                &population,
+            ],
+        ).unwrap();
+    }
+    if rtree {
+        conn.execute_batch(
+            "CREATE VIRTUAL TABLE rtree_places_geom USING rtree(id, minx, maxx, miny, maxy);
+             INSERT INTO rtree_places_geom VALUES (1, 8.5, 8.5, 47.3, 47.3);
+             INSERT INTO rtree_places_geom VALUES (2, 9.5, 9.5, 47.5, 47.5);",
+        ).unwrap();
+    }
+    path.to_str().unwrap().to_string()
+}
+
+#[test]
+fn test_detect_layers() {
+    let ds = GpkgDatasource::new(&create_gpkg("t_rex_detect.gpkg", true));
+    let layers = ds.detect_layers(true);
+    assert_eq!(layers.len(), 1);
+    assert_eq!(layers[0].name, "places");
+    assert_eq!(layers[0].table_name, Some("places".to_string()));
+    assert_eq!(layers[0].geometry_field, Some("geom".to_string()));
+    assert_eq!(layers[0].geometry_type, Some("POINT".to_string()));
+    assert_eq!(layers[0].srid, Some(4326));
+
+    let cols = ds.detect_data_columns(&layers[0], None);
+    assert_eq!(
+        cols,
+        vec![
+            ("fid".to_string(), "".to_string()),
+            ("name".to_string(), "".to_string()),
+            ("population".to_string(), "".to_string()),
+        ]
+    );
+
+    let extent = ds.layer_extent(&layers[0]).unwrap();
+    assert_eq!(extent.minx, 8.5);
+    assert_eq!(extent.maxy, 47.5);
+}
+
+fn retrieve_names(ds: &GpkgDatasource, layer: &Layer, extent: &Extent) -> Vec<String> {
+    let grid = Grid::web_mercator();
+    let mut names = Vec::new();
+    ds.retrieve_features(layer, extent, 10, &grid, |feat| {
+        let attrs = feat.attributes();
+        match attrs.iter().find(|attr| attr.key == "name").unwrap().value {
+            FeatureAttrValType::String(ref name) => names.push(name.clone()),
+            _ => panic!("String expected"),
+        }
+        match feat.geometry().unwrap() {
+            GeometryType::Point(p) => {
+                assert!(p.x > 940000.0 && p.x < 1060000.0);
+                assert_eq!(p.srid, Some(3857));
+            }
+            _ => panic!("Point expected"),
+        }
+    });
+    names
+}
+
+#[test]
+fn test_retrieve_features() {
+    // Extent around Zurich
+    let extent = Extent {
+        minx: 940000.0,
+        miny: 5980000.0,
+        maxx: 960000.0,
+        maxy: 6000000.0,
+    };
+    for &(name, rtree) in &[("t_rex_rtree.gpkg", true), ("t_rex_nortree.gpkg", false)] {
+        let mut ds = GpkgDatasource::new(&create_gpkg(name, rtree));
+        let mut layer = Layer::new("places");
+        layer.table_name = Some("places".to_string());
+        layer.fid_field = Some("fid".to_string());
+        ds.prepare_queries(&layer, 3857);
+
+        let names = retrieve_names(&ds, &layer, &extent);
+        if rtree {
+            assert_eq!(names, vec!["Zurich".to_string()]);
+        } else {
+            assert_eq!(names.len(), 2);
+        }
+
+        layer.query_limit = Some(1);
+        let world = Grid::web_mercator().tile_extent(0, 0, 0);
+        assert_eq!(retrieve_names(&ds, &layer, &world).len(), 1);
+    }
+}
+
+#[test]
+fn test_config_errors() {
+    use core::config::DatasourceCfg;
+    use core::{parse_config, Config};
+
+    let cfg: DatasourceCfg = parse_config("name = \"gpkg\"".to_string(), "").unwrap();
+    assert_eq!(
+        GpkgDatasource::from_config(&cfg).err(),
+        Some("GeoPackage file name missing".to_string())
+    );
+    let path = env::temp_dir().join("t_rex_missing.gpkg");
+    let _ = fs::remove_file(&path);
+    let ds = GpkgDatasource::new(path.to_str().unwrap());
+    assert!(ds.connected().is_err());
+}
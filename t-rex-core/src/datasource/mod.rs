@@ -3,10 +3,39 @@
 // Licensed under the MIT License. See LICENSE file in the project root for full license information.
 //
 
+pub mod csv_file;
+#[cfg(test)]
+mod csv_file_test;
 pub mod datasource;
+pub mod flatgeobuf;
+#[cfg(test)]
+mod flatgeobuf_test;
+pub mod geojson_file;
+#[cfg(test)]
+mod geojson_file_test;
+pub mod gpkg;
+#[cfg(test)]
+mod gpkg_test;
 pub mod postgis;
 #[cfg(test)]
 mod postgis_test;
+pub mod rtree;
+#[cfg(test)]
+mod rtree_test;
+pub mod tiles;
+#[cfg(test)]
+mod tiles_test;
+pub mod transform;
+#[cfg(test)]
+mod transform_test;
+pub mod wkb;
+#[cfg(test)]
+mod wkb_test;
 
+pub use self::csv_file::CsvDatasource;
 pub use self::datasource::{DatasourceInput, DummyDatasource};
+pub use self::flatgeobuf::FgbDatasource;
+pub use self::geojson_file::GeoJsonDatasource;
+pub use self::gpkg::GpkgDatasource;
 pub use self::postgis::PostgisInput;
+pub use self::tiles::TilesDatasource;
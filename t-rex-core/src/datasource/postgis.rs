@@ -8,11 +8,12 @@ use core::feature::{Feature, FeatureAttr, FeatureAttrValType};
 use core::geom::*;
 use core::grid::Extent;
 use core::grid::Grid;
-use core::layer::Layer;
+use core::layer::{Layer, LayerParam, ParamType, ParamValue};
 use core::Config;
 use datasource::DatasourceInput;
 use env;
 use fallible_iterator::FallibleIterator;
+use postgres;
 use postgres::rows::Row;
 use postgres::tls::native_tls::NativeTls;
 use postgres::types::{self, FromSql, ToSql, Type};
@@ -20,7 +21,7 @@ use r2d2;
 use r2d2_postgres::{PostgresConnectionManager, TlsMode};
 use std;
 use std::collections::BTreeMap;
-use std::error::Error;
+use std::time::Duration;
 
 impl GeometryType {
     pub fn from_geom_field(row: &Row, idx: &str, type_name: &str) -> Result<GeometryType, String> {
@@ -52,12 +53,32 @@ impl GeometryType {
     }
 }
 
+/// Hyphenated string representation of a binary UUID
+fn uuid_to_string(raw: &[u8]) -> Result<String, Box<std::error::Error + Sync + Send>> {
+    if raw.len() != 16 {
+        let err: Box<std::error::Error + Sync + Send> = "invalid UUID length".into();
+        return Err(err);
+    }
+    let hex: Vec<String> = raw.iter().map(|b| format!("{:02x}", b)).collect();
+    Ok(format!(
+        "{}-{}-{}-{}-{}",
+        hex[0..4].concat(),
+        hex[4..6].concat(),
+        hex[6..8].concat(),
+        hex[8..10].concat(),
+        hex[10..16].concat()
+    ))
+}
+
 impl FromSql for FeatureAttrValType {
     fn accepts(ty: &Type) -> bool {
         match ty {
             &types::VARCHAR
             | &types::TEXT
+            | &types::BPCHAR
+            | &types::NAME
             | &types::CHAR_ARRAY
+            | &types::UUID
             | &types::FLOAT4
             | &types::FLOAT8
             | &types::INT2
@@ -69,9 +90,10 @@ impl FromSql for FeatureAttrValType {
     }
     fn from_sql(ty: &Type, raw: &[u8]) -> Result<Self, Box<std::error::Error + Sync + Send>> {
         match ty {
-            &types::VARCHAR | &types::TEXT | &types::CHAR_ARRAY => {
+            &types::VARCHAR | &types::TEXT | &types::BPCHAR | &types::NAME | &types::CHAR_ARRAY => {
                 <String>::from_sql(ty, raw).and_then(|v| Ok(FeatureAttrValType::String(v)))
             }
+            &types::UUID => uuid_to_string(raw).map(FeatureAttrValType::String),
             &types::FLOAT4 => {
                 <f32>::from_sql(ty, raw).and_then(|v| Ok(FeatureAttrValType::Float(v)))
             }
@@ -165,6 +187,12 @@ pub enum QueryParam {
     Zoom,
     PixelWidth,
     ScaleDenominator,
+    TileX,
+    TileY,
+    BufferSize,
+    MvtBuffer,
+    /// Request parameter with index in `layer.params`
+    Param(usize),
 }
 
 #[derive(Clone, Debug)]
@@ -173,23 +201,121 @@ pub struct SqlQuery {
     pub params: Vec<QueryParam>,
 }
 
+/// PostgreSQL type of request parameter
+fn param_sql_type(param_type: ParamType) -> &'static str {
+    match param_type {
+        ParamType::Int => "INT8",
+        ParamType::Float => "FLOAT8",
+        ParamType::Text => "TEXT",
+        ParamType::Bool => "BOOLEAN",
+    }
+}
+
+/// Request parameter value for binding (typed NULL if undefined)
+fn param_sql_value(param_type: ParamType, value: &ParamValue) -> Box<ToSql> {
+    match value {
+        &ParamValue::Int(v) => Box::new(v),
+        &ParamValue::Float(v) => Box::new(v),
+        &ParamValue::Text(ref v) => Box::new(v.clone()),
+        &ParamValue::Bool(v) => Box::new(v),
+        &ParamValue::Null => match param_type {
+            ParamType::Int => Box::new(None::<i64>),
+            ParamType::Float => Box::new(None::<f64>),
+            ParamType::Text => Box::new(None::<String>),
+            ParamType::Bool => Box::new(None::<bool>),
+        },
+    }
+}
+
+/// Quoted SQL string literal
+fn sql_literal(value: &str) -> String {
+    format!("'{}'", value.replace('\'', "''"))
+}
+
+/// TLS mode of database connections
+#[derive(PartialEq, Clone, Debug)]
+pub enum PgTlsMode {
+    Disable,
+    Prefer,
+    Require,
+}
+
+impl Default for PgTlsMode {
+    fn default() -> PgTlsMode {
+        PgTlsMode::Prefer
+    }
+}
+
+impl PgTlsMode {
+    pub fn from_config(mode: &str) -> Result<Self, String> {
+        match mode {
+            "disable" => Ok(PgTlsMode::Disable),
+            "prefer" => Ok(PgTlsMode::Prefer),
+            "require" => Ok(PgTlsMode::Require),
+            _ => Err(format!("Unknown tls_mode `{}`", mode)),
+        }
+    }
+    fn tls_mode(&self) -> Result<TlsMode, String> {
+        if *self == PgTlsMode::Disable {
+            return Ok(TlsMode::None);
+        }
+        let negotiator = NativeTls::new().map_err(|e| format!("TLS initialization failed: {}", e))?;
+        match self {
+            &PgTlsMode::Require => Ok(TlsMode::Require(Box::new(negotiator))),
+            _ => Ok(TlsMode::Prefer(Box::new(negotiator))),
+        }
+    }
+}
+
+/// Session settings applied to new pool connections
+#[derive(Debug)]
+struct ConnectionSettings {
+    statement_timeout: Option<u64>,
+}
+
+impl r2d2::CustomizeConnection<postgres::Connection, postgres::Error> for ConnectionSettings {
+    fn on_acquire(&self, conn: &mut postgres::Connection) -> Result<(), postgres::Error> {
+        if let Some(timeout) = self.statement_timeout {
+            conn.batch_execute(&format!("SET statement_timeout = {}", timeout * 1000))?;
+        }
+        Ok(())
+    }
+}
+
 pub struct PostgisInput {
     pub connection_url: String,
+    /// Maximal number of pool connections (Default: 10)
+    pub pool_size: Option<u16>,
+    /// Seconds to wait for a pool connection (Default: 30)
+    pub connection_timeout: Option<u64>,
+    /// Seconds until idle pool connections are closed (Default: 600)
+    pub idle_timeout: Option<u64>,
+    /// Seconds until queries are cancelled (Default: no limit)
+    pub statement_timeout: Option<u64>,
+    pub tls_mode: PgTlsMode,
     conn_pool: Option<r2d2::Pool<PostgresConnectionManager>>,
     // Queries for all layers and zoom levels
     queries: BTreeMap<String, BTreeMap<u8, SqlQuery>>,
+    // ST_AsMVT queries for layers encoded in PostGIS
+    mvt_queries: BTreeMap<String, BTreeMap<u8, SqlQuery>>,
 }
 
 impl SqlQuery {
     /// Replace variables (!bbox!, !zoom!, etc.) in query
     // https://github.com/mapnik/mapnik/wiki/PostGIS
-    fn replace_params(&mut self, bbox_expr: String) {
+    fn replace_params(&mut self, bbox_expr: String, grid_srid: i32, layer_params: &[LayerParam]) {
         let mut numvars = 0;
-        if self.sql.contains("!bbox!") {
+        if self.sql.contains("!bbox!") || self.sql.contains("!tile_extent!") {
             self.params.push(QueryParam::Bbox);
             numvars += 4;
             self.sql = self.sql.replace("!bbox!", &bbox_expr);
+            // Unbuffered tile envelope
+            self.sql = self.sql.replace(
+                "!tile_extent!",
+                &format!("ST_MakeEnvelope($1,$2,$3,$4,{})", grid_srid),
+            );
         }
+        self.sql = self.sql.replace("!srid!", &grid_srid.to_string());
         // replace e.g. !zoom! with $5
         for (var, par, cast) in vec![
             ("!zoom!", QueryParam::Zoom, ""),
@@ -199,6 +325,10 @@ impl SqlQuery {
                 QueryParam::ScaleDenominator,
                 "FLOAT8",
             ),
+            ("!tile_x!", QueryParam::TileX, "INTEGER"),
+            ("!tile_y!", QueryParam::TileY, "INTEGER"),
+            ("!buffer_size!", QueryParam::BufferSize, "INTEGER"),
+            ("!mvt_buffer!", QueryParam::MvtBuffer, "INTEGER"),
         ] {
             if self.sql.contains(var) {
                 self.params.push(par);
@@ -210,13 +340,36 @@ impl SqlQuery {
                 }
             }
         }
+        // replace request parameters, e.g. !year! with $6::INT8
+        for (idx, param) in layer_params.iter().enumerate() {
+            let var = format!("!{}!", param.name);
+            if self.sql.contains(&var) {
+                self.params.push(QueryParam::Param(idx));
+                numvars += 1;
+                self.sql = self.sql.replace(
+                    &var,
+                    &format!("${}::{}", numvars, param_sql_type(param.param_type)),
+                );
+            }
+        }
     }
-    fn valid_sql_for_params(sql: &String) -> String {
+    fn valid_sql_for_params(sql: &String, layer_params: &[LayerParam]) -> String {
         let mut query: String;
         query = sql.replace("!bbox!", "ST_MakeEnvelope(0,0,0,0,3857)");
         query = query.replace("!zoom!", "0");
         query = query.replace("!pixel_width!", "0");
         query = query.replace("!scale_denominator!", "0");
+        query = query.replace("!tile_x!", "0");
+        query = query.replace("!tile_y!", "0");
+        query = query.replace("!buffer_size!", "0");
+        query = query.replace("!tile_extent!", "ST_MakeEnvelope(0,0,0,0,3857)");
+        query = query.replace("!srid!", "3857");
+        for param in layer_params {
+            query = query.replace(
+                &format!("!{}!", param.name),
+                &format!("NULL::{}", param_sql_type(param.param_type)),
+            );
+        }
         query
     }
 }
@@ -225,15 +378,57 @@ impl PostgisInput {
     pub fn new(connection_url: &str) -> PostgisInput {
         PostgisInput {
             connection_url: connection_url.to_string(),
+            pool_size: None,
+            connection_timeout: None,
+            idle_timeout: None,
+            statement_timeout: None,
+            tls_mode: PgTlsMode::default(),
             conn_pool: None,
             queries: BTreeMap::new(),
+            mvt_queries: BTreeMap::new(),
         }
     }
-    fn conn(&self) -> r2d2::PooledConnection<PostgresConnectionManager> {
-        let pool = self.conn_pool.as_ref().unwrap();
-        //debug!("{:?}", pool);
-        // Waits for at most Config::connection_timeout (default: 30s) before returning an error.
-        pool.get().unwrap()
+    /// New instance with connected pool or connection error
+    pub fn connect(&self) -> Result<PostgisInput, String> {
+        let manager =
+            PostgresConnectionManager::new(self.connection_url.as_ref(), self.tls_mode.tls_mode()?)
+                .map_err(|e| format!("Invalid connection URL '{}': {}", self.connection_url, e))?;
+        let mut builder = r2d2::Pool::builder()
+            .max_size(u32::from(self.pool_size.unwrap_or(10)))
+            .connection_customizer(Box::new(ConnectionSettings {
+                statement_timeout: self.statement_timeout,
+            }));
+        if let Some(timeout) = self.connection_timeout {
+            builder = builder.connection_timeout(Duration::from_secs(timeout));
+        }
+        if let Some(timeout) = self.idle_timeout {
+            builder = builder.idle_timeout(Some(Duration::from_secs(timeout)));
+        }
+        let pool = builder.build(manager).map_err(|e| {
+            format!(
+                "Connection to '{}' failed (tls_mode={:?}): {}",
+                self.connection_url, self.tls_mode, e
+            )
+        })?;
+        Ok(PostgisInput {
+            connection_url: self.connection_url.clone(),
+            pool_size: self.pool_size,
+            connection_timeout: self.connection_timeout,
+            idle_timeout: self.idle_timeout,
+            statement_timeout: self.statement_timeout,
+            tls_mode: self.tls_mode.clone(),
+            conn_pool: Some(pool),
+            queries: BTreeMap::new(),
+            mvt_queries: BTreeMap::new(),
+        })
+    }
+    fn conn(&self) -> Result<r2d2::PooledConnection<PostgresConnectionManager>, String> {
+        let pool = self.conn_pool
+            .as_ref()
+            .ok_or_else(|| "Datasource not connected".to_string())?;
+        // Waits for at most `connection_timeout` before returning an error.
+        pool.get()
+            .map_err(|e| format!("Unable to get connection from pool: {}", e))
     }
     pub fn detect_geometry_types(&self, layer: &Layer) -> Vec<String> {
         let field = layer.geometry_field.as_ref().unwrap();
@@ -243,7 +438,13 @@ impl PostgisInput {
             field, table
         );
 
-        let conn = self.conn();
+        let conn = match self.conn() {
+            Ok(conn) => conn,
+            Err(e) => {
+                error!("{}", e);
+                return Vec::new();
+            }
+        };
         let sql = format!(
             "SELECT DISTINCT GeometryType({}) AS geomtype FROM {}",
             field, table
@@ -264,8 +465,14 @@ impl PostgisInput {
                 layer.table_name.as_ref().unwrap_or(&layer.name)
             ),
         };
-        query = SqlQuery::valid_sql_for_params(&query);
-        let conn = self.conn();
+        query = SqlQuery::valid_sql_for_params(&query, &layer.params);
+        let conn = match self.conn() {
+            Ok(conn) => conn,
+            Err(e) => {
+                error!("Layer '{}': {}", layer.name, e);
+                return vec![];
+            }
+        };
         let stmt = conn.prepare(&query);
         match stmt {
             Err(e) => {
@@ -316,7 +523,13 @@ impl PostgisInput {
         use postgis::ewkb;
         use postgis::{LineString, Point, Polygon}; // conflicts with core::geom::Point etc.
 
-        let conn = self.conn();
+        let conn = match self.conn() {
+            Ok(conn) => conn,
+            Err(e) => {
+                error!("{}", e);
+                return None;
+            }
+        };
         let rows = conn.query(&sql, &[]).unwrap();
         let extpoly = rows.into_iter()
             .nth(0)
@@ -379,20 +592,25 @@ impl PostgisInput {
 
             // Simplify
             if layer.simplify {
+                let tolerance = match layer.tolerance {
+                    Some(tolerance) => format!("!pixel_width!*{}", tolerance),
+                    None => "!pixel_width!/2".to_string(),
+                };
                 geom_expr = match layer
                     .geometry_type
                     .as_ref()
                     .unwrap_or(&"GEOMETRY".to_string()) as &str
                 {
                     "LINESTRING" | "MULTILINESTRING" => format!(
-                        "ST_Multi(ST_SimplifyPreserveTopology({},!pixel_width!/2))",
-                        geom_expr
+                        "ST_Multi(ST_SimplifyPreserveTopology({},{}))",
+                        geom_expr, tolerance
                     ),
                     "POLYGON" | "MULTIPOLYGON" => {
                         let empty_geom =
                             format!("ST_GeomFromText('MULTIPOLYGON EMPTY',{})", layer_srid);
-                        format!("COALESCE(ST_SnapToGrid({}, !pixel_width!/2),{})::geometry(MULTIPOLYGON,{})",
+                        format!("COALESCE(ST_SnapToGrid({}, {}),{})::geometry(MULTIPOLYGON,{})",
                                 geom_expr,
+                                tolerance,
                                 empty_geom,
                                 layer_srid)
                     }
@@ -401,26 +619,33 @@ impl PostgisInput {
             }
         }
 
-        // Transform geometry to grid SRID
+        geom_expr = self.build_transform_expr(layer, grid_srid, geom_expr);
+
+        if geom_expr.starts_with("ST_") || geom_expr.starts_with("COALESCE") {
+            geom_expr = format!("{} AS {}", geom_expr, geom_name);
+        }
+
+        geom_expr
+    }
+    /// Transform geometry expression to grid SRID.
+    fn build_transform_expr(&self, layer: &Layer, grid_srid: i32, geom_expr: String) -> String {
+        let layer_srid = layer.srid.unwrap_or(0);
+        let ref geom_name = layer.geometry_field.as_ref().unwrap();
         if layer_srid <= 0 {
             warn!(
                 "Layer '{}': Unknown SRS of geometry '{}' - assuming SRID {}",
                 layer.name, geom_name, grid_srid
             );
-            geom_expr = format!("ST_SetSRID({},{})", geom_expr, grid_srid)
+            format!("ST_SetSRID({},{})", geom_expr, grid_srid)
         } else if layer_srid != grid_srid {
             info!(
                 "Layer '{}': Reprojecting geometry '{}' from SRID {} to {}",
                 layer.name, geom_name, layer_srid, grid_srid
             );
-            geom_expr = format!("ST_Transform({},{})", geom_expr, grid_srid);
-        }
-
-        if geom_expr.starts_with("ST_") || geom_expr.starts_with("COALESCE") {
-            geom_expr = format!("{} AS {}", geom_expr, geom_name);
+            format!("ST_Transform({},{})", geom_expr, grid_srid)
+        } else {
+            geom_expr
         }
-
-        geom_expr
     }
     /// Build select list expressions for feature query.
     fn build_select_list(&self, layer: &Layer, geom_expr: String, sql: Option<&String>) -> String {
@@ -470,10 +695,19 @@ impl PostgisInput {
         grid_srid: i32,
         sql: Option<&String>,
         raw_geom: bool,
+    ) -> Option<String> {
+        let geom_expr = self.build_geom_expr(layer, grid_srid, raw_geom);
+        self.build_query_sql_with_geom(layer, sql, geom_expr)
+    }
+    /// Build feature query SQL with given geometry selection expression.
+    fn build_query_sql_with_geom(
+        &self,
+        layer: &Layer,
+        sql: Option<&String>,
+        geom_expr: String,
     ) -> Option<String> {
         let mut query;
         let offline = self.conn_pool.is_none();
-        let geom_expr = self.build_geom_expr(layer, grid_srid, raw_geom);
         let select_list = self.build_select_list(layer, geom_expr, sql);
         let intersect_clause = format!(
             " WHERE {} && !bbox!",
@@ -493,7 +727,7 @@ impl PostgisInput {
             }
         } else {
             // automatic query
-            //TODO: check min-/maxzoom + handle overzoom
+            //TODO: check min-/maxzoom
             if layer.table_name.is_none() {
                 return None;
             }
@@ -522,49 +756,166 @@ impl PostgisInput {
             sql: sqlquery.unwrap(),
             params: Vec::new(),
         };
-        query.replace_params(bbox_expr);
+        query.replace_params(bbox_expr, grid_srid, &layer.params);
+        Some(query)
+    }
+    /// Build ST_AsMVT query returning the encoded layer.
+    pub fn build_mvt_query(
+        &self,
+        layer: &Layer,
+        grid_srid: i32,
+        sql: Option<&String>,
+    ) -> Option<SqlQuery> {
+        let ref geom_name = layer.geometry_field.as_ref().unwrap();
+        let geom_expr = self.build_transform_expr(layer, grid_srid, geom_name.to_string());
+        let mvt_geom_expr = match layer.buffer_size {
+            Some(_) => format!(
+                "ST_AsMVTGeom({},!tile_extent!,{},!mvt_buffer!,true) AS {}",
+                geom_expr, layer.tile_size, geom_name
+            ),
+            None => format!(
+                "ST_AsMVTGeom({},!tile_extent!,{},0,false) AS {}",
+                geom_expr, layer.tile_size, geom_name
+            ),
+        };
+        let sqlquery = self.build_query_sql_with_geom(layer, sql, mvt_geom_expr);
+        if sqlquery.is_none() {
+            return None;
+        }
+        let mut sqlquery = sqlquery.unwrap();
+        if let Some(limit) = layer.query_limit {
+            sqlquery.push_str(&format!(" LIMIT {}", limit));
+        }
+        let fid_arg = match layer.fid_field {
+            Some(ref fid_field) => format!(",{}", sql_literal(fid_field)),
+            None => "".to_string(),
+        };
+        let bbox_expr = self.build_bbox_expr(layer, grid_srid);
+        let mut query = SqlQuery {
+            sql: format!(
+                "SELECT ST_AsMVT(_mvt,{},{},{}{}) FROM ({}) AS _mvt",
+                sql_literal(&layer.name),
+                layer.tile_size,
+                sql_literal(geom_name),
+                fid_arg,
+                sqlquery
+            ),
+            params: Vec::new(),
+        };
+        query.replace_params(bbox_expr, grid_srid, &layer.params);
         Some(query)
     }
     fn query(&self, layer: &Layer, zoom: u8) -> Option<&SqlQuery> {
         let ref queries = self.queries[&layer.name];
         queries.get(&zoom)
     }
+    /// Bind query parameters and read rows until `read` returns false.
+    fn execute_query<F>(
+        &self,
+        layer: &Layer,
+        query: &SqlQuery,
+        extent: &Extent,
+        zoom: u8,
+        grid: &Grid,
+        param_values: &[ParamValue],
+        mut read: F,
+    ) -> Result<(), String>
+    where
+        F: FnMut(Row) -> bool,
+    {
+        let conn = self.conn()
+            .map_err(|e| format!("Layer '{}': {}", layer.name, e))?;
+        let stmt = conn.prepare_cached(&query.sql);
+        if let Err(err) = stmt {
+            error!("Query: {}", query.sql);
+            return Err(format!("Layer '{}': {}", layer.name, err));
+        };
+
+        // Add query params
+        let zoom_param = zoom as i32;
+        let pixel_width = grid.pixel_width(zoom); //TODO: calculate only if needed
+        let scale_denominator = grid.scale_denominator(zoom);
+        let (tile_x, tile_y) = grid.tile_index(extent, zoom);
+        // Same adressing scheme as tile requests
+        let tile_y = if grid.srid == 3857 {
+            grid.ytile_from_xyz(tile_y, zoom)
+        } else {
+            tile_y
+        };
+        let (tile_x, tile_y) = (tile_x as i32, tile_y as i32);
+        let buffer_size = layer.buffer_size(zoom).unwrap_or(0) as i32;
+        // Buffer in tile coordinates
+        let mvt_buffer = (layer.buffer_size(zoom).unwrap_or(0) * layer.tile_size(zoom)
+            / grid.tile_width() as u32) as i32;
+        // Request parameter values (default if missing)
+        let layer_params: Vec<Box<ToSql>> = layer
+            .params
+            .iter()
+            .enumerate()
+            .map(|(idx, param)| {
+                param_sql_value(param.param_type, param_values.get(idx).unwrap_or(&param.default))
+            })
+            .collect();
+        let mut params = Vec::new();
+        for param in &query.params {
+            match param {
+                &QueryParam::Bbox => {
+                    let mut bbox: Vec<&ToSql> =
+                        vec![&extent.minx, &extent.miny, &extent.maxx, &extent.maxy];
+                    params.append(&mut bbox);
+                }
+                &QueryParam::Zoom => params.push(&zoom_param),
+                &QueryParam::PixelWidth => params.push(&pixel_width),
+                &QueryParam::ScaleDenominator => {
+                    params.push(&scale_denominator);
+                }
+                &QueryParam::TileX => params.push(&tile_x),
+                &QueryParam::TileY => params.push(&tile_y),
+                &QueryParam::BufferSize => params.push(&buffer_size),
+                &QueryParam::MvtBuffer => params.push(&mvt_buffer),
+                &QueryParam::Param(idx) => params.push(layer_params[idx].as_ref()),
+            }
+        }
+
+        let stmt = stmt.unwrap();
+        let trans = conn.transaction()
+            .map_err(|e| format!("Layer '{}': {}", layer.name, e))?;
+        let rows = stmt.lazy_query(&trans, &params.as_slice(), 50);
+        if let Err(err) = rows {
+            error!("Query: {}", query.sql);
+            error!("Param types: {:?}", query.params);
+            error!("Param values: {:?}", params);
+            return Err(format!("Layer '{}': {}", layer.name, err));
+        };
+        for row in rows.unwrap().iterator() {
+            match row {
+                Ok(row) => {
+                    if !read(row) {
+                        break;
+                    }
+                }
+                Err(err) => return Err(format!("Layer '{}': {}", layer.name, err)),
+            }
+        }
+        Ok(())
+    }
 }
 
 impl DatasourceInput for PostgisInput {
     /// New instance with connected pool
-    /// New instance with connected pool
-    fn connected(&self) -> PostgisInput {
-        let pool_size = 10; //FIXME: make configurable
-                            // Emulate TlsMode::Allow (https://github.com/sfackler/rust-postgres/issues/278)
-        let manager =
-            PostgresConnectionManager::new(self.connection_url.as_ref(), TlsMode::None).unwrap();
-        let pool = r2d2::Pool::builder()
-            .max_size(pool_size)
-            .build(manager)
-            .or_else(|e| match e.description() {
-                "unable to initialize connections" => {
-                    info!("Couldn't connect with TlsMode::None - retrying with TlsMode::Require");
-                    let negotiator = NativeTls::new().unwrap();
-                    let manager = PostgresConnectionManager::new(
-                        self.connection_url.as_ref(),
-                        TlsMode::Require(Box::new(negotiator)),
-                    ).unwrap();
-                    r2d2::Pool::builder().max_size(pool_size).build(manager)
-                }
-                _ => Err(e),
-            })
-            .unwrap();
-        PostgisInput {
-            connection_url: self.connection_url.clone(),
-            conn_pool: Some(pool),
-            queries: BTreeMap::new(),
-        }
+    fn connected(&self) -> Result<PostgisInput, String> {
+        self.connect()
     }
     fn detect_layers(&self, detect_geometry_types: bool) -> Vec<Layer> {
         info!("Detecting layers from geometry_columns");
         let mut layers: Vec<Layer> = Vec::new();
-        let conn = self.conn();
+        let conn = match self.conn() {
+            Ok(conn) => conn,
+            Err(e) => {
+                error!("{}", e);
+                return layers;
+            }
+        };
         let sql = "SELECT * FROM geometry_columns ORDER BY f_table_schema,f_table_name DESC";
         for row in &conn.query(sql, &[]).unwrap() {
             let schema: String = row.get("f_table_schema");
@@ -658,97 +1009,81 @@ impl DatasourceInput for PostgisInput {
     }
     fn prepare_queries(&mut self, layer: &Layer, grid_srid: i32) {
         let mut queries = BTreeMap::new();
+        let mut mvt_queries = BTreeMap::new();
+        // Zoom levels with equal SQL and settings share the same query
+        let mut built: Vec<((Option<&String>, Option<u32>, bool, Option<f64>), Option<SqlQuery>)> =
+            Vec::new();
 
-        for layer_query in &layer.query {
-            if let Some(query) = self.build_query(layer, grid_srid, layer_query.sql.as_ref()) {
-                debug!("Query for layer '{}': {}", layer.name, query.sql);
-                for zoom in layer_query.minzoom()..layer_query.maxzoom() + 1 {
-                    if &layer.query(zoom).unwrap_or(&"".to_string())
-                        == &layer_query.sql.as_ref().unwrap_or(&"".to_string())
-                    {
-                        queries.insert(zoom, query.clone());
+        for zoom in layer.minzoom()..layer.maxzoom() + 1 {
+            let sql = layer.query(zoom);
+            let zoom_layer = layer.zoom_layer(zoom);
+            let key = (
+                sql,
+                zoom_layer.buffer_size,
+                zoom_layer.simplify,
+                zoom_layer.tolerance,
+            );
+            let prepared = built
+                .iter()
+                .find(|&&(ref k, _)| k == &key)
+                .map(|&(_, ref query)| query.clone());
+            let query = match prepared {
+                Some(query) => query,
+                None => {
+                    // Automatic query if `sql` is None
+                    let query = self.build_query(&zoom_layer, grid_srid, sql);
+                    if let Some(ref query) = query {
+                        debug!("Query for layer '{}': {}", layer.name, query.sql);
                     }
+                    built.push((key, query.clone()));
+                    query
                 }
+            };
+            if let Some(query) = query {
+                queries.insert(zoom, query);
             }
-        }
-
-        let has_gaps =
-            (layer.minzoom()..layer.maxzoom() + 1).any(|zoom| !queries.contains_key(&zoom));
-
-        // Genereate queries for zoom levels without user sql
-        if has_gaps {
-            if let Some(query) = self.build_query(layer, grid_srid, None) {
-                debug!("Query for layer '{}': {}", layer.name, query.sql);
-                for zoom in layer.minzoom()..layer.maxzoom() + 1 {
-                    if !queries.contains_key(&zoom) {
-                        queries.insert(zoom, query.clone());
-                    }
+            if layer.st_asmvt {
+                if let Some(query) = self.build_mvt_query(&zoom_layer, grid_srid, sql) {
+                    debug!("MVT query for layer '{}': {}", layer.name, query.sql);
+                    mvt_queries.insert(zoom, query);
                 }
             }
         }
 
         self.queries.insert(layer.name.clone(), queries);
+        self.mvt_queries.insert(layer.name.clone(), mvt_queries);
+    }
+    fn retrieve_features<F>(&self, layer: &Layer, extent: &Extent, zoom: u8, grid: &Grid, read: F)
+    where
+        F: FnMut(&Feature),
+    {
+        if let Err(e) = self.retrieve_features_with_params(layer, extent, zoom, grid, &[], read) {
+            error!("{}", e);
+        }
     }
-    fn retrieve_features<F>(
+    fn retrieve_features_with_params<F>(
         &self,
         layer: &Layer,
         extent: &Extent,
         zoom: u8,
         grid: &Grid,
+        params: &[ParamValue],
         mut read: F,
-    ) where
+    ) -> Result<(), String>
+    where
         F: FnMut(&Feature),
     {
-        let conn = self.conn();
         let query = self.query(&layer, zoom);
         if query.is_none() {
-            return;
-        }
-        let query = query.unwrap();
-        let stmt = conn.prepare_cached(&query.sql);
-        if let Err(err) = stmt {
-            error!("Layer '{}': {}", layer.name, err);
-            error!("Query: {}", query.sql);
-            return;
-        };
-
-        // Add query params
-        let zoom_param = zoom as i32;
-        let pixel_width = grid.pixel_width(zoom); //TODO: calculate only if needed
-        let scale_denominator = grid.scale_denominator(zoom);
-        let mut params = Vec::new();
-        for param in &query.params {
-            match param {
-                &QueryParam::Bbox => {
-                    let mut bbox: Vec<&ToSql> =
-                        vec![&extent.minx, &extent.miny, &extent.maxx, &extent.maxy];
-                    params.append(&mut bbox);
-                }
-                &QueryParam::Zoom => params.push(&zoom_param),
-                &QueryParam::PixelWidth => params.push(&pixel_width),
-                &QueryParam::ScaleDenominator => {
-                    params.push(&scale_denominator);
-                }
-            }
+            return Ok(());
         }
-
-        let stmt = stmt.unwrap();
-        let trans = conn.transaction().unwrap();
-        let rows = stmt.lazy_query(&trans, &params.as_slice(), 50);
-        if let Err(err) = rows {
-            error!("Layer '{}': {}", layer.name, err);
-            error!("Query: {}", query.sql);
-            error!("Param types: {:?}", query.params);
-            error!("Param values: {:?}", params);
-            return;
-        };
         debug!("Reading features in layer {}", layer.name);
         let mut cnt = 0;
-        let query_limit = layer.query_limit.unwrap_or(0);
-        for row in rows.unwrap().iterator() {
+        let query_limit = layer.query_limit(zoom).unwrap_or(0);
+        self.execute_query(layer, query.unwrap(), extent, zoom, grid, params, |row| {
             let feature = FeatureRow {
                 layer: layer,
-                row: &row.unwrap(),
+                row: &row,
             };
             read(&feature);
             cnt += 1;
@@ -757,21 +1092,60 @@ impl DatasourceInput for PostgisInput {
                     "Feature count limited (name={}, query_limit={})",
                     layer.name, cnt
                 );
-                break;
+                return false;
             }
-        }
+            true
+        })?;
         debug!("Feature count: {}", cnt);
+        Ok(())
+    }
+    fn retrieve_mvt_layer(
+        &self,
+        layer: &Layer,
+        extent: &Extent,
+        zoom: u8,
+        grid: &Grid,
+        params: &[ParamValue],
+    ) -> Result<Option<Vec<u8>>, String> {
+        let query = self.mvt_queries.get(&layer.name).and_then(|q| q.get(&zoom));
+        if query.is_none() {
+            return Ok(None);
+        }
+        debug!("Reading ST_AsMVT layer {}", layer.name);
+        let mut data = None;
+        self.execute_query(layer, query.unwrap(), extent, zoom, grid, params, |row| {
+            data = row.get_opt::<_, Option<Vec<u8>>>(0)
+                .and_then(|v| v.ok())
+                .and_then(|v| v);
+            false
+        })?;
+        // ST_AsMVT returns an empty tile if no feature was selected
+        Ok(Some(data.unwrap_or(Vec::new())))
     }
 }
 
 impl<'a> Config<'a, DatasourceCfg> for PostgisInput {
     fn from_config(ds_cfg: &DatasourceCfg) -> Result<Self, String> {
-        if let Ok(url) = env::var("TREX_DATASOURCE_URL") {
-            // FIXME: this overwrites *all* PostGIS connections instead of a specific one
-            Ok(PostgisInput::new(url.as_str()))
+        let mut ds = if let Ok(url) = env::var("TREX_DATASOURCE_URL") {
+            // Overwrites *all* PostGIS connections
+            warn!("TREX_DATASOURCE_URL is deprecated - use `dbconn = \"${{VAR}}\"` instead");
+            PostgisInput::new(url.as_str())
         } else {
-            Ok(PostgisInput::new(ds_cfg.dbconn.as_ref().unwrap()))
+            PostgisInput::new(ds_cfg.dbconn.as_ref().unwrap())
+        };
+        if ds_cfg.pool == Some(0) || ds_cfg.connection_timeout == Some(0) {
+            return Err(
+                "Datasource `pool` and `connection_timeout` must be greater than 0".to_string(),
+            );
+        }
+        ds.pool_size = ds_cfg.pool;
+        ds.connection_timeout = ds_cfg.connection_timeout;
+        ds.idle_timeout = ds_cfg.idle_timeout;
+        ds.statement_timeout = ds_cfg.statement_timeout;
+        if let Some(ref mode) = ds_cfg.tls_mode {
+            ds.tls_mode = PgTlsMode::from_config(mode)?;
         }
+        Ok(ds)
     }
 
     fn gen_config() -> String {
@@ -8,7 +8,7 @@ use core::geom::*;
 use core::grid::Extent;
 use core::grid::Grid;
 use core::layer::{Layer, LayerQuery};
-use datasource::postgis::{PostgisInput, QueryParam};
+use datasource::postgis::{PgTlsMode, PostgisInput, QueryParam};
 use datasource::DatasourceInput;
 use postgres;
 use postgres::Connection;
@@ -59,7 +59,7 @@ fn test_from_geom_fields() {
 #[ignore]
 fn test_detect_layers() {
     let pg: PostgisInput = match env::var("DBCONN") {
-        Result::Ok(val) => Some(PostgisInput::new(&val).connected()),
+        Result::Ok(val) => Some(PostgisInput::new(&val).connected().unwrap()),
         Result::Err(_) => panic!("DBCONN undefined"),
     }.unwrap();
     let layers = pg.detect_layers(false);
@@ -74,7 +74,7 @@ fn test_detect_layers() {
 #[ignore]
 fn test_detect_columns() {
     let pg: PostgisInput = match env::var("DBCONN") {
-        Result::Ok(val) => Some(PostgisInput::new(&val).connected()),
+        Result::Ok(val) => Some(PostgisInput::new(&val).connected().unwrap()),
         Result::Err(_) => panic!("DBCONN undefined"),
     }.unwrap();
     let layers = pg.detect_layers(false);
@@ -97,7 +97,7 @@ fn test_detect_columns() {
 #[ignore]
 fn test_extent_query() {
     let pg: PostgisInput = match env::var("DBCONN") {
-        Result::Ok(val) => Some(PostgisInput::new(&val).connected()),
+        Result::Ok(val) => Some(PostgisInput::new(&val).connected().unwrap()),
         Result::Err(_) => panic!("DBCONN undefined"),
     }.unwrap();
     let layers = pg.detect_layers(false);
@@ -183,6 +183,7 @@ fn test_feature_query() {
         minzoom: Some(0),
         maxzoom: Some(22),
         sql: Some(String::from("SELECT geometry AS geom FROM osm_place_point")),
+        ..Default::default()
     }];
     layer.query_limit = None;
     assert_eq!(pg.build_query(&layer, 3857, layer.query[0].sql.as_ref())
@@ -196,6 +197,7 @@ fn test_feature_query() {
         sql: Some(String::from(
             "SELECT * FROM osm_place_point WHERE name='Bern'",
         )),
+        ..Default::default()
     }];
     assert_eq!(pg.build_query(&layer, 3857, layer.query[0].sql.as_ref())
                    .unwrap()
@@ -219,6 +221,7 @@ fn test_query_params() {
                            minzoom: Some(0),
                            maxzoom: Some(22),
                            sql: Some(String::from("SELECT name, type, 0 as osm_id, ST_Union(geometry) AS way FROM osm_buildings_gen0 WHERE geometry && !bbox!")),
+                           ..Default::default()
                        }];
     let query = pg.build_query(&layer, 3857, layer.query[0].sql.as_ref())
         .unwrap();
@@ -230,6 +233,7 @@ fn test_query_params() {
                            minzoom: Some(0),
                            maxzoom: Some(22),
                            sql: Some(String::from("SELECT osm_id, geometry, typen FROM landuse_z13toz14n WHERE !zoom! BETWEEN 13 AND 14) AS landuse_z9toz14n")),
+                           ..Default::default()
                        }];
     let query = pg.build_query(&layer, 3857, layer.query[0].sql.as_ref())
         .unwrap();
@@ -241,19 +245,121 @@ fn test_query_params() {
                            minzoom: Some(0),
                            maxzoom: Some(22),
                            sql: Some(String::from("SELECT name, type, 0 as osm_id, ST_SimplifyPreserveTopology(ST_Union(geometry),!pixel_width!/2) AS way FROM osm_buildings")),
+                           ..Default::default()
                        }];
     let query = pg.build_query(&layer, 3857, layer.query[0].sql.as_ref())
         .unwrap();
     assert_eq!(query.sql,
                "SELECT * FROM (SELECT name, type, 0 as osm_id, ST_SimplifyPreserveTopology(ST_Union(geometry),$5::FLOAT8/2) AS way FROM osm_buildings) AS _q WHERE way && ST_MakeEnvelope($1,$2,$3,$4,3857)");
     assert_eq!(query.params, [QueryParam::Bbox, QueryParam::PixelWidth]);
+
+    layer.query = vec![LayerQuery {
+                           minzoom: Some(0),
+                           maxzoom: Some(22),
+                           sql: Some(String::from("SELECT osm_id, ST_ClipByBox2D(geometry,ST_Expand(!tile_extent!,!buffer_size!)) AS way, !tile_x! AS x, !tile_y! AS y FROM osm_buildings WHERE ST_SRID(geometry)=!srid! AND geometry && !bbox!")),
+                           ..Default::default()
+                       }];
+    let query = pg.build_query(&layer, 3857, layer.query[0].sql.as_ref())
+        .unwrap();
+    assert_eq!(query.sql,
+               "SELECT * FROM (SELECT osm_id, ST_ClipByBox2D(geometry,ST_Expand(ST_MakeEnvelope($1,$2,$3,$4,3857),$7::INTEGER)) AS way, $5::INTEGER AS x, $6::INTEGER AS y FROM osm_buildings WHERE ST_SRID(geometry)=3857 AND geometry && ST_MakeEnvelope($1,$2,$3,$4,3857)) AS _q");
+    assert_eq!(query.params,
+               [QueryParam::Bbox, QueryParam::TileX, QueryParam::TileY, QueryParam::BufferSize]);
+}
+
+#[test]
+fn test_request_params() {
+    use core::layer::{LayerParam, ParamType, ParamValue};
+
+    let pg = PostgisInput::new("postgresql://pi@localhost/osm2vectortiles");
+    let mut layer = Layer::new("buildings");
+    layer.geometry_field = Some(String::from("way"));
+    layer.params = vec![
+        LayerParam {
+            name: String::from("year"),
+            param_type: ParamType::Int,
+            default: ParamValue::Int(2020),
+        },
+        LayerParam {
+            name: String::from("category"),
+            param_type: ParamType::Text,
+            default: ParamValue::Null,
+        },
+    ];
+    layer.query = vec![LayerQuery {
+                           minzoom: Some(0),
+                           maxzoom: Some(22),
+                           sql: Some(String::from("SELECT name, way FROM osm_buildings WHERE built <= !year! AND (!category! IS NULL OR category = !category!)")),
+                           ..Default::default()
+                       }];
+    let query = pg.build_query(&layer, 3857, layer.query[0].sql.as_ref())
+        .unwrap();
+    assert_eq!(query.sql,
+               "SELECT * FROM (SELECT name, way FROM osm_buildings WHERE built <= $5::INT8 AND ($6::TEXT IS NULL OR category = $6::TEXT)) AS _q WHERE way && ST_MakeEnvelope($1,$2,$3,$4,3857)");
+    assert_eq!(query.params,
+               [QueryParam::Bbox, QueryParam::Param(0), QueryParam::Param(1)]);
+}
+
+#[test]
+fn test_mvt_query() {
+    let pg = PostgisInput::new("postgresql://pi@localhost/osm2vectortiles");
+    let mut layer = Layer::new("points");
+    layer.table_name = Some(String::from("osm_place_point"));
+    layer.geometry_field = Some(String::from("geometry"));
+    layer.srid = Some(3857);
+    let query = pg.build_mvt_query(&layer, 3857, None).unwrap();
+    assert_eq!(query.sql,
+               "SELECT ST_AsMVT(_mvt,'points',4096,'geometry') FROM (SELECT ST_AsMVTGeom(geometry,ST_MakeEnvelope($1,$2,$3,$4,3857),4096,0,false) AS geometry FROM osm_place_point WHERE geometry && ST_MakeEnvelope($1,$2,$3,$4,3857)) AS _mvt");
+    assert_eq!(query.params, [QueryParam::Bbox]);
+
+    layer.srid = Some(2056);
+    layer.buffer_size = Some(10);
+    layer.fid_field = Some(String::from("osm_id"));
+    layer.query_limit = Some(100);
+    let query = pg.build_mvt_query(&layer, 3857, None).unwrap();
+    assert_eq!(query.sql,
+               "SELECT ST_AsMVT(_mvt,'points',4096,'geometry','osm_id') FROM (SELECT ST_AsMVTGeom(ST_Transform(geometry,3857),ST_MakeEnvelope($1,$2,$3,$4,3857),4096,$6::INTEGER,true) AS geometry FROM osm_place_point WHERE geometry && ST_Transform(ST_Buffer(ST_MakeEnvelope($1,$2,$3,$4,3857),10*$5::FLOAT8),2056) LIMIT 100) AS _mvt");
+    assert_eq!(
+        query.params,
+        [QueryParam::Bbox, QueryParam::PixelWidth, QueryParam::MvtBuffer]
+    );
+
+    layer.name = String::from("Joe's places");
+    layer.fid_field = Some(String::from("id'"));
+    let query = pg.build_mvt_query(&layer, 3857, None).unwrap();
+    assert!(query
+        .sql
+        .starts_with("SELECT ST_AsMVT(_mvt,'Joe''s places',4096,'geometry','id''') FROM"));
+
+    layer.table_name = None;
+    assert!(pg.build_mvt_query(&layer, 3857, None).is_none());
+}
+
+#[test]
+fn test_query_errors() {
+    let mut pg = PostgisInput::new("postgresql://pi@localhost/osm2vectortiles");
+    let mut layer = Layer::new("points");
+    layer.table_name = Some(String::from("osm_place_point"));
+    layer.geometry_field = Some(String::from("geometry"));
+    layer.geometry_type = Some(String::from("POINT"));
+    layer.srid = Some(3857);
+    layer.st_asmvt = true;
+    pg.prepare_queries(&layer, 3857);
+    let grid = Grid::web_mercator();
+    let extent = grid.tile_extent(0, 0, 0);
+    let err = Err("Layer 'points': Datasource not connected".to_string());
+    assert_eq!(
+        pg.retrieve_features_with_params(&layer, &extent, 0, &grid, &[], |_| {}),
+        err
+    );
+    assert_eq!(pg.retrieve_mvt_layer(&layer, &extent, 0, &grid, &[]), err.map(|_| None));
 }
 
 #[test]
 #[ignore]
 fn test_retrieve_features() {
     let mut pg: PostgisInput = match env::var("DBCONN") {
-        Result::Ok(val) => Some(PostgisInput::new(&val).connected()),
+        Result::Ok(val) => Some(PostgisInput::new(&val).connected().unwrap()),
         Result::Err(_) => panic!("DBCONN undefined"),
     }.unwrap();
 
@@ -286,6 +392,7 @@ fn test_retrieve_features() {
         minzoom: Some(0),
         maxzoom: Some(22),
         sql: Some(String::from("SELECT * FROM ne_10m_populated_places")),
+        ..Default::default()
     }];
     layer.fid_field = Some(String::from("fid"));
     pg.prepare_queries(&layer, 3857);
@@ -308,6 +415,35 @@ fn test_retrieve_features() {
     });
 }
 
+#[test]
+fn test_pool_config() {
+    use core::config::DatasourceCfg;
+    use core::{parse_config, Config};
+
+    let toml = r#"
+        dbconn = "postgresql://pi@localhost/osm2vectortiles"
+        pool = 4
+        connection_timeout = 5
+        statement_timeout = 60
+        tls_mode = "require"
+        "#;
+    let cfg: DatasourceCfg = parse_config(toml.to_string(), "").unwrap();
+    let pg = PostgisInput::from_config(&cfg).unwrap();
+    assert_eq!(pg.pool_size, Some(4));
+    assert_eq!(pg.connection_timeout, Some(5));
+    assert_eq!(pg.idle_timeout, None);
+    assert_eq!(pg.statement_timeout, Some(60));
+    assert_eq!(pg.tls_mode, PgTlsMode::Require);
+
+    let cfg: DatasourceCfg = parse_config(toml.replace("require", "allow"), "").unwrap();
+    assert_eq!(
+        PostgisInput::from_config(&cfg).err(),
+        Some("Unknown tls_mode `allow`".to_string())
+    );
+    let cfg: DatasourceCfg = parse_config(toml.replace("pool = 4", "pool = 0"), "").unwrap();
+    assert!(PostgisInput::from_config(&cfg).is_err());
+}
+
 #[test]
 #[ignore]
 fn test_tls() {
@@ -325,3 +461,20 @@ fn test_tls() {
     //assert!(conn.unwrap().execute("SELECT 1::VARCHAR", &[]).is_ok());
     // Check pg_stat_ssl? https://www.postgresql.org/docs/9.6/static/monitoring-stats.html#PG-STAT-SSL-VIEW
 }
+
+#[test]
+fn test_uuid_attribute() {
+    use postgres::types::{self, FromSql};
+
+    let raw = [
+        0x55, 0x0e, 0x84, 0x00, 0xe2, 0x9b, 0x41, 0xd4, 0xa7, 0x16, 0x44, 0x66, 0x55, 0x44, 0x00,
+        0x00,
+    ];
+    assert!(<FeatureAttrValType as FromSql>::accepts(&types::UUID));
+    assert_eq!(
+        FeatureAttrValType::from_sql(&types::UUID, &raw).unwrap(),
+        FeatureAttrValType::String("550e8400-e29b-41d4-a716-446655440000".to_string())
+    );
+    assert!(FeatureAttrValType::from_sql(&types::UUID, &raw[0..8]).is_err());
+    assert!(<FeatureAttrValType as FromSql>::accepts(&types::BPCHAR));
+}
@@ -0,0 +1,235 @@
+//
+// Copyright (c) Pirmin Kalberer. All rights reserved.
+// Licensed under the MIT License. See LICENSE file in the project root for full license information.
+//
+
+//! Static packed Hilbert R-tree with the node layout of FlatGeobuf
+
+use core::grid::Extent;
+
+/// Bounding box and offset of an index node
+#[derive(Clone, Debug)]
+pub struct NodeItem {
+    pub extent: Extent,
+    /// Feature offset (leaf) or index of first child node
+    pub offset: u64,
+}
+
+/// Size of serialized node item in bytes
+pub const NODE_ITEM_LEN: usize = 40;
+
+pub struct PackedRTree {
+    nodes: Vec<NodeItem>,
+    node_size: usize,
+    /// Node index range of each level, leaves first
+    level_bounds: Vec<(usize, usize)>,
+}
+
+fn intersects(a: &Extent, b: &Extent) -> bool {
+    a.minx <= b.maxx && a.maxx >= b.minx && a.miny <= b.maxy && a.maxy >= b.miny
+}
+
+fn union(a: &Extent, b: &Extent) -> Extent {
+    Extent {
+        minx: a.minx.min(b.minx),
+        miny: a.miny.min(b.miny),
+        maxx: a.maxx.max(b.maxx),
+        maxy: a.maxy.max(b.maxy),
+    }
+}
+
+/// Hilbert curve index of 16 bit coordinates
+fn hilbert(x: u32, y: u32) -> u32 {
+    const MAX: u32 = 0xffff;
+    let (mut x, mut y) = (x.min(MAX), y.min(MAX));
+    let mut d = 0;
+    let mut s: u32 = 1 << 15;
+    while s > 0 {
+        let rx = (x & s > 0) as u32;
+        let ry = (y & s > 0) as u32;
+        d += s * s * ((3 * rx) ^ ry);
+        if ry == 0 {
+            if rx == 1 {
+                x = MAX - x;
+                y = MAX - y;
+            }
+            ::std::mem::swap(&mut x, &mut y);
+        }
+        s /= 2;
+    }
+    d
+}
+
+fn level_bounds(num_items: usize, node_size: usize) -> Vec<(usize, usize)> {
+    let mut level_num_nodes = vec![num_items];
+    let mut n = num_items;
+    let mut num_nodes = n;
+    while n > 1 {
+        n = (n + node_size - 1) / node_size;
+        num_nodes += n;
+        level_num_nodes.push(n);
+    }
+    let mut bounds = Vec::with_capacity(level_num_nodes.len());
+    let mut end = num_nodes;
+    for size in level_num_nodes {
+        bounds.push((end - size, end));
+        end -= size;
+    }
+    bounds
+}
+
+fn read_f64(data: &[u8]) -> f64 {
+    let mut v: u64 = 0;
+    for i in 0..8 {
+        v |= (data[i] as u64) << (8 * i);
+    }
+    f64::from_bits(v)
+}
+
+impl PackedRTree {
+    /// Build index of items. Leaf offsets are the item positions.
+    pub fn build(items: &[Extent], node_size: usize) -> PackedRTree {
+        let node_size = node_size.max(2);
+        let level_bounds = level_bounds(items.len(), node_size);
+        let num_nodes = level_bounds.first().map_or(0, |&(_, end)| end);
+        let empty = Extent {
+            minx: 0.0,
+            miny: 0.0,
+            maxx: 0.0,
+            maxy: 0.0,
+        };
+        let mut nodes = vec![
+            NodeItem {
+                extent: empty.clone(),
+                offset: 0,
+            };
+            num_nodes
+        ];
+        if items.is_empty() {
+            return PackedRTree {
+                nodes: nodes,
+                node_size: node_size,
+                level_bounds: level_bounds,
+            };
+        }
+
+        // Sort leaves along Hilbert curve
+        let total = items.iter().skip(1).fold(items[0].clone(), |e, i| union(&e, i));
+        let width = (total.maxx - total.minx).max(::std::f64::MIN_POSITIVE);
+        let height = (total.maxy - total.miny).max(::std::f64::MIN_POSITIVE);
+        let mut sorted: Vec<(u32, usize)> = items
+            .iter()
+            .enumerate()
+            .map(|(i, e)| {
+                let x = 65535.0 * ((e.minx + e.maxx) / 2.0 - total.minx) / width;
+                let y = 65535.0 * ((e.miny + e.maxy) / 2.0 - total.miny) / height;
+                (hilbert(x as u32, y as u32), i)
+            })
+            .collect();
+        sorted.sort();
+        let leaf_start = level_bounds[0].0;
+        for (pos, &(_, i)) in sorted.iter().enumerate() {
+            nodes[leaf_start + pos] = NodeItem {
+                extent: items[i].clone(),
+                offset: i as u64,
+            };
+        }
+
+        // Parent nodes from bottom to top
+        for level in 0..level_bounds.len() - 1 {
+            let (start, end) = level_bounds[level];
+            let mut parent = level_bounds[level + 1].0;
+            let mut pos = start;
+            while pos < end {
+                let last = (pos + node_size).min(end);
+                let extent = nodes[pos + 1..last]
+                    .iter()
+                    .fold(nodes[pos].extent.clone(), |e, n| union(&e, &n.extent));
+                nodes[parent] = NodeItem {
+                    extent: extent,
+                    offset: pos as u64,
+                };
+                parent += 1;
+                pos = last;
+            }
+        }
+        PackedRTree {
+            nodes: nodes,
+            node_size: node_size,
+            level_bounds: level_bounds,
+        }
+    }
+    /// Index size in bytes
+    pub fn size(num_items: usize, node_size: usize) -> usize {
+        let num_nodes = level_bounds(num_items, node_size.max(2))
+            .first()
+            .map_or(0, |&(_, end)| end);
+        num_nodes * NODE_ITEM_LEN
+    }
+    /// Read serialized index (little endian)
+    pub fn from_bytes(data: &[u8], num_items: usize, node_size: usize) -> PackedRTree {
+        let nodes = data.chunks(NODE_ITEM_LEN)
+            .filter(|chunk| chunk.len() == NODE_ITEM_LEN)
+            .map(|chunk| {
+                let mut offset: u64 = 0;
+                for i in 0..8 {
+                    offset |= (chunk[32 + i] as u64) << (8 * i);
+                }
+                NodeItem {
+                    extent: Extent {
+                        minx: read_f64(&chunk[0..8]),
+                        miny: read_f64(&chunk[8..16]),
+                        maxx: read_f64(&chunk[16..24]),
+                        maxy: read_f64(&chunk[24..32]),
+                    },
+                    offset: offset,
+                }
+            })
+            .collect();
+        PackedRTree {
+            nodes: nodes,
+            node_size: node_size.max(2),
+            level_bounds: level_bounds(num_items, node_size.max(2)),
+        }
+    }
+    /// Extent of all items
+    pub fn extent(&self) -> Option<Extent> {
+        match self.level_bounds.first() {
+            Some(&(start, end)) if end > start => self.nodes.get(0).map(|n| n.extent.clone()),
+            _ => None,
+        }
+    }
+    /// Offsets of items intersecting `extent` in ascending order
+    pub fn search(&self, extent: &Extent) -> Vec<u64> {
+        self.search_items(extent)
+            .into_iter()
+            .map(|(_, offset)| offset)
+            .collect()
+    }
+    /// Item numbers and offsets of items intersecting `extent` in ascending offset order
+    pub fn search_items(&self, extent: &Extent) -> Vec<(u64, u64)> {
+        let mut results = Vec::new();
+        let leaf_start = match self.level_bounds.first() {
+            Some(&(start, end)) if end > start && end <= self.nodes.len() => start,
+            _ => return results,
+        };
+        let mut queue = vec![(0, self.level_bounds.len() - 1)];
+        while let Some((node_index, level)) = queue.pop() {
+            let is_leaf = node_index >= leaf_start;
+            let end = (node_index + self.node_size).min(self.level_bounds[level].1);
+            for pos in node_index..end {
+                let node = &self.nodes[pos];
+                if !intersects(extent, &node.extent) {
+                    continue;
+                }
+                if is_leaf {
+                    results.push(((pos - leaf_start) as u64, node.offset));
+                } else if level > 0 {
+                    queue.push((node.offset as usize, level - 1));
+                }
+            }
+        }
+        results.sort_by_key(|&(_, offset)| offset);
+        results
+    }
+}
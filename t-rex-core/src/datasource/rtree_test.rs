@@ -0,0 +1,50 @@
+//
+// Copyright (c) Pirmin Kalberer. All rights reserved.
+// Licensed under the MIT License. See LICENSE file in the project root for full license information.
+//
+
+use core::grid::Extent;
+use datasource::rtree::{PackedRTree, NODE_ITEM_LEN};
+
+fn point_extent(x: f64, y: f64) -> Extent {
+    Extent {
+        minx: x,
+        miny: y,
+        maxx: x,
+        maxy: y,
+    }
+}
+
+#[test]
+fn test_search() {
+    let items: Vec<Extent> = (0..100)
+        .map(|i| point_extent((i % 10) as f64, (i / 10) as f64))
+        .collect();
+    let index = PackedRTree::build(&items, 4);
+    let found = index.search(&Extent {
+        minx: 1.5,
+        miny: 1.5,
+        maxx: 3.5,
+        maxy: 2.5,
+    });
+    assert_eq!(found, vec![22, 23]);
+    assert_eq!(index.search(&point_extent(20.0, 20.0)), Vec::<u64>::new());
+    assert_eq!(
+        index.search(&point_extent(9.0, 9.0)).len(),
+        1
+    );
+    let extent = index.extent().unwrap();
+    assert_eq!(
+        (extent.minx, extent.miny, extent.maxx, extent.maxy),
+        (0.0, 0.0, 9.0, 9.0)
+    );
+}
+
+#[test]
+fn test_size() {
+    // 100 leaves + 25 + 7 + 2 + 1 nodes
+    assert_eq!(PackedRTree::size(100, 4), 135 * NODE_ITEM_LEN);
+    assert_eq!(PackedRTree::size(1, 16), NODE_ITEM_LEN);
+    let index = PackedRTree::build(&[], 16);
+    assert!(index.extent().is_none());
+}
@@ -0,0 +1,523 @@
+//
+// Copyright (c) Pirmin Kalberer. All rights reserved.
+// Licensed under the MIT License. See LICENSE file in the project root for full license information.
+//
+
+//! Datasource decoding pre-rendered vector tiles from MBTiles files or XYZ tile servers
+
+use core::config::DatasourceCfg;
+use core::feature::{Feature, FeatureAttr};
+use core::geom::{self, GeometryType};
+use core::grid::Extent;
+use core::grid::Grid;
+use core::layer::Layer;
+use core::screen;
+use core::Config;
+use datasource::datasource::{layer_bbox, FeatureCount};
+use datasource::transform::Transform;
+use datasource::DatasourceInput;
+use mvt::overzoom::{clip_geometry, ClipRect};
+use mvt::tile::Tile;
+use mvt::tile_decoder::{decode_layer, DecodedFeature};
+use rusqlite::types::ToSql;
+use rusqlite::{Connection, OpenFlags};
+use serde_json;
+use std::io::Read;
+use ureq;
+
+/// Width of the Web Mercator world
+const WORLD_WIDTH: f64 = 2.0 * 20037508.342789248;
+
+/// Source of vector tiles in Web Mercator XYZ scheme
+#[derive(Clone, Debug)]
+pub enum TileSource {
+    MBTiles(String),
+    /// URL template with `{z}`, `{x}` and `{y}` placeholders
+    Xyz(String),
+}
+
+pub struct TilesDatasource {
+    pub source: TileSource,
+    pub minzoom: u8,
+    pub maxzoom: u8,
+}
+
+/// Timeout for connecting to and reading from tile servers
+const HTTP_TIMEOUT_SECS: u64 = 30;
+/// Maximal number of followed HTTP redirects
+const MAX_REDIRECTS: u32 = 5;
+
+/// Maximal size of tile server responses
+const MAX_RESPONSE_SIZE: u64 = 16 * 1024 * 1024;
+
+fn check_url(url: &str) -> Result<(), String> {
+    if url.starts_with("http://") || url.starts_with("https://") {
+        Ok(())
+    } else {
+        Err(format!("Unsupported URL scheme: {}", url))
+    }
+}
+
+/// Response body of HTTP(S) GET request.
+/// Redirects are followed up to `MAX_REDIRECTS` times, responses are limited to `MAX_RESPONSE_SIZE`.
+pub fn http_get(url: &str) -> Result<Vec<u8>, String> {
+    let response = ureq::get(url)
+        .set("Accept-Encoding", "gzip")
+        .set("User-Agent", "t-rex")
+        .timeout_connect(HTTP_TIMEOUT_SECS * 1000)
+        .timeout_read(HTTP_TIMEOUT_SECS * 1000)
+        .redirects(MAX_REDIRECTS)
+        .call();
+    if let Some(ref e) = *response.synthetic_error() {
+        return Err(format!("{}: {}", url, e));
+    }
+    match response.status() {
+        200 => {}
+        // Empty tile
+        204 | 404 => return Ok(Vec::new()),
+        status => return Err(format!("{}: HTTP status {}", url, status)),
+    }
+    let mut body = Vec::new();
+    response
+        .into_reader()
+        .take(MAX_RESPONSE_SIZE + 1)
+        .read_to_end(&mut body)
+        .map_err(|e| format!("{}: {}", url, e))?;
+    if body.len() as u64 > MAX_RESPONSE_SIZE {
+        return Err(format!(
+            "{}: Response larger than {} bytes",
+            url, MAX_RESPONSE_SIZE
+        ));
+    }
+    Ok(body)
+}
+
+/// Source zoom level with tile width closest to extent width
+fn source_zoom(width: f64) -> i32 {
+    (WORLD_WIDTH / width).log2().round() as i32
+}
+
+/// XYZ tile index range (x, y) covering Web Mercator extent
+fn tile_range(extent: &Extent, zoom: u8) -> ((u32, u32), (u32, u32)) {
+    const EPSILON: f64 = 0.0000001;
+    let tile_width = WORLD_WIDTH / f64::from(1u32 << zoom);
+    let max_index = (1i64 << zoom) - 1;
+    let index = |v: f64| ((v / tile_width + EPSILON).floor() as i64).max(0).min(max_index) as u32;
+    let last = |v: f64| ((v / tile_width - EPSILON).ceil() as i64 - 1).max(0).min(max_index) as u32;
+    let half = WORLD_WIDTH / 2.0;
+    (
+        (index(extent.minx + half), last(extent.maxx + half)),
+        (index(half - extent.maxy), last(half - extent.miny)),
+    )
+}
+
+fn screen_points(
+    points: &[screen::Point],
+    georef: &Fn(&screen::Point) -> (f64, f64),
+    srid: Option<i32>,
+) -> Vec<geom::Point> {
+    points
+        .iter()
+        .map(|p| {
+            let (x, y) = georef(p);
+            geom::Point::new(x, y, srid)
+        })
+        .collect()
+}
+
+fn screen_line(
+    line: &screen::LineString,
+    georef: &Fn(&screen::Point) -> (f64, f64),
+    srid: Option<i32>,
+) -> geom::LineString {
+    geom::LineString {
+        points: screen_points(&line.points, georef, srid),
+        srid: srid,
+    }
+}
+
+fn screen_polygon(
+    polygon: &screen::Polygon,
+    georef: &Fn(&screen::Point) -> (f64, f64),
+    srid: Option<i32>,
+) -> geom::Polygon {
+    geom::Polygon {
+        rings: polygon
+            .rings
+            .iter()
+            .map(|r| screen_line(r, georef, srid))
+            .collect(),
+        srid: srid,
+    }
+}
+
+/// Geometry from screen coordinates
+fn screen_to_geometry(
+    geom: &screen::Geometry,
+    georef: &Fn(&screen::Point) -> (f64, f64),
+    srid: Option<i32>,
+) -> GeometryType {
+    match geom {
+        &screen::Geometry::Point(ref g) => {
+            let (x, y) = georef(g);
+            GeometryType::Point(geom::Point::new(x, y, srid))
+        }
+        &screen::Geometry::MultiPoint(ref g) => GeometryType::MultiPoint(geom::MultiPoint {
+            points: screen_points(&g.points, georef, srid),
+            srid: srid,
+        }),
+        &screen::Geometry::LineString(ref g) => {
+            GeometryType::LineString(screen_line(g, georef, srid))
+        }
+        &screen::Geometry::MultiLineString(ref g) => {
+            GeometryType::MultiLineString(geom::MultiLineString {
+                lines: g.lines
+                    .iter()
+                    .map(|l| screen_line(l, georef, srid))
+                    .collect(),
+                srid: srid,
+            })
+        }
+        &screen::Geometry::Polygon(ref g) => {
+            GeometryType::Polygon(screen_polygon(g, georef, srid))
+        }
+        &screen::Geometry::MultiPolygon(ref g) => {
+            GeometryType::MultiPolygon(geom::MultiPolygon {
+                polygons: g.polygons
+                    .iter()
+                    .map(|p| screen_polygon(p, georef, srid))
+                    .collect(),
+                srid: srid,
+            })
+        }
+    }
+}
+
+/// Feature of a source tile
+struct TileFeature<'a> {
+    feature: &'a DecodedFeature,
+    /// Geometry clipped to the source tile without buffer
+    geometry: Result<screen::Geometry, String>,
+    /// Extent of source tile in Web Mercator
+    tile_extent: &'a Extent,
+    tile_size: u32,
+    grid_srid: i32,
+    transform: Transform,
+}
+
+impl<'a> Feature for TileFeature<'a> {
+    fn fid(&self) -> Option<u64> {
+        self.feature.id
+    }
+    fn attributes(&self) -> Vec<FeatureAttr> {
+        self.feature.attributes.clone()
+    }
+    fn geometry(&self) -> Result<GeometryType, String> {
+        let geom = self.geometry.as_ref().map_err(|e| e.clone())?;
+        let extent = self.tile_extent;
+        let scale = (extent.maxx - extent.minx) / f64::from(self.tile_size);
+        let transform = self.transform;
+        let georef = move |p: &screen::Point| {
+            transform.coords(
+                extent.minx + f64::from(p.x) * scale,
+                extent.maxy - f64::from(p.y) * scale,
+            )
+        };
+        Ok(screen_to_geometry(geom, &georef, Some(self.grid_srid)))
+    }
+}
+
+impl TilesDatasource {
+    pub fn mbtiles(path: &str) -> TilesDatasource {
+        TilesDatasource {
+            source: TileSource::MBTiles(path.to_string()),
+            minzoom: 0,
+            maxzoom: 14,
+        }
+    }
+    pub fn xyz(url: &str) -> TilesDatasource {
+        TilesDatasource {
+            source: TileSource::Xyz(url.to_string()),
+            minzoom: 0,
+            maxzoom: 14,
+        }
+    }
+    fn open(path: &str) -> Result<Connection, String> {
+        Connection::open_with_flags(path, OpenFlags::SQLITE_OPEN_READ_ONLY)
+            .map_err(|e| format!("{}: {}", path, e))
+    }
+    /// Value of MBTiles metadata entry
+    fn metadata(&self, name: &str) -> Option<String> {
+        let path = match self.source {
+            TileSource::MBTiles(ref path) => path,
+            TileSource::Xyz(_) => return None,
+        };
+        let conn = Self::open(path).map_err(|e| error!("{}", e)).ok()?;
+        conn.query_row(
+            "SELECT value FROM metadata WHERE name = ?",
+            &[&name as &ToSql],
+            |row| row.get::<_, String>(0),
+        ).ok()
+    }
+    /// Layer descriptions of MBTiles `json` metadata
+    fn vector_layers(&self) -> Vec<serde_json::Value> {
+        self.metadata("json")
+            .and_then(|json| serde_json::from_str::<serde_json::Value>(&json).ok())
+            .and_then(|json| json["vector_layers"].as_array().cloned())
+            .unwrap_or(Vec::new())
+    }
+    /// Raw tile data in XYZ adressing scheme. Empty if tile doesn't exist.
+    fn tile_data(&self, conn: Option<&Connection>, x: u32, y: u32, z: u8) -> Result<Vec<u8>, String> {
+        match (&self.source, conn) {
+            (&TileSource::MBTiles(_), Some(conn)) => {
+                // MBTiles rows are in TMS scheme
+                let row = (1u32 << z) - 1 - y;
+                match conn.query_row(
+                    "SELECT tile_data FROM tiles WHERE zoom_level = ? AND tile_column = ? AND tile_row = ?",
+                    &[&(z as i64) as &ToSql, &(x as i64), &(row as i64)],
+                    |row| row.get::<_, Vec<u8>>(0),
+                ) {
+                    Ok(data) => Ok(data),
+                    Err(::rusqlite::Error::QueryReturnedNoRows) => Ok(Vec::new()),
+                    Err(e) => Err(e.to_string()),
+                }
+            }
+            (&TileSource::Xyz(ref url), _) => http_get(
+                &url.replace("{z}", &z.to_string())
+                    .replace("{x}", &x.to_string())
+                    .replace("{y}", &y.to_string()),
+            ),
+            _ => Err("MBTiles not opened".to_string()),
+        }
+    }
+}
+
+impl DatasourceInput for TilesDatasource {
+    /// New instance with zoom range from MBTiles metadata
+    fn connected(&self) -> Result<TilesDatasource, String> {
+        let zoom = |name, default| {
+            self.metadata(name)
+                .and_then(|v| v.parse::<u8>().ok())
+                .unwrap_or(default)
+        };
+        Ok(TilesDatasource {
+            source: self.source.clone(),
+            minzoom: zoom("minzoom", self.minzoom),
+            maxzoom: zoom("maxzoom", self.maxzoom),
+        })
+    }
+    fn detect_layers(&self, _detect_geometry_types: bool) -> Vec<Layer> {
+        self.vector_layers()
+            .iter()
+            .filter_map(|vector_layer| vector_layer["id"].as_str())
+            .map(|id| {
+                let mut layer = Layer::new(id);
+                layer.table_name = Some(id.to_string());
+                layer.srid = Some(3857);
+                layer
+            })
+            .collect()
+    }
+    /// Return attribute names and types of MBTiles `vector_layers` metadata
+    fn detect_data_columns(&self, layer: &Layer, _sql: Option<&String>) -> Vec<(String, String)> {
+        let name = layer.table_name.as_ref().unwrap_or(&layer.name);
+        let vector_layers = self.vector_layers();
+        let fields = vector_layers
+            .iter()
+            .find(|vector_layer| vector_layer["id"].as_str() == Some(name))
+            .and_then(|vector_layer| vector_layer["fields"].as_object());
+        match fields {
+            Some(fields) => fields
+                .iter()
+                .map(|(key, field_type)| {
+                    let type_name = match field_type.as_str() {
+                        Some("Number") => "double",
+                        Some("Boolean") => "bool",
+                        _ => "string",
+                    };
+                    (key.clone(), type_name.to_string())
+                })
+                .collect(),
+            None => Vec::new(),
+        }
+    }
+    /// Projected extent
+    fn extent_from_wgs84(&self, extent: &Extent, dest_srid: i32) -> Option<Extent> {
+        Transform::new(4326, dest_srid)
+            .map(|t| t.extent(extent))
+            .map_err(|e| warn!("{}", e))
+            .ok()
+    }
+    /// Detect extent of layer (in WGS84)
+    fn layer_extent(&self, _layer: &Layer) -> Option<Extent> {
+        let bounds: Vec<f64> = self.metadata("bounds")?
+            .split(',')
+            .filter_map(|v| v.trim().parse().ok())
+            .collect();
+        if bounds.len() != 4 {
+            return None;
+        }
+        Some(Extent {
+            minx: bounds[0],
+            miny: bounds[1],
+            maxx: bounds[2],
+            maxy: bounds[3],
+        })
+    }
+    fn prepare_queries(&mut self, layer: &Layer, grid_srid: i32) {
+        if let Err(e) = Transform::new(3857, grid_srid) {
+            error!("Layer '{}': {}", layer.name, e);
+        }
+    }
+    /// Features of source tiles covering the extent.
+    /// Features crossing source tile borders are returned as separate parts clipped at the borders.
+    fn retrieve_features<F>(
+        &self,
+        layer: &Layer,
+        extent: &Extent,
+        zoom: u8,
+        grid: &Grid,
+        mut read: F,
+    ) where
+        F: FnMut(&Feature),
+    {
+        let (to_source, from_source) = match (Transform::new(grid.srid, 3857), Transform::new(3857, grid.srid)) {
+            (Ok(to_source), Ok(from_source)) => (to_source, from_source),
+            (Err(e), _) | (_, Err(e)) => {
+                error!("Layer '{}': {}", layer.name, e);
+                return;
+            }
+        };
+        let tile_extent = to_source.extent(extent);
+        let src_zoom = source_zoom(tile_extent.maxx - tile_extent.minx);
+        if src_zoom < self.minzoom as i32 {
+            debug!(
+                "Layer '{}': No source tiles below zoom level {}",
+                layer.name, self.minzoom
+            );
+            return;
+        }
+        let src_zoom = src_zoom.min(self.maxzoom as i32) as u8;
+        let bbox = layer_bbox(layer, extent, zoom, grid, 3857);
+        let ((minx, maxx), (miny, maxy)) = tile_range(&bbox, src_zoom);
+        let conn = match self.source {
+            TileSource::MBTiles(ref path) => match Self::open(path) {
+                Ok(conn) => Some(conn),
+                Err(e) => {
+                    error!("{}", e);
+                    return;
+                }
+            },
+            TileSource::Xyz(_) => None,
+        };
+        let source_layer = layer.table_name.as_ref().unwrap_or(&layer.name);
+        let source_grid = Grid::web_mercator();
+
+        let mut count = FeatureCount::new(layer, zoom);
+        'tiles: for y in miny..maxy + 1 {
+            for x in minx..maxx + 1 {
+                let data = match self.tile_data(conn.as_ref(), x, y, src_zoom) {
+                    Ok(data) => data,
+                    Err(e) => {
+                        error!("Layer '{}': {}", layer.name, e);
+                        continue;
+                    }
+                };
+                if data.is_empty() {
+                    continue;
+                }
+                let mvt_tile = match Tile::read_bytes(&data) {
+                    Ok(mvt_tile) => mvt_tile,
+                    Err(e) => {
+                        error!("Layer '{}': Invalid tile {}/{}/{}: {}", layer.name, src_zoom, x, y, e);
+                        continue;
+                    }
+                };
+                let mvt_layer = match mvt_tile
+                    .get_layers()
+                    .iter()
+                    .find(|l| l.get_name() == source_layer)
+                {
+                    Some(mvt_layer) => decode_layer(mvt_layer),
+                    None => continue,
+                };
+                let source_extent = source_grid.tile_extent_xyz(x, y, src_zoom);
+                // Features in the buffer of a source tile are read from its neighbour tiles
+                let size = f64::from(mvt_layer.extent);
+                let tile_rect = ClipRect {
+                    minx: 0.0,
+                    miny: 0.0,
+                    maxx: size,
+                    maxy: size,
+                };
+                let screen_coord = |p: &screen::Point| (f64::from(p.x), f64::from(p.y));
+                for decoded in &mvt_layer.features {
+                    let geometry = match decoded.geometry {
+                        Ok(ref geom) => match clip_geometry(geom, &screen_coord, &tile_rect) {
+                            Some(geom) => Ok(geom),
+                            None => continue,
+                        },
+                        Err(ref e) => Err(e.clone()),
+                    };
+                    let feature = TileFeature {
+                        feature: decoded,
+                        geometry: geometry,
+                        tile_extent: &source_extent,
+                        tile_size: mvt_layer.extent,
+                        grid_srid: grid.srid,
+                        transform: from_source,
+                    };
+                    read(&feature);
+                    if !count.add() {
+                        break 'tiles;
+                    }
+                }
+            }
+        }
+    }
+}
+
+impl<'a> Config<'a, DatasourceCfg> for TilesDatasource {
+    fn from_config(ds_cfg: &DatasourceCfg) -> Result<Self, String> {
+        let mut ds = match (&ds_cfg.mbtiles, &ds_cfg.tiles) {
+            (&Some(ref path), _) => TilesDatasource::mbtiles(path),
+            (_, &Some(ref url)) => {
+                check_url(url)?;
+                TilesDatasource::xyz(url)
+            }
+            _ => return Err("Unsupported datasource".to_string()),
+        };
+        if let Some(maxzoom) = ds_cfg.maxzoom {
+            ds.maxzoom = maxzoom;
+        }
+        Ok(ds)
+    }
+
+    fn gen_config() -> String {
+        let toml = r#"
+[[datasource]]
+name = "tiles"
+# MBTiles file with vector tiles
+mbtiles = "<filename>"
+# Vector tile server URL
+#tiles = "http://localhost:6767/tileset/{z}/{x}/{y}.pbf"
+# Maximal zoom level of source tiles. Higher zoom levels are cut from source tiles.
+#maxzoom = 14
+"#;
+        toml.to_string()
+    }
+    fn gen_runtime_config(&self) -> String {
+        let source = match self.source {
+            TileSource::MBTiles(ref path) => format!("mbtiles = \"{}\"", path),
+            TileSource::Xyz(ref url) => format!("tiles = \"{}\"", url),
+        };
+        format!(
+            r#"
+[[datasource]]
+{}
+maxzoom = {}
+"#,
+            source, self.maxzoom
+        )
+    }
+}
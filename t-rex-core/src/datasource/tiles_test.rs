@@ -0,0 +1,180 @@
+//
+// Copyright (c) Pirmin Kalberer. All rights reserved.
+// Licensed under the MIT License. See LICENSE file in the project root for full license information.
+//
+
+use core::feature::{FeatureAttr, FeatureAttrValType, FeatureStruct};
+use core::geom::{self, GeometryType};
+use core::grid::Grid;
+use core::layer::Layer;
+use datasource::tiles::{http_get, TilesDatasource};
+use datasource::DatasourceInput;
+use mvt::tile::Tile;
+use rusqlite::types::ToSql;
+use rusqlite::Connection;
+use std::env;
+use std::fs;
+use std::io::{Read, Write};
+use std::net::TcpListener;
+use std::thread;
+
+/// Gzip compressed tile 11/x/717 with layer `points` containing a point in tile 11/1072/717
+fn zurich_tile(x: u32) -> Vec<u8> {
+    let extent = Grid::web_mercator().tile_extent_xyz(x, 717, 11);
+    let mut tile = Tile::new(&extent, true);
+    let layer = Layer::new("points");
+    let mut mvt_layer = tile.new_layer(&layer);
+    let feature = FeatureStruct {
+        fid: Some(42),
+        attributes: vec![FeatureAttr {
+            key: String::from("name"),
+            value: FeatureAttrValType::String(String::from("Zürich")),
+        }],
+        geometry: GeometryType::Point(geom::Point::new(949398.0, 6002729.0, Some(3857))),
+    };
+    tile.add_feature(&mut mvt_layer, &feature);
+    tile.add_layer(mvt_layer);
+    let mut data = Vec::new();
+    Tile::write_gz_to(&mut data, &tile.mvt_tile);
+    data
+}
+
+fn create_mbtiles(name: &str) -> String {
+    let path = env::temp_dir().join(name);
+    let _ = fs::remove_file(&path);
+    let conn = Connection::open(&path).unwrap();
+    conn.execute_batch(
+        r#"CREATE TABLE metadata (name TEXT, value TEXT);
+         INSERT INTO metadata VALUES ('minzoom', '10');
+         INSERT INTO metadata VALUES ('maxzoom', '11');
+         INSERT INTO metadata VALUES ('bounds', '8.4,47.3,8.6,47.4');
+         INSERT INTO metadata VALUES ('json', '{"vector_layers": [{"id": "points", "fields": {"name": "String"}}]}');
+         CREATE TABLE tiles (zoom_level INTEGER, tile_column INTEGER, tile_row INTEGER, tile_data BLOB);"#,
+    ).unwrap();
+    // TMS row of XYZ tile 717
+    let row = (1 << 11) - 1 - 717;
+    conn.execute(
+        "INSERT INTO tiles VALUES (11, 1072, ?, ?)",
+        &[&row as &ToSql, &zurich_tile(1072)],
+    ).unwrap();
+    // Neighbour tile with the point in its buffer
+    conn.execute(
+        "INSERT INTO tiles VALUES (11, 1073, ?, ?)",
+        &[&row as &ToSql, &zurich_tile(1073)],
+    ).unwrap();
+    path.to_str().unwrap().to_string()
+}
+
+fn retrieve_points(ds: &TilesDatasource, layer: &Layer, x: u32, y: u32, zoom: u8) -> Vec<(f64, f64)> {
+    let grid = Grid::web_mercator();
+    let extent = grid.tile_extent_xyz(x, y, zoom);
+    let mut points = Vec::new();
+    ds.retrieve_features(layer, &extent, zoom, &grid, |feat| {
+        assert_eq!(feat.fid(), Some(42));
+        assert_eq!(feat.attributes()[0].key, "name");
+        match feat.geometry() {
+            Ok(GeometryType::Point(p)) => {
+                assert_eq!(p.srid, Some(3857));
+                points.push((p.x, p.y));
+            }
+            _ => panic!("Point expected"),
+        }
+    });
+    points
+}
+
+#[test]
+fn test_detect_layers() {
+    let ds = TilesDatasource::mbtiles(&create_mbtiles("t_rex_detect.mbtiles")).connected().unwrap();
+    assert_eq!((ds.minzoom, ds.maxzoom), (10, 11));
+    let layers = ds.detect_layers(true);
+    assert_eq!(layers.len(), 1);
+    assert_eq!(layers[0].name, "points");
+    assert_eq!(
+        ds.detect_data_columns(&layers[0], None),
+        vec![("name".to_string(), "string".to_string())]
+    );
+    let extent = ds.layer_extent(&layers[0]).unwrap();
+    assert_eq!((extent.minx, extent.maxy), (8.4, 47.4));
+}
+
+#[test]
+fn test_mbtiles_features() {
+    let ds = TilesDatasource::mbtiles(&create_mbtiles("t_rex_features.mbtiles")).connected().unwrap();
+    let layer = Layer::new("points");
+    let points = retrieve_points(&ds, &layer, 1072, 717, 11);
+    assert_eq!(points.len(), 1);
+    // Precision of 4096 pixel tile at zoom level 11: ~5m
+    assert!((points[0].0 - 949398.0).abs() < 5.0);
+    assert!((points[0].1 - 6002729.0).abs() < 5.0);
+
+    // Overzoomed from source zoom level 11
+    assert_eq!(retrieve_points(&ds, &layer, 4290, 2868, 13).len(), 1);
+    // Below minzoom
+    assert_eq!(retrieve_points(&ds, &layer, 1, 1, 2).len(), 0);
+    // Layer not in tile
+    assert_eq!(retrieve_points(&ds, &Layer::new("roads"), 1072, 717, 11).len(), 0);
+    // Features in the buffer of source tiles are skipped
+    assert_eq!(retrieve_points(&ds, &layer, 1073, 717, 11).len(), 0);
+    let mut buffered_layer = Layer::new("points");
+    buffered_layer.buffer_size = Some(64);
+    assert_eq!(retrieve_points(&ds, &buffered_layer, 1072, 717, 11).len(), 1);
+}
+
+#[test]
+fn test_xyz_features() {
+    let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+    let port = listener.local_addr().unwrap().port();
+    thread::spawn(move || {
+        let tile = zurich_tile(1072);
+        for stream in listener.incoming() {
+            let mut stream = stream.unwrap();
+            let mut request = String::new();
+            let mut buf = [0; 1024];
+            while !request.ends_with("\r\n\r\n") {
+                let len = stream.read(&mut buf).unwrap();
+                if len == 0 {
+                    break;
+                }
+                request.push_str(&String::from_utf8_lossy(&buf[..len]));
+            }
+            if request.starts_with("GET /moved/") {
+                let path = request.split_whitespace().nth(1).unwrap().replace("/moved", "/tiles");
+                write!(stream, "HTTP/1.0 302 Found\r\nLocation: {}\r\n\r\n", path).unwrap();
+            } else if request.starts_with("GET /large/") {
+                write!(stream, "HTTP/1.0 200 OK\r\n\r\n").unwrap();
+                let _ = stream.write_all(&vec![0; 16 * 1024 * 1024 + 1]);
+            } else if request.starts_with("GET /tiles/11/1072/717.pbf ") {
+                write!(
+                    stream,
+                    "HTTP/1.0 200 OK\r\nContent-Length: {}\r\n\r\n",
+                    tile.len()
+                ).unwrap();
+                stream.write_all(&tile).unwrap();
+            } else {
+                write!(stream, "HTTP/1.0 404 Not Found\r\n\r\n").unwrap();
+            }
+        }
+    });
+
+    let mut ds =
+        TilesDatasource::xyz(&format!("http://127.0.0.1:{}/tiles/{{z}}/{{x}}/{{y}}.pbf", port));
+    ds.maxzoom = 11;
+    let layer = Layer::new("points");
+    assert_eq!(retrieve_points(&ds, &layer, 1072, 717, 11).len(), 1);
+    assert_eq!(retrieve_points(&ds, &layer, 4290, 2868, 13).len(), 1);
+    assert_eq!(retrieve_points(&ds, &layer, 1073, 717, 11).len(), 0);
+
+    // Redirected requests
+    let mut ds =
+        TilesDatasource::xyz(&format!("http://127.0.0.1:{}/moved/{{z}}/{{x}}/{{y}}.pbf", port));
+    ds.maxzoom = 11;
+    assert_eq!(retrieve_points(&ds, &layer, 1072, 717, 11).len(), 1);
+
+    // Responses are limited to 16MB
+    let url = format!("http://127.0.0.1:{}/large/11/1072/717.pbf", port);
+    assert_eq!(
+        http_get(&url),
+        Err(format!("{}: Response larger than 16777216 bytes", url))
+    );
+}
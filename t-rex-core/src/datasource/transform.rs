@@ -0,0 +1,92 @@
+//
+// Copyright (c) Pirmin Kalberer. All rights reserved.
+// Licensed under the MIT License. See LICENSE file in the project root for full license information.
+//
+
+//! Coordinate transformations for datasources without PROJ support
+
+use core::geom::{self, GeometryType};
+use core::grid::{web_mercator_to_wgs84, wgs84_to_web_mercator, Extent};
+
+/// Supported transformation between two SRIDs
+#[derive(PartialEq, Clone, Copy, Debug)]
+pub enum Transform {
+    Identity,
+    Wgs84ToWebMercator,
+    WebMercatorToWgs84,
+}
+
+impl Transform {
+    pub fn new(src_srid: i32, dest_srid: i32) -> Result<Transform, String> {
+        match (src_srid, dest_srid) {
+            (src, dest) if src == dest => Ok(Transform::Identity),
+            (4326, 3857) => Ok(Transform::Wgs84ToWebMercator),
+            (3857, 4326) => Ok(Transform::WebMercatorToWgs84),
+            (src, dest) => Err(format!(
+                "Transformation from SRID {} to {} not supported",
+                src, dest
+            )),
+        }
+    }
+    pub fn coords(&self, x: f64, y: f64) -> (f64, f64) {
+        match self {
+            &Transform::Identity => (x, y),
+            &Transform::Wgs84ToWebMercator => wgs84_to_web_mercator(x, y),
+            &Transform::WebMercatorToWgs84 => web_mercator_to_wgs84(x, y),
+        }
+    }
+    /// Transformed extent. Both transformations preserve the axis order of coordinates.
+    pub fn extent(&self, extent: &Extent) -> Extent {
+        let (minx, miny) = self.coords(extent.minx, extent.miny);
+        let (maxx, maxy) = self.coords(extent.maxx, extent.maxy);
+        Extent {
+            minx: minx,
+            miny: miny,
+            maxx: maxx,
+            maxy: maxy,
+        }
+    }
+    fn point(&self, p: &geom::Point, srid: Option<i32>) -> geom::Point {
+        let (x, y) = self.coords(p.x, p.y);
+        geom::Point::new(x, y, srid)
+    }
+    fn line(&self, line: &geom::LineString, srid: Option<i32>) -> geom::LineString {
+        geom::LineString {
+            points: line.points.iter().map(|p| self.point(p, srid)).collect(),
+            srid: srid,
+        }
+    }
+    fn polygon(&self, polygon: &geom::Polygon, srid: Option<i32>) -> geom::Polygon {
+        geom::Polygon {
+            rings: polygon.rings.iter().map(|r| self.line(r, srid)).collect(),
+            srid: srid,
+        }
+    }
+    /// Transformed copy of geometry with SRID `srid`
+    pub fn geometry(&self, geom: &GeometryType, srid: Option<i32>) -> Result<GeometryType, String> {
+        match geom {
+            &GeometryType::Point(ref g) => Ok(GeometryType::Point(self.point(g, srid))),
+            &GeometryType::MultiPoint(ref g) => Ok(GeometryType::MultiPoint(geom::MultiPoint {
+                points: g.points.iter().map(|p| self.point(p, srid)).collect(),
+                srid: srid,
+            })),
+            &GeometryType::LineString(ref g) => Ok(GeometryType::LineString(self.line(g, srid))),
+            &GeometryType::MultiLineString(ref g) => {
+                Ok(GeometryType::MultiLineString(geom::MultiLineString {
+                    lines: g.lines.iter().map(|l| self.line(l, srid)).collect(),
+                    srid: srid,
+                }))
+            }
+            &GeometryType::Polygon(ref g) => Ok(GeometryType::Polygon(self.polygon(g, srid))),
+            &GeometryType::MultiPolygon(ref g) => {
+                Ok(GeometryType::MultiPolygon(geom::MultiPolygon {
+                    polygons: g.polygons.iter().map(|p| self.polygon(p, srid)).collect(),
+                    srid: srid,
+                }))
+            }
+            &GeometryType::GeometryCollection(_) => {
+                Err("GeometryCollection not supported".to_string())
+            }
+        }
+    }
+}
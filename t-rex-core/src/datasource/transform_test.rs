@@ -0,0 +1,40 @@
+//
+// Copyright (c) Pirmin Kalberer. All rights reserved.
+// Licensed under the MIT License. See LICENSE file in the project root for full license information.
+//
+
+use core::geom::{self, GeometryType};
+use core::grid::Extent;
+use datasource::transform::Transform;
+
+#[test]
+fn test_transform() {
+    assert_eq!(Transform::new(3857, 3857), Ok(Transform::Identity));
+    assert_eq!(
+        Transform::new(2056, 3857),
+        Err("Transformation from SRID 2056 to 3857 not supported".to_string())
+    );
+
+    let transform = Transform::new(4326, 3857).unwrap();
+    let (x, y) = transform.coords(8.6, 47.4);
+    assert!((x - 957347.6).abs() < 0.1);
+    assert!((y - 6007610.4).abs() < 0.1);
+
+    let extent = Transform::new(3857, 4326).unwrap().extent(&Extent {
+        minx: -20037508.342789244,
+        miny: -20037508.342789244,
+        maxx: 20037508.342789244,
+        maxy: 20037508.342789244,
+    });
+    assert!((extent.minx + 180.0).abs() < 1e-9);
+    assert!((extent.maxy - 85.0511287798066).abs() < 1e-9);
+
+    let geom = GeometryType::Point(geom::Point::new(8.6, 47.4, Some(4326)));
+    match transform.geometry(&geom, Some(3857)).unwrap() {
+        GeometryType::Point(p) => {
+            assert!((p.x - 957347.6).abs() < 0.1);
+            assert_eq!(p.srid, Some(3857));
+        }
+        _ => panic!("Point expected"),
+    }
+}
@@ -0,0 +1,183 @@
+//
+// Copyright (c) Pirmin Kalberer. All rights reserved.
+// Licensed under the MIT License. See LICENSE file in the project root for full license information.
+//
+
+//! Well-known binary (WKB) and GeoPackage binary geometry reader
+
+use core::geom::{self, GeometryType};
+
+/// Byte reader with byte order
+struct WkbReader<'a> {
+    data: &'a [u8],
+    pos: usize,
+    little_endian: bool,
+}
+
+impl<'a> WkbReader<'a> {
+    fn bytes(&mut self, n: usize) -> Result<&'a [u8], String> {
+        if self.pos + n > self.data.len() {
+            return Err("Unexpected end of WKB geometry".to_string());
+        }
+        let bytes = &self.data[self.pos..self.pos + n];
+        self.pos += n;
+        Ok(bytes)
+    }
+    fn u8(&mut self) -> Result<u8, String> {
+        Ok(self.bytes(1)?[0])
+    }
+    fn u32(&mut self) -> Result<u32, String> {
+        let b = self.bytes(4)?;
+        let mut v: u32 = 0;
+        for i in 0..4 {
+            let byte = if self.little_endian { b[3 - i] } else { b[i] };
+            v = (v << 8) | byte as u32;
+        }
+        Ok(v)
+    }
+    fn f64(&mut self) -> Result<f64, String> {
+        let b = self.bytes(8)?;
+        let mut v: u64 = 0;
+        for i in 0..8 {
+            let byte = if self.little_endian { b[7 - i] } else { b[i] };
+            v = (v << 8) | byte as u64;
+        }
+        Ok(f64::from_bits(v))
+    }
+}
+
+/// Geometry type and number of coordinate dimensions of ISO or extended WKB type code
+fn wkb_type(code: u32) -> (u32, usize) {
+    let zm = (code & 0x8000_0000 != 0) as usize + (code & 0x4000_0000 != 0) as usize;
+    let code = code & 0x0fff_ffff;
+    let dims = match code / 1000 {
+        1 | 2 => 3,
+        3 => 4,
+        _ => 2 + zm,
+    };
+    (code % 1000, dims)
+}
+
+fn read_point(r: &mut WkbReader, dims: usize, srid: Option<i32>) -> Result<geom::Point, String> {
+    let x = r.f64()?;
+    let y = r.f64()?;
+    for _ in 2..dims {
+        r.f64()?;
+    }
+    Ok(geom::Point::new(x, y, srid))
+}
+
+fn read_points(
+    r: &mut WkbReader,
+    dims: usize,
+    srid: Option<i32>,
+) -> Result<Vec<geom::Point>, String> {
+    let n = r.u32()?;
+    (0..n).map(|_| read_point(r, dims, srid)).collect()
+}
+
+fn read_rings(
+    r: &mut WkbReader,
+    dims: usize,
+    srid: Option<i32>,
+) -> Result<Vec<geom::LineString>, String> {
+    let n = r.u32()?;
+    (0..n)
+        .map(|_| {
+            Ok(geom::LineString {
+                points: read_points(r, dims, srid)?,
+                srid: srid,
+            })
+        })
+        .collect()
+}
+
+fn read_geometry(r: &mut WkbReader, srid: Option<i32>) -> Result<GeometryType, String> {
+    r.little_endian = r.u8()? == 1;
+    let (geom_type, dims) = wkb_type(r.u32()?);
+    // Members of multi geometries
+    let parts = |r: &mut WkbReader| -> Result<Vec<GeometryType>, String> {
+        let n = r.u32()?;
+        (0..n).map(|_| read_geometry(r, srid)).collect()
+    };
+    match geom_type {
+        1 => Ok(GeometryType::Point(read_point(r, dims, srid)?)),
+        2 => Ok(GeometryType::LineString(geom::LineString {
+            points: read_points(r, dims, srid)?,
+            srid: srid,
+        })),
+        3 => Ok(GeometryType::Polygon(geom::Polygon {
+            rings: read_rings(r, dims, srid)?,
+            srid: srid,
+        })),
+        4 => {
+            let mut points = Vec::new();
+            for part in parts(r)? {
+                match part {
+                    GeometryType::Point(p) => points.push(p),
+                    _ => return Err("Expected Point in MultiPoint".to_string()),
+                }
+            }
+            Ok(GeometryType::MultiPoint(geom::MultiPoint {
+                points: points,
+                srid: srid,
+            }))
+        }
+        5 => {
+            let mut lines = Vec::new();
+            for part in parts(r)? {
+                match part {
+                    GeometryType::LineString(l) => lines.push(l),
+                    _ => return Err("Expected LineString in MultiLineString".to_string()),
+                }
+            }
+            Ok(GeometryType::MultiLineString(geom::MultiLineString {
+                lines: lines,
+                srid: srid,
+            }))
+        }
+        6 => {
+            let mut polygons = Vec::new();
+            for part in parts(r)? {
+                match part {
+                    GeometryType::Polygon(p) => polygons.push(p),
+                    _ => return Err("Expected Polygon in MultiPolygon".to_string()),
+                }
+            }
+            Ok(GeometryType::MultiPolygon(geom::MultiPolygon {
+                polygons: polygons,
+                srid: srid,
+            }))
+        }
+        t => Err(format!("Unsupported WKB geometry type {}", t)),
+    }
+}
+
+/// Parse WKB geometry
+pub fn wkb_to_geometry(data: &[u8], srid: Option<i32>) -> Result<GeometryType, String> {
+    let mut r = WkbReader {
+        data: data,
+        pos: 0,
+        little_endian: true,
+    };
+    read_geometry(&mut r, srid)
+}
+
+/// Parse GeoPackage binary geometry (header followed by WKB)
+pub fn gpkg_to_geometry(data: &[u8], srid: Option<i32>) -> Result<GeometryType, String> {
+    if data.len() < 8 || &data[0..2] != b"GP" {
+        return Err("Invalid GeoPackage geometry header".to_string());
+    }
+    let flags = data[3];
+    let envelope_len = match (flags >> 1) & 0x07 {
+        0 => 0,
+        1 => 32,
+        2 | 3 => 48,
+        4 => 64,
+        e => return Err(format!("Invalid GeoPackage envelope indicator {}", e)),
+    };
+    if data.len() < 8 + envelope_len {
+        return Err("Invalid GeoPackage geometry envelope".to_string());
+    }
+    wkb_to_geometry(&data[8 + envelope_len..], srid)
+}
@@ -0,0 +1,122 @@
+//
+// Copyright (c) Pirmin Kalberer. All rights reserved.
+// Licensed under the MIT License. See LICENSE file in the project root for full license information.
+//
+
+use core::geom::GeometryType;
+use datasource::wkb::{gpkg_to_geometry, wkb_to_geometry};
+
+fn le_f64(v: f64) -> Vec<u8> {
+    (0..8).map(|i| (v.to_bits() >> (8 * i)) as u8).collect()
+}
+
+fn le_u32(v: u32) -> Vec<u8> {
+    (0..4).map(|i| (v >> (8 * i)) as u8).collect()
+}
+
+/// Little endian WKB point
+fn wkb_point(x: f64, y: f64) -> Vec<u8> {
+    let mut wkb = vec![1];
+    wkb.extend(le_u32(1));
+    wkb.extend(le_f64(x));
+    wkb.extend(le_f64(y));
+    wkb
+}
+
+#[test]
+fn test_wkb_point() {
+    let geom = wkb_to_geometry(&wkb_point(960000.0, 6002729.0), Some(3857)).unwrap();
+    match geom {
+        GeometryType::Point(p) => {
+            assert_eq!((p.x, p.y, p.srid), (960000.0, 6002729.0, Some(3857)))
+        }
+        _ => panic!("Point expected"),
+    }
+
+    // Big endian
+    let wkb = vec![
+        0, 0, 0, 0, 1, 0x40, 0x24, 0, 0, 0, 0, 0, 0, 0xc0, 0x34, 0, 0, 0, 0, 0, 0,
+    ];
+    match wkb_to_geometry(&wkb, None).unwrap() {
+        GeometryType::Point(p) => assert_eq!((p.x, p.y), (10.0, -20.0)),
+        _ => panic!("Point expected"),
+    }
+
+    assert_eq!(
+        wkb_to_geometry(&wkb[0..12], None).err(),
+        Some("Unexpected end of WKB geometry".to_string())
+    );
+}
+
+#[test]
+fn test_wkb_line_z() {
+    // ISO WKB LineString Z
+    let mut wkb = vec![1];
+    wkb.extend(le_u32(1002));
+    wkb.extend(le_u32(2));
+    for &(x, y, z) in &[(1.0, 2.0, 100.0), (3.0, 4.0, 200.0)] {
+        wkb.extend(le_f64(x));
+        wkb.extend(le_f64(y));
+        wkb.extend(le_f64(z));
+    }
+    match wkb_to_geometry(&wkb, None).unwrap() {
+        GeometryType::LineString(l) => {
+            let coords: Vec<(f64, f64)> = l.points.iter().map(|p| (p.x, p.y)).collect();
+            assert_eq!(coords, vec![(1.0, 2.0), (3.0, 4.0)]);
+        }
+        _ => panic!("LineString expected"),
+    }
+}
+
+#[test]
+fn test_wkb_multipolygon() {
+    let mut wkb = vec![1];
+    wkb.extend(le_u32(6));
+    wkb.extend(le_u32(2));
+    for offset in &[0.0, 10.0] {
+        wkb.push(1);
+        wkb.extend(le_u32(3));
+        wkb.extend(le_u32(1));
+        wkb.extend(le_u32(4));
+        for &(x, y) in &[(0.0, 0.0), (1.0, 0.0), (1.0, 1.0), (0.0, 0.0)] {
+            wkb.extend(le_f64(x + offset));
+            wkb.extend(le_f64(y));
+        }
+    }
+    match wkb_to_geometry(&wkb, None).unwrap() {
+        GeometryType::MultiPolygon(mp) => {
+            assert_eq!(mp.polygons.len(), 2);
+            assert_eq!(mp.polygons[1].rings[0].points.len(), 4);
+            assert_eq!(mp.polygons[1].rings[0].points[1].x, 11.0);
+        }
+        _ => panic!("MultiPolygon expected"),
+    }
+
+    let mut wkb = vec![1];
+    wkb.extend(le_u32(7));
+    wkb.extend(le_u32(0));
+    assert_eq!(
+        wkb_to_geometry(&wkb, None).err(),
+        Some("Unsupported WKB geometry type 7".to_string())
+    );
+}
+
+#[test]
+fn test_gpkg_geometry() {
+    // Little endian header with xy envelope
+    let mut gpb = vec![b'G', b'P', 0, 0b0000_0011];
+    gpb.extend(le_u32(3857));
+    for v in &[1.0, 1.0, 2.0, 2.0] {
+        gpb.extend(le_f64(*v));
+    }
+    gpb.extend(wkb_point(1.0, 2.0));
+    match gpkg_to_geometry(&gpb, Some(3857)).unwrap() {
+        GeometryType::Point(p) => assert_eq!((p.x, p.y), (1.0, 2.0)),
+        _ => panic!("Point expected"),
+    }
+
+    assert_eq!(
+        gpkg_to_geometry(&wkb_point(1.0, 2.0), None).err(),
+        Some("Invalid GeoPackage geometry header".to_string())
+    );
+}
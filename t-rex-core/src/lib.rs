@@ -12,11 +12,14 @@ extern crate postgres;
 extern crate protobuf;
 extern crate r2d2;
 extern crate r2d2_postgres;
+extern crate rusqlite;
 extern crate serde;
 #[macro_use]
 extern crate serde_derive;
+#[macro_use]
 extern crate serde_json;
 extern crate toml;
+extern crate ureq;
 
 pub mod cache;
 pub mod core;
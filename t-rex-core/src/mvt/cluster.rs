@@ -0,0 +1,158 @@
+//
+// Copyright (c) Pirmin Kalberer. All rights reserved.
+// Licensed under the MIT License. See LICENSE file in the project root for full license information.
+//
+
+//! Point clustering in screen coordinates
+
+use core::feature::{FeatureAttr, FeatureAttrValType};
+use core::layer::{AggregateFunc, ClusterAggregate, LayerCluster};
+use core::screen;
+use std::collections::HashMap;
+
+/// Attribute containing the number of clustered points
+pub const POINT_COUNT_ATTR: &str = "point_count";
+
+/// Point feature in screen coordinates
+#[derive(Debug)]
+pub struct ScreenPointFeature {
+    pub fid: Option<u64>,
+    pub attributes: Vec<FeatureAttr>,
+    pub point: screen::Point,
+}
+
+/// Greedy grid based point clustering
+pub struct PointClusterer {
+    /// Cluster radius in screen units
+    radius: i32,
+    aggregates: Vec<ClusterAggregate>,
+    features: Vec<ScreenPointFeature>,
+}
+
+fn cell(point: &screen::Point, size: i32) -> (i32, i32) {
+    (
+        (point.x as f64 / size as f64).floor() as i32,
+        (point.y as f64 / size as f64).floor() as i32,
+    )
+}
+
+fn aggregate(
+    agg: &ClusterAggregate,
+    members: &Vec<ScreenPointFeature>,
+) -> Option<FeatureAttrValType> {
+    let mut result: Option<f64> = None;
+    let mut integral = true;
+    for feature in members {
+        let value = feature
+            .attributes
+            .iter()
+            .find(|attr| attr.key == agg.field)
+            .and_then(|attr| attr.value.numeric_value());
+        if let Some((v, is_int)) = value {
+            integral = integral && is_int;
+            result = Some(match (result, agg.func) {
+                (None, _) => v,
+                (Some(r), AggregateFunc::Sum) => r + v,
+                (Some(r), AggregateFunc::Min) => r.min(v),
+                (Some(r), AggregateFunc::Max) => r.max(v),
+            });
+        }
+    }
+    result.map(|v| {
+        if integral {
+            FeatureAttrValType::Int(v as i64)
+        } else {
+            FeatureAttrValType::Double(v)
+        }
+    })
+}
+
+impl PointClusterer {
+    /// Create clusterer with `pixel_size` screen units per pixel
+    pub fn new(cluster: &LayerCluster, pixel_size: f64) -> PointClusterer {
+        PointClusterer {
+            radius: ((cluster.radius as f64 * pixel_size).round() as i32).max(1),
+            aggregates: cluster.aggregates.clone(),
+            features: Vec::new(),
+        }
+    }
+    pub fn add(&mut self, feature: ScreenPointFeature) {
+        self.features.push(feature);
+    }
+    /// Cluster collected points. Points without neighbours are returned unchanged.
+    pub fn clusters(self) -> Vec<ScreenPointFeature> {
+        let radius = self.radius;
+        let mut grid: HashMap<(i32, i32), Vec<usize>> = HashMap::new();
+        for (i, feature) in self.features.iter().enumerate() {
+            grid.entry(cell(&feature.point, radius))
+                .or_insert(Vec::new())
+                .push(i);
+        }
+        let mut assigned = vec![false; self.features.len()];
+        let mut groups = Vec::new();
+        for i in 0..self.features.len() {
+            if assigned[i] {
+                continue;
+            }
+            assigned[i] = true;
+            let mut members = vec![i];
+            let center = self.features[i].point;
+            let (cx, cy) = cell(&center, radius);
+            for x in cx - 1..cx + 2 {
+                for y in cy - 1..cy + 2 {
+                    if let Some(indices) = grid.get(&(x, y)) {
+                        for &j in indices {
+                            if assigned[j] {
+                                continue;
+                            }
+                            let p = self.features[j].point;
+                            let dx = (p.x - center.x) as i64;
+                            let dy = (p.y - center.y) as i64;
+                            if dx * dx + dy * dy <= radius as i64 * radius as i64 {
+                                assigned[j] = true;
+                                members.push(j);
+                            }
+                        }
+                    }
+                }
+            }
+            groups.push(members);
+        }
+
+        let mut features: Vec<Option<ScreenPointFeature>> =
+            self.features.into_iter().map(|f| Some(f)).collect();
+        let mut clusters = Vec::new();
+        for members in groups {
+            let mut members: Vec<ScreenPointFeature> =
+                members.iter().filter_map(|&i| features[i].take()).collect();
+            if members.len() == 1 {
+                clusters.push(members.pop().unwrap());
+                continue;
+            }
+            let n = members.len() as i64;
+            let sx: i64 = members.iter().map(|f| f.point.x as i64).sum();
+            let sy: i64 = members.iter().map(|f| f.point.y as i64).sum();
+            let mut attributes = vec![FeatureAttr {
+                key: POINT_COUNT_ATTR.to_string(),
+                value: FeatureAttrValType::Int(n),
+            }];
+            for agg in &self.aggregates {
+                if let Some(value) = aggregate(agg, &members) {
+                    attributes.push(FeatureAttr {
+                        key: agg.name.clone(),
+                        value: value,
+                    });
+                }
+            }
+            clusters.push(ScreenPointFeature {
+                fid: None,
+                attributes: attributes,
+                point: screen::Point {
+                    x: (sx / n) as i32,
+                    y: (sy / n) as i32,
+                },
+            });
+        }
+        clusters
+    }
+}
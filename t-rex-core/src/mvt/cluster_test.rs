@@ -0,0 +1,113 @@
+//
+// Copyright (c) Pirmin Kalberer. All rights reserved.
+// Licensed under the MIT License. See LICENSE file in the project root for full license information.
+//
+
+use core::feature::{FeatureAttr, FeatureAttrValType, FeatureStruct};
+use core::geom;
+use core::geom::GeometryType;
+use core::grid::Extent;
+use core::layer::{AggregateFunc, ClusterAggregate, Layer, LayerCluster};
+use core::screen;
+use mvt::cluster::{PointClusterer, ScreenPointFeature};
+use mvt::tile::Tile;
+
+fn point_feature(fid: u64, x: i32, y: i32, capacity: i64) -> ScreenPointFeature {
+    ScreenPointFeature {
+        fid: Some(fid),
+        attributes: vec![FeatureAttr {
+            key: "capacity".to_string(),
+            value: FeatureAttrValType::Int(capacity),
+        }],
+        point: screen::Point { x: x, y: y },
+    }
+}
+
+fn layer_cluster() -> LayerCluster {
+    LayerCluster {
+        radius: 10,
+        maxzoom: Some(11),
+        aggregates: vec![
+            ClusterAggregate {
+                field: "capacity".to_string(),
+                func: AggregateFunc::Sum,
+                name: "capacity_sum".to_string(),
+            },
+            ClusterAggregate {
+                field: "capacity".to_string(),
+                func: AggregateFunc::Max,
+                name: "capacity_max".to_string(),
+            },
+        ],
+    }
+}
+
+#[test]
+fn test_clustering() {
+    // radius 10px with 16 screen units per pixel
+    let mut clusterer = PointClusterer::new(&layer_cluster(), 16.0);
+    clusterer.add(point_feature(1, 100, 100, 5));
+    clusterer.add(point_feature(2, 200, 150, 7));
+    clusterer.add(point_feature(3, 3000, 3000, 1));
+    clusterer.add(point_feature(4, 150, 30, 2));
+    let clusters = clusterer.clusters();
+    assert_eq!(clusters.len(), 2);
+
+    assert_eq!(clusters[0].fid, None);
+    assert_eq!(clusters[0].point, screen::Point { x: 150, y: 93 });
+    assert_eq!(clusters[0].attributes[0].key, "point_count");
+    assert_eq!(clusters[0].attributes[0].value, FeatureAttrValType::Int(3));
+    assert_eq!(clusters[0].attributes[1].key, "capacity_sum");
+    assert_eq!(clusters[0].attributes[1].value, FeatureAttrValType::Int(14));
+    assert_eq!(clusters[0].attributes[2].key, "capacity_max");
+    assert_eq!(clusters[0].attributes[2].value, FeatureAttrValType::Int(7));
+
+    // Single points are unchanged
+    assert_eq!(clusters[1].fid, Some(3));
+    assert_eq!(clusters[1].point, screen::Point { x: 3000, y: 3000 });
+    assert_eq!(clusters[1].attributes.len(), 1);
+}
+
+#[test]
+fn test_cluster_tile() {
+    let extent = Extent {
+        minx: 0.0,
+        miny: 0.0,
+        maxx: 4096.0,
+        maxy: 4096.0,
+    };
+    let mut tile = Tile::new(&extent, false);
+    let layer = Layer::new("points");
+    let mut mvt_layer = tile.new_layer(&layer);
+    mvt_layer.set_cluster(&layer_cluster(), 16.0);
+    for i in 0..100 {
+        let feature = FeatureStruct {
+            fid: Some(i),
+            attributes: vec![FeatureAttr {
+                key: "capacity".to_string(),
+                value: FeatureAttrValType::Double(0.5),
+            }],
+            geometry: GeometryType::Point(geom::Point::new(
+                1000.0 + (i % 10) as f64,
+                1000.0 + (i / 10) as f64,
+                None,
+            )),
+        };
+        tile.add_feature(&mut mvt_layer, &feature);
+    }
+    tile.add_layer(mvt_layer);
+
+    let mvt_layer = &tile.mvt_tile.get_layers()[0];
+    assert_eq!(mvt_layer.get_features().len(), 1);
+    assert_eq!(
+        mvt_layer.get_keys(),
+        &[
+            "point_count".to_string(),
+            "capacity_sum".to_string(),
+            "capacity_max".to_string(),
+        ]
+    );
+    assert_eq!(mvt_layer.get_values()[0].get_int_value(), 100);
+    assert_eq!(mvt_layer.get_values()[1].get_double_value(), 50.0);
+    assert_eq!(mvt_layer.get_values()[2].get_double_value(), 0.5);
+}
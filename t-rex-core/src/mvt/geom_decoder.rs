@@ -0,0 +1,116 @@
+//
+// Copyright (c) Pirmin Kalberer. All rights reserved.
+// Licensed under the MIT License. See LICENSE file in the project root for full license information.
+//
+
+//! Decode MVT geometries into screen coordinates
+//! https://github.com/mapbox/vector-tile-spec/tree/master/2.1#43-geometry-encoding
+
+use core::screen;
+use core::screen::ring_area;
+use mvt::vector_tile::Tile_GeomType;
+
+const MOVE_TO: u32 = 1;
+const LINE_TO: u32 = 2;
+const CLOSE_PATH: u32 = 7;
+
+fn decode_param(value: u32) -> i32 {
+    ((value >> 1) as i32) ^ (-((value & 1) as i32))
+}
+
+/// Read commands and parameters of a geometry into paths of absolute coordinates
+fn decode_paths(commands: &[u32]) -> Result<Vec<(Vec<screen::Point>, bool)>, String> {
+    let mut paths: Vec<(Vec<screen::Point>, bool)> = Vec::new();
+    let (mut x, mut y) = (0i32, 0i32);
+    let mut i = 0;
+    while i < commands.len() {
+        let id = commands[i] & 0x7;
+        let count = (commands[i] >> 3) as usize;
+        i += 1;
+        match id {
+            MOVE_TO | LINE_TO => {
+                if i + 2 * count > commands.len() {
+                    return Err(format!("Missing parameters at position {}", i));
+                }
+                for _ in 0..count {
+                    x = x.wrapping_add(decode_param(commands[i]));
+                    y = y.wrapping_add(decode_param(commands[i + 1]));
+                    i += 2;
+                    if id == MOVE_TO {
+                        paths.push((Vec::new(), false));
+                    }
+                    match paths.last_mut() {
+                        Some(path) => path.0.push(screen::Point { x: x, y: y }),
+                        None => return Err("LineTo without MoveTo".to_string()),
+                    }
+                }
+            }
+            CLOSE_PATH => match paths.last_mut() {
+                Some(path) => path.1 = true,
+                None => return Err("ClosePath without MoveTo".to_string()),
+            },
+            _ => return Err(format!("Unknown command {}", id)),
+        }
+    }
+    Ok(paths)
+}
+
+/// Decode MVT geometry commands
+pub fn decode_geom(geom_type: Tile_GeomType, commands: &[u32]) -> Result<screen::Geometry, String> {
+    let paths = decode_paths(commands)?;
+    if paths.is_empty() {
+        return Err("Empty geometry".to_string());
+    }
+    match geom_type {
+        Tile_GeomType::POINT => {
+            let mut points: Vec<screen::Point> =
+                paths.into_iter().flat_map(|(pts, _)| pts).collect();
+            if points.len() == 1 {
+                Ok(screen::Geometry::Point(points.remove(0)))
+            } else {
+                Ok(screen::Geometry::MultiPoint(screen::MultiPoint {
+                    points: points,
+                }))
+            }
+        }
+        Tile_GeomType::LINESTRING => {
+            let mut lines: Vec<screen::LineString> = paths
+                .into_iter()
+                .map(|(pts, _)| screen::LineString { points: pts })
+                .collect();
+            if lines.len() == 1 {
+                Ok(screen::Geometry::LineString(lines.remove(0)))
+            } else {
+                Ok(screen::Geometry::MultiLineString(screen::MultiLineString {
+                    lines: lines,
+                }))
+            }
+        }
+        Tile_GeomType::POLYGON => {
+            let mut polygons: Vec<screen::Polygon> = Vec::new();
+            for (mut pts, closed) in paths {
+                if !closed {
+                    return Err("Polygon ring without ClosePath".to_string());
+                }
+                // Repeat first point, like in the geometries passed to the encoder
+                let first = pts[0];
+                pts.push(first);
+                let ring = screen::LineString { points: pts };
+                // Exterior rings have a positive area and start a new polygon
+                if ring_area(&ring) > 0.0 || polygons.is_empty() {
+                    polygons.push(screen::Polygon { rings: vec![ring] });
+                } else {
+                    polygons.last_mut().unwrap().rings.push(ring);
+                }
+            }
+            if polygons.len() == 1 {
+                Ok(screen::Geometry::Polygon(polygons.remove(0)))
+            } else {
+                Ok(screen::Geometry::MultiPolygon(screen::MultiPolygon {
+                    polygons: polygons,
+                }))
+            }
+        }
+        Tile_GeomType::UNKNOWN => Err("Unknown geometry type".to_string()),
+    }
+}
@@ -193,3 +193,16 @@ impl EncodableGeom for screen::MultiPolygon {
         }
     }
 }
+
+impl EncodableGeom for screen::Geometry {
+    fn encode_from(&self, startpos: &screen::Point, seq: &mut CommandSequence) {
+        match self {
+            &screen::Geometry::Point(ref g) => g.encode_from(startpos, seq),
+            &screen::Geometry::MultiPoint(ref g) => g.encode_from(startpos, seq),
+            &screen::Geometry::LineString(ref g) => g.encode_from(startpos, seq),
+            &screen::Geometry::MultiLineString(ref g) => g.encode_from(startpos, seq),
+            &screen::Geometry::Polygon(ref g) => g.encode_from(startpos, seq),
+            &screen::Geometry::MultiPolygon(ref g) => g.encode_from(startpos, seq),
+        }
+    }
+}
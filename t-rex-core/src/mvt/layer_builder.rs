@@ -0,0 +1,420 @@
+//
+// Copyright (c) Pirmin Kalberer. All rights reserved.
+// Licensed under the MIT License. See LICENSE file in the project root for full license information.
+//
+
+use core::feature::{FeatureAttr, FeatureAttrValType};
+use core::layer::{FeatureOrder, FidMode, LayerCluster, LayerLabel, LayerQuery, LayerThin};
+use core::screen;
+use core::screen::{geometry_area, geometry_length};
+use mvt::cluster::{PointClusterer, ScreenPointFeature};
+use mvt::geom_encoder::EncodableGeom;
+use mvt::merge::FeatureMerger;
+use mvt::thin::PointThinner;
+use mvt::vector_tile;
+use protobuf::{Message, RepeatedField};
+use std::cmp::Ordering;
+use std::collections::HashMap;
+
+/// Hashable representation of an attribute value (floats compared by bit pattern)
+#[derive(PartialEq, Eq, Hash, Debug)]
+enum ValueKey {
+    String(String),
+    Float(u32),
+    Double(u64),
+    Int(i64),
+    UInt(u64),
+    SInt(i64),
+    Bool(bool),
+}
+
+impl<'a> From<&'a FeatureAttrValType> for ValueKey {
+    fn from(value: &FeatureAttrValType) -> ValueKey {
+        match value {
+            &FeatureAttrValType::String(ref v) => ValueKey::String(v.clone()),
+            &FeatureAttrValType::Float(v) => ValueKey::Float(v.to_bits()),
+            &FeatureAttrValType::Double(v) => ValueKey::Double(v.to_bits()),
+            &FeatureAttrValType::Int(v) => ValueKey::Int(v),
+            &FeatureAttrValType::UInt(v) => ValueKey::UInt(v),
+            &FeatureAttrValType::SInt(v) => ValueKey::SInt(v),
+            &FeatureAttrValType::Bool(v) => ValueKey::Bool(v),
+        }
+    }
+}
+
+const FNV_OFFSET_BASIS: u64 = 0xcbf29ce484222325;
+const FNV_PRIME: u64 = 0x100000001b3;
+/// Largest integer exactly representable in JavaScript (Mapbox GL feature IDs)
+const MAX_SAFE_INTEGER: u64 = (1 << 53) - 1;
+
+/// FNV-1a hash (stable across platforms and releases)
+fn fnv_hash(hash: u64, bytes: &[u8]) -> u64 {
+    bytes
+        .iter()
+        .fold(hash, |h, b| (h ^ *b as u64).wrapping_mul(FNV_PRIME))
+}
+
+fn hash_value(hash: u64, value: &FeatureAttrValType) -> u64 {
+    match value {
+        &FeatureAttrValType::String(ref s) => fnv_hash(hash, s.as_bytes()),
+        _ => fnv_hash(hash, value.to_json().to_string().as_bytes()),
+    }
+}
+
+/// Sort key of a feature
+#[derive(PartialEq, PartialOrd, Debug)]
+enum OrderKey {
+    /// Position in list of ordered values
+    Rank(usize),
+    Number(f64),
+    Text(String),
+    Missing,
+}
+
+/// MVT layer under construction.
+///
+/// Keys and values are interned in hash tables. The protobuf layer
+/// is materialized by `into_layer` after all features are added.
+pub struct LayerBuilder {
+    name: String,
+    extent: u32,
+    keys: Vec<String>,
+    key_index: HashMap<String, u32>,
+    values: Vec<vector_tile::Tile_Value>,
+    value_index: HashMap<ValueKey, u32>,
+    features: Vec<vector_tile::Tile_Feature>,
+    cluster: Option<PointClusterer>,
+    thinner: Option<PointThinner>,
+    merger: Option<FeatureMerger>,
+    label: Option<LayerLabel>,
+    /// Field selection and renaming
+    attribute_query: Option<LayerQuery>,
+    order: Option<FeatureOrder>,
+    fid_mode: FidMode,
+    fid_field: Option<String>,
+    /// Last ID of FidMode::Sequence
+    last_fid: u64,
+    /// Minimal polygon area in screen units
+    min_area: Option<f64>,
+    /// Minimal line length in screen units
+    min_length: Option<f64>,
+    /// Separate layer for label points
+    label_layer: Option<Box<LayerBuilder>>,
+}
+
+impl LayerBuilder {
+    pub fn new(name: &str, extent: u32) -> LayerBuilder {
+        LayerBuilder {
+            name: name.to_string(),
+            extent: extent,
+            keys: Vec::new(),
+            key_index: HashMap::new(),
+            values: Vec::new(),
+            value_index: HashMap::new(),
+            features: Vec::new(),
+            cluster: None,
+            thinner: None,
+            merger: None,
+            label: None,
+            attribute_query: None,
+            order: None,
+            fid_mode: FidMode::Column,
+            fid_field: None,
+            last_fid: 0,
+            min_area: None,
+            min_length: None,
+            label_layer: None,
+        }
+    }
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+    /// Width and height of the layer in screen coordinates
+    pub fn extent(&self) -> u32 {
+        self.extent
+    }
+    fn key_idx(&mut self, key: &str) -> u32 {
+        if let Some(idx) = self.key_index.get(key) {
+            return *idx;
+        }
+        let idx = self.keys.len() as u32;
+        self.keys.push(key.to_string());
+        self.key_index.insert(key.to_string(), idx);
+        idx
+    }
+    fn value_idx(&mut self, value: &FeatureAttrValType) -> u32 {
+        let value_key = ValueKey::from(value);
+        if let Some(idx) = self.value_index.get(&value_key) {
+            return *idx;
+        }
+        let idx = self.values.len() as u32;
+        self.values.push(value.mvt_value());
+        self.value_index.insert(value_key, idx);
+        idx
+    }
+    /// Add key/value pair to feature tags
+    pub fn add_feature_attribute(
+        &mut self,
+        mvt_feature: &mut vector_tile::Tile_Feature,
+        key: &str,
+        value: &FeatureAttrValType,
+    ) {
+        let keyidx = self.key_idx(key);
+        let validx = self.value_idx(value);
+        mvt_feature.mut_tags().push(keyidx);
+        mvt_feature.mut_tags().push(validx);
+    }
+    pub fn push_feature(&mut self, mvt_feature: vector_tile::Tile_Feature) {
+        self.features.push(mvt_feature);
+    }
+    pub fn feature_count(&self) -> usize {
+        self.features.len()
+    }
+    /// Collect points for clustering instead of encoding them directly
+    pub fn set_cluster(&mut self, cluster: &LayerCluster, pixel_size: f64) {
+        self.cluster = Some(PointClusterer::new(cluster, pixel_size));
+    }
+    pub fn is_clustering(&self) -> bool {
+        self.cluster.is_some()
+    }
+    pub fn add_cluster_point(&mut self, feature: ScreenPointFeature) {
+        if let Some(ref mut clusterer) = self.cluster {
+            clusterer.add(feature);
+        }
+    }
+    /// Collect points for thinning instead of encoding them directly
+    pub fn set_thin(&mut self, thin: &LayerThin, pixel_size: f64) {
+        self.thinner = Some(PointThinner::new(thin, pixel_size));
+    }
+    pub fn is_thinning(&self) -> bool {
+        self.thinner.is_some()
+    }
+    pub fn add_thin_point(&mut self, feature: ScreenPointFeature) {
+        if let Some(ref mut thinner) = self.thinner {
+            thinner.add(feature);
+        }
+    }
+    /// Collect lines and polygons for merging features with equal attributes
+    pub fn set_merge(&mut self) {
+        self.merger = Some(FeatureMerger::new());
+    }
+    pub fn is_merging(&self) -> bool {
+        self.merger.is_some()
+    }
+    /// Add feature with tags for merging
+    pub fn add_merge_feature(
+        &mut self,
+        mvt_feature: vector_tile::Tile_Feature,
+        geometry: screen::Geometry,
+    ) {
+        let rejected = match self.merger {
+            Some(ref mut merger) => merger.add(mvt_feature, geometry),
+            None => Some((mvt_feature, geometry)),
+        };
+        if let Some((mut mvt_feature, geometry)) = rejected {
+            mvt_feature.set_geometry(geometry.encode().vec());
+            self.push_feature(mvt_feature);
+        }
+    }
+    /// Drop polygons and lines smaller than `min_area` square pixels or `min_length` pixels
+    pub fn set_min_size(&mut self, min_area: Option<f64>, min_length: Option<f64>, pixel_size: f64) {
+        self.min_area = min_area.map(|area| area * pixel_size * pixel_size);
+        self.min_length = min_length.map(|length| length * pixel_size);
+    }
+    pub fn has_min_size(&self) -> bool {
+        self.min_area.is_some() || self.min_length.is_some()
+    }
+    /// Polygon or line below minimal size
+    pub fn is_too_small(&self, geom: &screen::Geometry) -> bool {
+        match geom {
+            &screen::Geometry::Polygon(_) | &screen::Geometry::MultiPolygon(_) => self.min_area
+                .map_or(false, |min_area| geometry_area(geom) < min_area),
+            &screen::Geometry::LineString(_) | &screen::Geometry::MultiLineString(_) => self
+                .min_length
+                .map_or(false, |min_length| geometry_length(geom) < min_length),
+            _ => false,
+        }
+    }
+    /// Apply field selection and renaming of query to added features
+    pub fn set_attribute_query(&mut self, query: &LayerQuery) {
+        self.attribute_query = Some(query.clone());
+    }
+    pub fn filter_attributes(&self, attributes: Vec<FeatureAttr>) -> Vec<FeatureAttr> {
+        match self.attribute_query {
+            Some(ref query) => query.filter_attributes(attributes),
+            None => attributes,
+        }
+    }
+    /// Set feature ID generation
+    pub fn set_fid_mode(&mut self, fid_mode: FidMode, fid_field: Option<&String>) {
+        self.fid_mode = fid_mode;
+        self.fid_field = fid_field.cloned();
+    }
+    /// Feature ID of datasource feature ID or attributes
+    pub fn feature_id(&mut self, fid: Option<u64>, attributes: &Vec<FeatureAttr>) -> Option<u64> {
+        match self.fid_mode {
+            FidMode::Column => fid,
+            FidMode::Hash => {
+                let field = self.fid_field.as_ref()?;
+                attributes
+                    .iter()
+                    .find(|attr| attr.key == *field)
+                    .map(|attr| match attr.value {
+                        FeatureAttrValType::Int(v) if v >= 0 => v as u64,
+                        FeatureAttrValType::UInt(v) => v,
+                        ref v => hash_value(FNV_OFFSET_BASIS, v) & MAX_SAFE_INTEGER,
+                    })
+            }
+            FidMode::Attributes => {
+                let hash = attributes.iter().fold(FNV_OFFSET_BASIS, |h, attr| {
+                    let h = fnv_hash(h, attr.key.as_bytes());
+                    let h = fnv_hash(h, &[0]);
+                    let h = hash_value(h, &attr.value);
+                    fnv_hash(h, &[0])
+                });
+                Some(hash & MAX_SAFE_INTEGER)
+            }
+            FidMode::Sequence => {
+                self.last_fid += 1;
+                Some(self.last_fid)
+            }
+        }
+    }
+    /// Sort features when building the layer
+    pub fn set_order(&mut self, order: &FeatureOrder) {
+        self.order = Some(order.clone());
+    }
+    fn order_key(
+        &self,
+        order: &FeatureOrder,
+        keyidx: Option<u32>,
+        mvt_feature: &vector_tile::Tile_Feature,
+    ) -> OrderKey {
+        let value = keyidx
+            .and_then(|keyidx| {
+                mvt_feature
+                    .get_tags()
+                    .chunks(2)
+                    .find(|tag| tag.len() == 2 && tag[0] == keyidx)
+                    .map(|tag| tag[1])
+            })
+            .and_then(|validx| self.values.get(validx as usize))
+            .and_then(FeatureAttrValType::from_mvt_value);
+        match value {
+            None => OrderKey::Missing,
+            Some(FeatureAttrValType::String(ref s)) if order.values.is_empty() => {
+                OrderKey::Text(s.clone())
+            }
+            Some(ref v) if order.values.is_empty() => match v {
+                &FeatureAttrValType::Bool(b) => OrderKey::Number(b as i32 as f64),
+                _ => OrderKey::Number(v.numeric_value().map_or(0.0, |(n, _)| n)),
+            },
+            Some(ref v) => {
+                let text = match v {
+                    &FeatureAttrValType::String(ref s) => s.clone(),
+                    _ => format!("{}", v.to_json()),
+                };
+                match order.values.iter().position(|ov| *ov == text) {
+                    Some(rank) => OrderKey::Rank(rank),
+                    None => OrderKey::Missing,
+                }
+            }
+        }
+    }
+    fn sort_features(&mut self) {
+        let order = match self.order.take() {
+            Some(order) => order,
+            None => return,
+        };
+        let keyidx = self.key_index.get(&order.field).cloned();
+        let features = ::std::mem::replace(&mut self.features, Vec::new());
+        let mut keyed: Vec<(OrderKey, vector_tile::Tile_Feature)> = features
+            .into_iter()
+            .map(|f| (self.order_key(&order, keyidx, &f), f))
+            .collect();
+        // Stable sort, features without value at the end
+        keyed.sort_by(|a, b| match (&a.0, &b.0) {
+            (&OrderKey::Missing, &OrderKey::Missing) => Ordering::Equal,
+            (&OrderKey::Missing, _) => Ordering::Greater,
+            (_, &OrderKey::Missing) => Ordering::Less,
+            (ka, kb) => {
+                let ord = ka.partial_cmp(kb).unwrap_or(Ordering::Equal);
+                if order.descending {
+                    ord.reverse()
+                } else {
+                    ord
+                }
+            }
+        });
+        self.features = keyed.into_iter().map(|(_, f)| f).collect();
+    }
+    /// Generate label points of polygons
+    pub fn set_label(&mut self, label: &LayerLabel) {
+        self.label_layer = label
+            .layer
+            .as_ref()
+            .map(|name| Box::new(LayerBuilder::new(name, self.extent)));
+        self.label = Some(label.clone());
+    }
+    pub fn label(&self) -> Option<&LayerLabel> {
+        self.label.as_ref()
+    }
+    /// Add label point to this layer or the separate label layer
+    pub fn add_label_point(
+        &mut self,
+        fid: Option<u64>,
+        attributes: &Vec<FeatureAttr>,
+        point: screen::Point,
+    ) {
+        let feature = ScreenPointFeature {
+            fid: fid,
+            attributes: attributes.clone(),
+            point: point,
+        };
+        match self.label_layer {
+            Some(ref mut label_layer) => label_layer.push_point_feature(feature),
+            None => self.push_point_feature(feature),
+        }
+    }
+    /// Remove separate label layer for adding it to the tile
+    pub fn take_label_layer(&mut self) -> Option<LayerBuilder> {
+        self.label_layer.take().map(|l| *l)
+    }
+    fn push_point_feature(&mut self, feature: ScreenPointFeature) {
+        let mut mvt_feature = vector_tile::Tile_Feature::new();
+        if let Some(fid) = feature.fid {
+            mvt_feature.set_id(fid);
+        }
+        for attr in &feature.attributes {
+            self.add_feature_attribute(&mut mvt_feature, &attr.key, &attr.value);
+        }
+        mvt_feature.set_field_type(vector_tile::Tile_GeomType::POINT);
+        mvt_feature.set_geometry(feature.point.encode().vec());
+        self.push_feature(mvt_feature);
+    }
+    /// Build protobuf layer
+    pub fn into_layer(mut self) -> vector_tile::Tile_Layer {
+        if let Some(clusterer) = self.cluster.take() {
+            for feature in clusterer.clusters() {
+                self.push_point_feature(feature);
+            }
+        }
+        if let Some(thinner) = self.thinner.take() {
+            for feature in thinner.points() {
+                self.push_point_feature(feature);
+            }
+        }
+        if let Some(merger) = self.merger.take() {
+            self.features.extend(merger.features());
+        }
+        self.sort_features();
+        let mut mvt_layer = vector_tile::Tile_Layer::new();
+        mvt_layer.set_version(2);
+        mvt_layer.set_name(self.name);
+        mvt_layer.set_extent(self.extent);
+        mvt_layer.set_keys(RepeatedField::from_vec(self.keys));
+        mvt_layer.set_values(RepeatedField::from_vec(self.values));
+        mvt_layer.set_features(RepeatedField::from_vec(self.features));
+        mvt_layer
+    }
+}
@@ -0,0 +1,211 @@
+//
+// Copyright (c) Pirmin Kalberer. All rights reserved.
+// Licensed under the MIT License. See LICENSE file in the project root for full license information.
+//
+
+use core::feature::FeatureAttrValType;
+use mvt::layer_builder::LayerBuilder;
+use mvt::vector_tile;
+use protobuf::Message;
+
+#[test]
+fn test_key_value_interning() {
+    let mut builder = LayerBuilder::new("pois", 4096);
+    for i in 0..1000 {
+        let mut mvt_feature = vector_tile::Tile_Feature::new();
+        builder.add_feature_attribute(
+            &mut mvt_feature,
+            "name",
+            &FeatureAttrValType::String(format!("poi {}", i % 10)),
+        );
+        builder.add_feature_attribute(&mut mvt_feature, "rank", &FeatureAttrValType::Int(1));
+        builder.push_feature(mvt_feature);
+    }
+    assert_eq!(builder.feature_count(), 1000);
+
+    let mvt_layer = builder.into_layer();
+    assert_eq!(mvt_layer.get_name(), "pois");
+    assert_eq!(mvt_layer.get_version(), 2);
+    assert_eq!(mvt_layer.get_extent(), 4096);
+    assert_eq!(
+        mvt_layer.get_keys(),
+        &["name".to_string(), "rank".to_string()]
+    );
+    assert_eq!(mvt_layer.get_values().len(), 11);
+    assert_eq!(mvt_layer.get_values()[0].get_string_value(), "poi 0");
+    assert_eq!(mvt_layer.get_values()[1].get_int_value(), 1);
+    assert_eq!(mvt_layer.get_features()[0].get_tags(), &[0, 0, 1, 1]);
+    assert_eq!(mvt_layer.get_features()[12].get_tags(), &[0, 3, 1, 1]);
+}
+
+#[test]
+fn test_value_types() {
+    let mut builder = LayerBuilder::new("values", 4096);
+    let mut mvt_feature = vector_tile::Tile_Feature::new();
+    // Same numeric value with different types results in different entries
+    for value in vec![
+        FeatureAttrValType::Int(1),
+        FeatureAttrValType::UInt(1),
+        FeatureAttrValType::SInt(1),
+        FeatureAttrValType::Double(1.0),
+        FeatureAttrValType::Float(1.0),
+        FeatureAttrValType::Bool(true),
+        FeatureAttrValType::String("1".to_string()),
+        FeatureAttrValType::Double(1.0),
+    ] {
+        builder.add_feature_attribute(&mut mvt_feature, "v", &value);
+    }
+    builder.push_feature(mvt_feature);
+    let mvt_layer = builder.into_layer();
+    assert_eq!(mvt_layer.get_keys().len(), 1);
+    assert_eq!(mvt_layer.get_values().len(), 7);
+    assert_eq!(
+        mvt_layer.get_features()[0].get_tags(),
+        &[0, 0, 0, 1, 0, 2, 0, 3, 0, 4, 0, 5, 0, 6, 0, 3]
+    );
+    assert_eq!(mvt_layer.get_values()[4].get_float_value(), 1.0);
+}
+
+#[test]
+fn test_feature_order() {
+    use core::layer::FeatureOrder;
+
+    let build = |order: FeatureOrder| {
+        let mut builder = LayerBuilder::new("roads", 4096);
+        builder.set_order(&order);
+        for (id, class, rank) in vec![
+            (1, "residential", 3),
+            (2, "motorway", 1),
+            (3, "primary", 2),
+            (4, "track", 5),
+        ] {
+            let mut mvt_feature = vector_tile::Tile_Feature::new();
+            mvt_feature.set_id(id);
+            builder.add_feature_attribute(
+                &mut mvt_feature,
+                "class",
+                &FeatureAttrValType::String(class.to_string()),
+            );
+            builder.add_feature_attribute(&mut mvt_feature, "rank", &FeatureAttrValType::Int(rank));
+            builder.push_feature(mvt_feature);
+        }
+        let mut mvt_feature = vector_tile::Tile_Feature::new();
+        mvt_feature.set_id(5);
+        builder.push_feature(mvt_feature);
+        builder
+            .into_layer()
+            .get_features()
+            .iter()
+            .map(|f| f.get_id())
+            .collect::<Vec<_>>()
+    };
+
+    let order = FeatureOrder {
+        field: "rank".to_string(),
+        descending: true,
+        values: vec![],
+    };
+    assert_eq!(build(order), vec![4, 1, 3, 2, 5]);
+
+    let order = FeatureOrder {
+        field: "class".to_string(),
+        descending: false,
+        values: vec![],
+    };
+    assert_eq!(build(order), vec![2, 3, 1, 4, 5]);
+
+    // z-order: motorway on top
+    let order = FeatureOrder {
+        field: "class".to_string(),
+        descending: false,
+        values: vec![
+            "residential".to_string(),
+            "primary".to_string(),
+            "motorway".to_string(),
+        ],
+    };
+    assert_eq!(build(order), vec![1, 3, 2, 4, 5]);
+}
+
+#[test]
+fn test_feature_ids() {
+    use core::feature::FeatureAttr;
+    use core::layer::FidMode;
+
+    let attrs = |uuid: &str| {
+        vec![
+            FeatureAttr {
+                key: "uuid".to_string(),
+                value: FeatureAttrValType::String(uuid.to_string()),
+            },
+            FeatureAttr {
+                key: "name".to_string(),
+                value: FeatureAttrValType::String("Bern".to_string()),
+            },
+        ]
+    };
+    let uuid1 = "b7f0e3c2-5d1a-4c8e-9f3b-2a6d8e4c1f00";
+    let uuid2 = "0c2e9a71-3b4f-4d6e-8a5c-7f1b2d3e4a99";
+    let mut builder = LayerBuilder::new("places", 4096);
+    assert_eq!(builder.feature_id(Some(3), &attrs(uuid1)), Some(3));
+
+    let field = "uuid".to_string();
+    builder.set_fid_mode(FidMode::Hash, Some(&field));
+    let id1 = builder.feature_id(None, &attrs(uuid1)).unwrap();
+    assert_eq!(builder.feature_id(None, &attrs(uuid1)), Some(id1));
+    assert!(builder.feature_id(None, &attrs(uuid2)) != Some(id1));
+    assert!(id1 < 1 << 53);
+    let intattr = vec![FeatureAttr {
+        key: "uuid".to_string(),
+        value: FeatureAttrValType::Int(42),
+    }];
+    assert_eq!(builder.feature_id(None, &intattr), Some(42));
+    assert_eq!(builder.feature_id(None, &vec![]), None);
+
+    builder.set_fid_mode(FidMode::Attributes, None);
+    let id1 = builder.feature_id(None, &attrs(uuid1)).unwrap();
+    assert_eq!(builder.feature_id(Some(3), &attrs(uuid1)), Some(id1));
+    assert!(builder.feature_id(None, &attrs(uuid2)) != Some(id1));
+
+    builder.set_fid_mode(FidMode::Sequence, None);
+    assert_eq!(builder.feature_id(Some(3), &attrs(uuid1)), Some(1));
+    assert_eq!(builder.feature_id(Some(3), &attrs(uuid1)), Some(2));
+}
+
+#[test]
+fn test_min_size() {
+    use core::screen;
+
+    let line = |len: i32| {
+        screen::Geometry::LineString(screen::LineString {
+            points: vec![screen::Point { x: 0, y: 0 }, screen::Point { x: len, y: 0 }],
+        })
+    };
+    let square = |size: i32| {
+        screen::Geometry::Polygon(screen::Polygon {
+            rings: vec![screen::LineString {
+                points: vec![
+                    screen::Point { x: 0, y: 0 },
+                    screen::Point { x: size, y: 0 },
+                    screen::Point { x: size, y: size },
+                    screen::Point { x: 0, y: size },
+                    screen::Point { x: 0, y: 0 },
+                ],
+            }],
+        })
+    };
+    let mut builder = LayerBuilder::new("landuse", 4096);
+    assert!(!builder.has_min_size());
+    // 16 screen units per pixel
+    builder.set_min_size(Some(4.0), Some(2.0), 16.0);
+    assert!(builder.has_min_size());
+    assert!(builder.is_too_small(&square(31)));
+    assert!(!builder.is_too_small(&square(32)));
+    assert!(builder.is_too_small(&line(31)));
+    assert!(!builder.is_too_small(&line(32)));
+    assert!(!builder.is_too_small(&screen::Geometry::Point(screen::Point { x: 0, y: 0 })));
+
+    // Only polygons filtered
+    builder.set_min_size(Some(4.0), None, 16.0);
+    assert!(!builder.is_too_small(&line(1)));
+}
@@ -0,0 +1,282 @@
+//
+// Copyright (c) Pirmin Kalberer. All rights reserved.
+// Licensed under the MIT License. See LICENSE file in the project root for full license information.
+//
+
+//! Merging of lines and polygons with equal attributes
+
+use core::screen;
+use core::screen::ring_area;
+use mvt::geom_encoder::EncodableGeom;
+use mvt::vector_tile;
+use std::collections::HashMap;
+
+/// Join lines sharing end points
+pub fn merge_lines(lines: Vec<screen::LineString>) -> Vec<screen::LineString> {
+    let lines: Vec<Vec<screen::Point>> = lines
+        .into_iter()
+        .map(|l| l.points)
+        .filter(|pts| pts.len() > 1)
+        .collect();
+    // End points -> line indices
+    let mut endpoints: HashMap<screen::Point, Vec<usize>> = HashMap::new();
+    for (i, pts) in lines.iter().enumerate() {
+        endpoints.entry(pts[0]).or_insert(Vec::new()).push(i);
+        endpoints
+            .entry(pts[pts.len() - 1])
+            .or_insert(Vec::new())
+            .push(i);
+    }
+    let mut used = vec![false; lines.len()];
+    let find_next = |pt: &screen::Point, used: &Vec<bool>| -> Option<usize> {
+        endpoints
+            .get(pt)
+            .and_then(|indices| indices.iter().find(|&&j| !used[j]).cloned())
+    };
+    let mut merged = Vec::new();
+    for i in 0..lines.len() {
+        if used[i] {
+            continue;
+        }
+        used[i] = true;
+        let mut pts = lines[i].clone();
+        // Extend at end
+        while let Some(j) = find_next(&pts[pts.len() - 1], &used) {
+            used[j] = true;
+            if lines[j][0] == pts[pts.len() - 1] {
+                pts.extend_from_slice(&lines[j][1..]);
+            } else {
+                pts.extend(lines[j].iter().rev().skip(1).cloned());
+            }
+        }
+        // Extend at start
+        while let Some(j) = find_next(&pts[0], &used) {
+            used[j] = true;
+            let mut head = lines[j].clone();
+            if head[0] == pts[0] {
+                head.reverse();
+            }
+            head.pop();
+            head.extend_from_slice(&pts);
+            pts = head;
+        }
+        merged.push(screen::LineString { points: pts });
+    }
+    merged
+}
+
+fn oriented_ring(ring: &screen::LineString, exterior: bool) -> Vec<screen::Point> {
+    let mut pts = ring.points.clone();
+    if (ring_area(ring) > 0.0) != exterior {
+        pts.reverse();
+    }
+    pts
+}
+
+fn ring_contains(ring: &screen::LineString, pt: &screen::Point) -> bool {
+    let pts = &ring.points;
+    let mut inside = false;
+    let mut j = pts.len() - 1;
+    for i in 0..pts.len() {
+        let (pi, pj) = (&pts[i], &pts[j]);
+        if (pi.y > pt.y) != (pj.y > pt.y)
+            && (pt.x as f64)
+                < (pj.x - pi.x) as f64 * (pt.y - pi.y) as f64 / (pj.y - pi.y) as f64 + pi.x as f64
+        {
+            inside = !inside;
+        }
+        j = i;
+    }
+    inside
+}
+
+/// Union of polygons sharing common edges.
+///
+/// Edges shared by two polygons are removed and the remaining edges are
+/// joined into new rings. Overlapping polygons are not dissolved.
+pub fn merge_polygons(polygons: Vec<screen::Polygon>) -> Vec<screen::Polygon> {
+    // Collect directed edges with exterior rings oriented positively.
+    // Shared edges of adjacent polygons have opposite directions and cancel out.
+    let mut edges: Vec<(screen::Point, screen::Point)> = Vec::new();
+    let mut alive: Vec<bool> = Vec::new();
+    let mut edge_index: HashMap<(screen::Point, screen::Point), Vec<usize>> = HashMap::new();
+    for polygon in &polygons {
+        for (ringno, ring) in polygon.rings.iter().enumerate() {
+            if ring.points.len() < 4 {
+                continue;
+            }
+            let pts = oriented_ring(ring, ringno == 0);
+            for w in pts.windows(2) {
+                let (a, b) = (w[0], w[1]);
+                if a == b {
+                    continue;
+                }
+                let opposite = edge_index.get_mut(&(b, a)).and_then(|v| v.pop());
+                match opposite {
+                    Some(j) => alive[j] = false,
+                    None => {
+                        edge_index
+                            .entry((a, b))
+                            .or_insert(Vec::new())
+                            .push(edges.len());
+                        edges.push((a, b));
+                        alive.push(true);
+                    }
+                }
+            }
+        }
+    }
+
+    // Join remaining edges into rings
+    let mut outgoing: HashMap<screen::Point, Vec<usize>> = HashMap::new();
+    for (i, edge) in edges.iter().enumerate() {
+        if alive[i] {
+            outgoing.entry(edge.0).or_insert(Vec::new()).push(i);
+        }
+    }
+    let mut used = vec![false; edges.len()];
+    let mut exteriors = Vec::new();
+    let mut holes = Vec::new();
+    for i in 0..edges.len() {
+        if !alive[i] || used[i] {
+            continue;
+        }
+        let start = edges[i].0;
+        let mut pts = vec![start];
+        let mut cur = i;
+        let closed = loop {
+            used[cur] = true;
+            let pt = edges[cur].1;
+            pts.push(pt);
+            if pt == start {
+                break true;
+            }
+            let next = outgoing
+                .get(&pt)
+                .and_then(|indices| indices.iter().find(|&&j| !used[j]).cloned());
+            match next {
+                Some(j) => cur = j,
+                None => break false,
+            }
+        };
+        if !closed || pts.len() < 4 {
+            continue;
+        }
+        let ring = screen::LineString { points: pts };
+        if ring_area(&ring) > 0.0 {
+            exteriors.push(ring);
+        } else {
+            holes.push(ring);
+        }
+    }
+
+    let mut merged: Vec<screen::Polygon> = exteriors
+        .into_iter()
+        .map(|ring| screen::Polygon { rings: vec![ring] })
+        .collect();
+    let areas: Vec<f64> = merged.iter().map(|p| ring_area(&p.rings[0])).collect();
+    for hole in holes {
+        // Assign hole to the smallest exterior containing it (e.g. an island within a lake)
+        let idx = (0..merged.len())
+            .filter(|&i| ring_contains(&merged[i].rings[0], &hole.points[0]))
+            .min_by(|&i, &j| areas[i].partial_cmp(&areas[j]).unwrap());
+        match idx {
+            Some(idx) => merged[idx].rings.push(hole),
+            None => warn!("Dropping hole outside of merged polygons"),
+        }
+    }
+    merged
+}
+
+/// Features with equal tags collected for merging
+struct MergeGroup {
+    feature: vector_tile::Tile_Feature,
+    count: usize,
+    lines: Vec<screen::LineString>,
+    polygons: Vec<screen::Polygon>,
+}
+
+/// Collects lines and polygons grouped by their attributes
+pub struct FeatureMerger {
+    groups: Vec<MergeGroup>,
+    /// (is polygon, tags) -> group index
+    group_index: HashMap<(bool, Vec<u32>), usize>,
+}
+
+impl FeatureMerger {
+    pub fn new() -> FeatureMerger {
+        FeatureMerger {
+            groups: Vec::new(),
+            group_index: HashMap::new(),
+        }
+    }
+    /// Add feature with tags set. Returns the geometry if it can't be merged.
+    pub fn add(
+        &mut self,
+        mvt_feature: vector_tile::Tile_Feature,
+        geometry: screen::Geometry,
+    ) -> Option<(vector_tile::Tile_Feature, screen::Geometry)> {
+        let is_polygon = match geometry {
+            screen::Geometry::LineString(_) | screen::Geometry::MultiLineString(_) => false,
+            screen::Geometry::Polygon(_) | screen::Geometry::MultiPolygon(_) => true,
+            _ => return Some((mvt_feature, geometry)),
+        };
+        let key = (is_polygon, mvt_feature.get_tags().to_vec());
+        let idx = match self.group_index.get(&key) {
+            Some(idx) => *idx,
+            None => {
+                self.groups.push(MergeGroup {
+                    feature: mvt_feature,
+                    count: 0,
+                    lines: Vec::new(),
+                    polygons: Vec::new(),
+                });
+                self.groups.len() - 1
+            }
+        };
+        self.group_index.insert(key, idx);
+        let group = &mut self.groups[idx];
+        group.count += 1;
+        match geometry {
+            screen::Geometry::LineString(g) => group.lines.push(g),
+            screen::Geometry::MultiLineString(g) => group.lines.extend(g.lines),
+            screen::Geometry::Polygon(g) => group.polygons.push(g),
+            screen::Geometry::MultiPolygon(g) => group.polygons.extend(g.polygons),
+            _ => {}
+        }
+        None
+    }
+    /// Merged features
+    pub fn features(self) -> Vec<vector_tile::Tile_Feature> {
+        let mut features = Vec::new();
+        for group in self.groups {
+            let mut mvt_feature = group.feature;
+            if group.count > 1 {
+                mvt_feature.clear_id();
+            }
+            let geometry = if group.polygons.is_empty() {
+                mvt_feature.set_field_type(vector_tile::Tile_GeomType::LINESTRING);
+                let mut lines = merge_lines(group.lines);
+                if lines.len() == 1 {
+                    screen::Geometry::LineString(lines.remove(0))
+                } else {
+                    screen::Geometry::MultiLineString(screen::MultiLineString { lines: lines })
+                }
+            } else {
+                mvt_feature.set_field_type(vector_tile::Tile_GeomType::POLYGON);
+                let mut polygons = merge_polygons(group.polygons);
+                if polygons.len() == 1 {
+                    screen::Geometry::Polygon(polygons.remove(0))
+                } else {
+                    screen::Geometry::MultiPolygon(screen::MultiPolygon { polygons: polygons })
+                }
+            };
+            if geometry.num_points() == 0 {
+                continue;
+            }
+            mvt_feature.set_geometry(geometry.encode().vec());
+            features.push(mvt_feature);
+        }
+        features
+    }
+}
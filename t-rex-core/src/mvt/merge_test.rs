@@ -0,0 +1,199 @@
+//
+// Copyright (c) Pirmin Kalberer. All rights reserved.
+// Licensed under the MIT License. See LICENSE file in the project root for full license information.
+//
+
+use core::feature::{Feature, FeatureAttr, FeatureAttrValType};
+use core::geom::{GeometryType, LineString, Point, Polygon};
+use core::grid::Extent;
+use core::layer::Layer;
+use core::screen;
+use mvt::geom_decoder::decode_geom;
+use mvt::merge::{merge_lines, merge_polygons};
+use mvt::tile::Tile;
+
+fn line(coords: &[(i32, i32)]) -> screen::LineString {
+    screen::LineString {
+        points: coords
+            .iter()
+            .map(|&(x, y)| screen::Point { x: x, y: y })
+            .collect(),
+    }
+}
+
+fn square(x: i32, y: i32, size: i32) -> screen::Polygon {
+    screen::Polygon {
+        rings: vec![line(&[
+            (x, y),
+            (x + size, y),
+            (x + size, y + size),
+            (x, y + size),
+            (x, y),
+        ])],
+    }
+}
+
+#[test]
+fn test_merge_lines() {
+    let lines = vec![
+        line(&[(10, 10), (20, 10)]),
+        line(&[(30, 10), (20, 10)]),
+        line(&[(0, 0), (10, 10)]),
+        line(&[(100, 100), (110, 110)]),
+    ];
+    let merged = merge_lines(lines);
+    assert_eq!(merged.len(), 2);
+    assert_eq!(merged[0], line(&[(0, 0), (10, 10), (20, 10), (30, 10)]));
+    assert_eq!(merged[1], line(&[(100, 100), (110, 110)]));
+}
+
+#[test]
+fn test_merge_polygons() {
+    // Two adjacent squares and a separate one
+    let merged = merge_polygons(vec![square(0, 0, 10), square(10, 0, 10), square(50, 50, 5)]);
+    assert_eq!(merged.len(), 2);
+    assert_eq!(merged[0].rings.len(), 1);
+    assert_eq!(
+        merged[0].rings[0],
+        line(&[
+            (0, 0),
+            (10, 0),
+            (20, 0),
+            (20, 10),
+            (10, 10),
+            (0, 10),
+            (0, 0),
+        ])
+    );
+    assert_eq!(merged[1], square(50, 50, 5));
+
+    // Ring of 8 squares around a hole
+    let mut squares = Vec::new();
+    for &(x, y) in &[
+        (0, 0),
+        (10, 0),
+        (20, 0),
+        (0, 10),
+        (20, 10),
+        (0, 20),
+        (10, 20),
+        (20, 20),
+    ] {
+        squares.push(square(x, y, 10));
+    }
+    let merged = merge_polygons(squares);
+    assert_eq!(merged.len(), 1);
+    assert_eq!(merged[0].rings.len(), 2);
+    assert_eq!(merged[0].rings[1].points.len(), 5);
+
+    // Island with a pond inside a lake surrounded by land
+    let mut polygons = Vec::new();
+    for &(x, y) in &[
+        (0, 0),
+        (30, 0),
+        (60, 0),
+        (0, 30),
+        (60, 30),
+        (0, 60),
+        (30, 60),
+        (60, 60),
+    ] {
+        polygons.push(square(x, y, 30));
+    }
+    let mut island = square(35, 35, 20);
+    island.rings.push(square(40, 40, 10).rings[0].clone());
+    polygons.push(island);
+    let merged = merge_polygons(polygons);
+    assert_eq!(merged.len(), 2);
+    assert_eq!(merged[0].rings.len(), 2);
+    assert!(merged[0].rings[1].points.contains(&screen::Point { x: 30, y: 30 }));
+    assert_eq!(merged[1].rings.len(), 2);
+    assert!(merged[1].rings[1].points.contains(&screen::Point { x: 40, y: 40 }));
+}
+
+/// Feature with a line or polygon geometry (GeometryType::clone supports only points)
+struct TestFeature {
+    fid: u64,
+    class: &'static str,
+    coords: Vec<(f64, f64)>,
+    polygon: bool,
+}
+
+impl Feature for TestFeature {
+    fn fid(&self) -> Option<u64> {
+        Some(self.fid)
+    }
+    fn attributes(&self) -> Vec<FeatureAttr> {
+        vec![FeatureAttr {
+            key: "class".to_string(),
+            value: FeatureAttrValType::String(self.class.to_string()),
+        }]
+    }
+    fn geometry(&self) -> Result<GeometryType, String> {
+        let line = LineString {
+            points: self
+                .coords
+                .iter()
+                .map(|&(x, y)| Point::new(x, y, None))
+                .collect(),
+            srid: None,
+        };
+        if self.polygon {
+            Ok(GeometryType::Polygon(Polygon {
+                rings: vec![line],
+                srid: None,
+            }))
+        } else {
+            Ok(GeometryType::LineString(line))
+        }
+    }
+}
+
+#[test]
+fn test_merge_features() {
+    let extent = Extent {
+        minx: 0.0,
+        miny: 0.0,
+        maxx: 4096.0,
+        maxy: 4096.0,
+    };
+    let mut tile = Tile::new(&extent, false);
+    let mut layer = Layer::new("roads");
+    layer.merge = true;
+    let mut mvt_layer = tile.new_layer(&layer);
+    for (i, &(x, class)) in [(0.0, "primary"), (100.0, "primary"), (200.0, "secondary")]
+        .iter()
+        .enumerate()
+    {
+        let feature = TestFeature {
+            fid: i as u64,
+            class: class,
+            coords: vec![(x, 10.0), (x + 100.0, 10.0)],
+            polygon: false,
+        };
+        tile.add_feature(&mut mvt_layer, &feature);
+    }
+    let feature = TestFeature {
+        fid: 9,
+        class: "primary",
+        coords: vec![(0.0, 0.0), (10.0, 0.0), (10.0, 10.0), (0.0, 0.0)],
+        polygon: true,
+    };
+    tile.add_feature(&mut mvt_layer, &feature);
+    tile.add_layer(mvt_layer);
+
+    let mvt_layer = &tile.mvt_tile.get_layers()[0];
+    let features = mvt_layer.get_features();
+    assert_eq!(features.len(), 3);
+    assert!(!features[0].has_id());
+    assert_eq!(
+        decode_geom(features[0].get_field_type(), features[0].get_geometry()),
+        Ok(screen::Geometry::LineString(line(&[
+            (0, 10),
+            (100, 10),
+            (200, 10),
+        ])))
+    );
+    assert_eq!(features[1].get_id(), 2);
+    assert_eq!(features[2].get_id(), 9);
+}
@@ -3,10 +3,32 @@
 // Licensed under the MIT License. See LICENSE file in the project root for full license information.
 //
 
+pub mod cluster;
+#[cfg(test)]
+mod cluster_test;
+pub mod geom_decoder;
 pub mod geom_encoder;
 #[cfg(test)]
 mod geom_encoder_test;
+pub mod layer_builder;
+#[cfg(test)]
+mod layer_builder_test;
+pub mod merge;
+#[cfg(test)]
+mod merge_test;
+pub mod overzoom;
+#[cfg(test)]
+mod overzoom_test;
+pub mod polylabel;
+#[cfg(test)]
+mod polylabel_test;
+pub mod thin;
+#[cfg(test)]
+mod thin_test;
 pub mod tile;
+pub mod tile_decoder;
+#[cfg(test)]
+mod tile_decoder_test;
 #[cfg(test)]
 mod tile_test;
 #[cfg_attr(rustfmt, rustfmt_skip)]
@@ -0,0 +1,361 @@
+//
+// Copyright (c) Pirmin Kalberer. All rights reserved.
+// Licensed under the MIT License. See LICENSE file in the project root for full license information.
+//
+
+//! Tiles above the maximal zoom level derived from parent tiles
+
+use core::screen;
+use mvt::geom_encoder::EncodableGeom;
+use mvt::layer_builder::LayerBuilder;
+use mvt::tile_decoder::decode_layer;
+use mvt::vector_tile;
+use protobuf::Message;
+
+type Coord = (f64, f64);
+
+/// Maximal zoom level difference between a child tile and its parent tile
+pub const MAX_ZOOM_DIFF: u8 = 31;
+
+/// Clipping rectangle in screen coordinates
+#[derive(Clone, Copy, Debug)]
+pub struct ClipRect {
+    pub minx: f64,
+    pub miny: f64,
+    pub maxx: f64,
+    pub maxy: f64,
+}
+
+impl ClipRect {
+    fn contains(&self, c: &Coord) -> bool {
+        c.0 >= self.minx && c.0 <= self.maxx && c.1 >= self.miny && c.1 <= self.maxy
+    }
+}
+
+/// Position of a child tile within its parent tile
+#[derive(Clone, Copy, Debug)]
+pub struct ChildTile {
+    /// Zoom level difference
+    pub dz: u8,
+    /// Column from the left edge of the parent
+    pub x: u32,
+    /// Row from the top edge of the parent
+    pub y: u32,
+}
+
+impl ChildTile {
+    /// Child position from tile indices with y origin at the bottom (TMS)
+    pub fn from_tms(xtile: u32, ytile: u32, dz: u8) -> Result<ChildTile, String> {
+        if dz > MAX_ZOOM_DIFF {
+            return Err(format!(
+                "Zoom level difference {} to parent tile exceeds {}",
+                dz, MAX_ZOOM_DIFF
+            ));
+        }
+        let n = 1u32 << dz;
+        Ok(ChildTile {
+            dz: dz,
+            x: xtile % n,
+            y: n - 1 - ytile % n,
+        })
+    }
+}
+
+/// Liang-Barsky clipping of a segment
+fn clip_segment(a: Coord, b: Coord, rect: &ClipRect) -> Option<(Coord, Coord)> {
+    let (dx, dy) = (b.0 - a.0, b.1 - a.1);
+    let mut t0 = 0.0;
+    let mut t1 = 1.0;
+    for &(p, q) in &[
+        (-dx, a.0 - rect.minx),
+        (dx, rect.maxx - a.0),
+        (-dy, a.1 - rect.miny),
+        (dy, rect.maxy - a.1),
+    ] {
+        if p == 0.0 {
+            if q < 0.0 {
+                return None;
+            }
+        } else {
+            let r = q / p;
+            if p < 0.0 {
+                if r > t1 {
+                    return None;
+                }
+                if r > t0 {
+                    t0 = r;
+                }
+            } else {
+                if r < t0 {
+                    return None;
+                }
+                if r < t1 {
+                    t1 = r;
+                }
+            }
+        }
+    }
+    Some((
+        (a.0 + t0 * dx, a.1 + t0 * dy),
+        (a.0 + t1 * dx, a.1 + t1 * dy),
+    ))
+}
+
+/// Clip line to rectangle. Returns the parts inside.
+pub fn clip_line(points: &[Coord], rect: &ClipRect) -> Vec<Vec<Coord>> {
+    let mut parts = Vec::new();
+    let mut current: Vec<Coord> = Vec::new();
+    for w in points.windows(2) {
+        match clip_segment(w[0], w[1], rect) {
+            Some((a, b)) => {
+                if current.last() != Some(&a) {
+                    if current.len() > 1 {
+                        parts.push(current);
+                    }
+                    current = vec![a];
+                }
+                current.push(b);
+                // Segment leaves the rectangle
+                if b != w[1] {
+                    parts.push(current);
+                    current = Vec::new();
+                }
+            }
+            None => {
+                if current.len() > 1 {
+                    parts.push(current);
+                }
+                current = Vec::new();
+            }
+        }
+    }
+    if current.len() > 1 {
+        parts.push(current);
+    }
+    parts
+}
+
+/// Sutherland-Hodgman clipping of a closed ring. The result is closed again.
+pub fn clip_ring(points: &[Coord], rect: &ClipRect) -> Vec<Coord> {
+    let mut ring: Vec<Coord> = points.to_vec();
+    if ring.len() > 1 && ring[0] == ring[ring.len() - 1] {
+        ring.pop();
+    }
+    // Edges as (inside test, intersection) per side
+    let sides: [(&Fn(&Coord) -> bool, &Fn(&Coord, &Coord) -> Coord); 4] = [
+        (&|c| c.0 >= rect.minx, &|a, b| {
+            (rect.minx, a.1 + (b.1 - a.1) * (rect.minx - a.0) / (b.0 - a.0))
+        }),
+        (&|c| c.0 <= rect.maxx, &|a, b| {
+            (rect.maxx, a.1 + (b.1 - a.1) * (rect.maxx - a.0) / (b.0 - a.0))
+        }),
+        (&|c| c.1 >= rect.miny, &|a, b| {
+            (a.0 + (b.0 - a.0) * (rect.miny - a.1) / (b.1 - a.1), rect.miny)
+        }),
+        (&|c| c.1 <= rect.maxy, &|a, b| {
+            (a.0 + (b.0 - a.0) * (rect.maxy - a.1) / (b.1 - a.1), rect.maxy)
+        }),
+    ];
+    for &(inside, intersect) in sides.iter() {
+        if ring.is_empty() {
+            break;
+        }
+        let input = ring;
+        ring = Vec::with_capacity(input.len());
+        let mut prev = input[input.len() - 1];
+        for &cur in &input {
+            if inside(&cur) {
+                if !inside(&prev) {
+                    ring.push(intersect(&prev, &cur));
+                }
+                ring.push(cur);
+            } else if inside(&prev) {
+                ring.push(intersect(&prev, &cur));
+            }
+            prev = cur;
+        }
+    }
+    if ring.len() < 3 {
+        return Vec::new();
+    }
+    let first = ring[0];
+    ring.push(first);
+    ring
+}
+
+/// Round coordinates and remove repeated points
+fn to_screen(coords: &[Coord]) -> Vec<screen::Point> {
+    let mut points: Vec<screen::Point> = Vec::with_capacity(coords.len());
+    for c in coords {
+        let p = screen::Point {
+            x: c.0.round() as i32,
+            y: c.1.round() as i32,
+        };
+        if points.last() != Some(&p) {
+            points.push(p);
+        }
+    }
+    points
+}
+
+fn clip_polygon(
+    polygon: &screen::Polygon,
+    transform: &Fn(&screen::Point) -> Coord,
+    rect: &ClipRect,
+) -> Option<screen::Polygon> {
+    let mut rings = Vec::new();
+    for (i, ring) in polygon.rings.iter().enumerate() {
+        let coords: Vec<Coord> = ring.points.iter().map(|p| transform(p)).collect();
+        let points = to_screen(&clip_ring(&coords, rect));
+        if points.len() < 4 {
+            if i == 0 {
+                return None;
+            }
+            continue;
+        }
+        rings.push(screen::LineString { points: points });
+    }
+    Some(screen::Polygon { rings: rings })
+}
+
+fn clip_lines(
+    line: &screen::LineString,
+    transform: &Fn(&screen::Point) -> Coord,
+    rect: &ClipRect,
+) -> Vec<screen::LineString> {
+    let coords: Vec<Coord> = line.points.iter().map(|p| transform(p)).collect();
+    clip_line(&coords, rect)
+        .iter()
+        .map(|part| to_screen(part))
+        .filter(|points| points.len() > 1)
+        .map(|points| screen::LineString { points: points })
+        .collect()
+}
+
+fn clip_points(
+    points: &[screen::Point],
+    transform: &Fn(&screen::Point) -> Coord,
+    rect: &ClipRect,
+) -> Vec<screen::Point> {
+    points
+        .iter()
+        .map(|p| transform(p))
+        .filter(|c| rect.contains(c))
+        .map(|c| screen::Point {
+            x: c.0.round() as i32,
+            y: c.1.round() as i32,
+        })
+        .collect()
+}
+
+/// Transform geometry and clip it to a rectangle
+pub fn clip_geometry(
+    geom: &screen::Geometry,
+    transform: &Fn(&screen::Point) -> Coord,
+    rect: &ClipRect,
+) -> Option<screen::Geometry> {
+    let points = |pts: &[screen::Point]| {
+        let mut points = clip_points(pts, transform, rect);
+        match points.len() {
+            0 => None,
+            1 => Some(screen::Geometry::Point(points.remove(0))),
+            _ => Some(screen::Geometry::MultiPoint(screen::MultiPoint {
+                points: points,
+            })),
+        }
+    };
+    let lines = |mut lines: Vec<screen::LineString>| match lines.len() {
+        0 => None,
+        1 => Some(screen::Geometry::LineString(lines.remove(0))),
+        _ => Some(screen::Geometry::MultiLineString(screen::MultiLineString {
+            lines: lines,
+        })),
+    };
+    let polygons = |mut polygons: Vec<screen::Polygon>| match polygons.len() {
+        0 => None,
+        1 => Some(screen::Geometry::Polygon(polygons.remove(0))),
+        _ => Some(screen::Geometry::MultiPolygon(screen::MultiPolygon {
+            polygons: polygons,
+        })),
+    };
+    match geom {
+        &screen::Geometry::Point(ref p) => points(&[*p]),
+        &screen::Geometry::MultiPoint(ref mp) => points(&mp.points),
+        &screen::Geometry::LineString(ref l) => lines(clip_lines(l, transform, rect)),
+        &screen::Geometry::MultiLineString(ref ml) => lines(
+            ml.lines
+                .iter()
+                .flat_map(|l| clip_lines(l, transform, rect))
+                .collect(),
+        ),
+        &screen::Geometry::Polygon(ref p) => {
+            polygons(clip_polygon(p, transform, rect).into_iter().collect())
+        }
+        &screen::Geometry::MultiPolygon(ref mp) => polygons(
+            mp.polygons
+                .iter()
+                .filter_map(|p| clip_polygon(p, transform, rect))
+                .collect(),
+        ),
+    }
+}
+
+fn geom_type(geom: &screen::Geometry) -> vector_tile::Tile_GeomType {
+    match geom {
+        &screen::Geometry::Point(_) | &screen::Geometry::MultiPoint(_) => {
+            vector_tile::Tile_GeomType::POINT
+        }
+        &screen::Geometry::LineString(_) | &screen::Geometry::MultiLineString(_) => {
+            vector_tile::Tile_GeomType::LINESTRING
+        }
+        &screen::Geometry::Polygon(_) | &screen::Geometry::MultiPolygon(_) => {
+            vector_tile::Tile_GeomType::POLYGON
+        }
+    }
+}
+
+/// Scale the features of a parent tile layer to a child tile.
+///
+/// Geometries are clipped to the child tile with `buffer` screen units.
+pub fn overzoom_layer(
+    parent: &vector_tile::Tile_Layer,
+    child: &ChildTile,
+    buffer: u32,
+) -> vector_tile::Tile_Layer {
+    let layer = decode_layer(parent);
+    let extent = layer.extent as f64;
+    let scale = (1u64 << child.dz) as f64;
+    let (ox, oy) = (child.x as f64 * extent, child.y as f64 * extent);
+    let transform = |p: &screen::Point| (p.x as f64 * scale - ox, p.y as f64 * scale - oy);
+    let rect = ClipRect {
+        minx: -(buffer as f64),
+        miny: -(buffer as f64),
+        maxx: extent + buffer as f64,
+        maxy: extent + buffer as f64,
+    };
+    let mut builder = LayerBuilder::new(&layer.name, layer.extent);
+    for feature in layer.features {
+        let geom = match feature.geometry {
+            Ok(ref geom) => clip_geometry(geom, &transform, &rect),
+            Err(ref e) => {
+                warn!("Layer '{}': {}", layer.name, e);
+                None
+            }
+        };
+        let geom = match geom {
+            Some(geom) => geom,
+            None => continue,
+        };
+        let mut mvt_feature = vector_tile::Tile_Feature::new();
+        if let Some(id) = feature.id {
+            mvt_feature.set_id(id);
+        }
+        for attr in &feature.attributes {
+            builder.add_feature_attribute(&mut mvt_feature, &attr.key, &attr.value);
+        }
+        mvt_feature.set_field_type(geom_type(&geom));
+        mvt_feature.set_geometry(geom.encode().vec());
+        builder.push_feature(mvt_feature);
+    }
+    builder.into_layer()
+}
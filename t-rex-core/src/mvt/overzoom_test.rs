@@ -0,0 +1,154 @@
+//
+// Copyright (c) Pirmin Kalberer. All rights reserved.
+// Licensed under the MIT License. See LICENSE file in the project root for full license information.
+//
+
+use core::feature::FeatureAttrValType;
+use core::screen;
+use mvt::geom_decoder::decode_geom;
+use mvt::geom_encoder::EncodableGeom;
+use mvt::layer_builder::LayerBuilder;
+use mvt::overzoom::{clip_line, clip_ring, overzoom_layer, ChildTile, ClipRect};
+use mvt::vector_tile;
+use protobuf::Message;
+
+const RECT: ClipRect = ClipRect {
+    minx: 0.0,
+    miny: 0.0,
+    maxx: 10.0,
+    maxy: 10.0,
+};
+
+#[test]
+fn test_clip_line() {
+    let parts = clip_line(&[(-5.0, 5.0), (5.0, 5.0), (5.0, 15.0)], &RECT);
+    assert_eq!(parts, vec![vec![(0.0, 5.0), (5.0, 5.0), (5.0, 10.0)]]);
+
+    // Line leaving and entering again
+    let parts = clip_line(&[(2.0, 2.0), (2.0, 20.0), (8.0, 20.0), (8.0, 2.0)], &RECT);
+    assert_eq!(
+        parts,
+        vec![vec![(2.0, 2.0), (2.0, 10.0)], vec![(8.0, 10.0), (8.0, 2.0)]]
+    );
+
+    assert!(clip_line(&[(20.0, 0.0), (20.0, 10.0)], &RECT).is_empty());
+}
+
+#[test]
+fn test_clip_ring() {
+    let ring = clip_ring(
+        &[(5.0, 5.0), (15.0, 5.0), (15.0, 15.0), (5.0, 15.0), (5.0, 5.0)],
+        &RECT,
+    );
+    assert_eq!(
+        ring,
+        vec![(5.0, 10.0), (5.0, 5.0), (10.0, 5.0), (10.0, 10.0), (5.0, 10.0)]
+    );
+
+    // Tile completely covered
+    let ring = clip_ring(
+        &[(-5.0, -5.0), (15.0, -5.0), (15.0, 15.0), (-5.0, 15.0), (-5.0, -5.0)],
+        &RECT,
+    );
+    assert_eq!(ring.len(), 5);
+
+    assert!(clip_ring(&[(20.0, 20.0), (30.0, 20.0), (30.0, 30.0), (20.0, 20.0)], &RECT).is_empty());
+}
+
+#[test]
+fn test_child_tile() {
+    // Top left grandchild in TMS numbering
+    let child = ChildTile::from_tms(8, 11, 2).unwrap();
+    assert_eq!((child.x, child.y), (0, 0));
+    let child = ChildTile::from_tms(9, 8, 2).unwrap();
+    assert_eq!((child.x, child.y), (1, 3));
+
+    // Zoom level differences beyond u32 tile indices
+    let child = ChildTile::from_tms(5, 0, 31).unwrap();
+    assert_eq!((child.x, child.y), (5, (1 << 31) - 1));
+    assert_eq!(
+        ChildTile::from_tms(5, 0, 32).err(),
+        Some("Zoom level difference 32 to parent tile exceeds 31".to_string())
+    );
+}
+
+fn feature_with_geom(
+    builder: &mut LayerBuilder,
+    id: u64,
+    geom: screen::Geometry,
+    geom_type: vector_tile::Tile_GeomType,
+) {
+    let mut mvt_feature = vector_tile::Tile_Feature::new();
+    mvt_feature.set_id(id);
+    builder.add_feature_attribute(
+        &mut mvt_feature,
+        "name",
+        &FeatureAttrValType::String(format!("f{}", id)),
+    );
+    mvt_feature.set_field_type(geom_type);
+    mvt_feature.set_geometry(geom.encode().vec());
+    builder.push_feature(mvt_feature);
+}
+
+#[test]
+fn test_overzoom_layer() {
+    let mut builder = LayerBuilder::new("places", 4096);
+    // Point in the top right quarter
+    feature_with_geom(
+        &mut builder,
+        1,
+        screen::Geometry::Point(screen::Point { x: 3000, y: 1000 }),
+        vector_tile::Tile_GeomType::POINT,
+    );
+    // Point in the bottom left quarter
+    feature_with_geom(
+        &mut builder,
+        2,
+        screen::Geometry::Point(screen::Point { x: 1000, y: 3000 }),
+        vector_tile::Tile_GeomType::POINT,
+    );
+    // Line crossing the tile horizontally
+    feature_with_geom(
+        &mut builder,
+        3,
+        screen::Geometry::LineString(screen::LineString {
+            points: vec![
+                screen::Point { x: 0, y: 1024 },
+                screen::Point { x: 4096, y: 1024 },
+            ],
+        }),
+        vector_tile::Tile_GeomType::LINESTRING,
+    );
+    let parent = builder.into_layer();
+
+    // Top right child
+    let layer = overzoom_layer(
+        &parent,
+        &ChildTile {
+            dz: 1,
+            x: 1,
+            y: 0,
+        },
+        0,
+    );
+    assert_eq!(layer.get_name(), "places");
+    assert_eq!(layer.get_extent(), 4096);
+    let features = layer.get_features();
+    assert_eq!(features.len(), 2);
+    assert_eq!(features[0].get_id(), 1);
+    assert_eq!(layer.get_keys(), &["name".to_string()]);
+    assert_eq!(
+        decode_geom(features[0].get_field_type(), features[0].get_geometry()),
+        Ok(screen::Geometry::Point(screen::Point { x: 1904, y: 2000 }))
+    );
+    assert_eq!(features[1].get_id(), 3);
+    assert_eq!(
+        decode_geom(features[1].get_field_type(), features[1].get_geometry()),
+        Ok(screen::Geometry::LineString(screen::LineString {
+            points: vec![
+                screen::Point { x: 0, y: 2048 },
+                screen::Point { x: 4096, y: 2048 },
+            ],
+        }))
+    );
+}
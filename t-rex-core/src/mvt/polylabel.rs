@@ -0,0 +1,203 @@
+//
+// Copyright (c) Pirmin Kalberer. All rights reserved.
+// Licensed under the MIT License. See LICENSE file in the project root for full license information.
+//
+
+//! Pole of inaccessibility of polygons for label placement
+//! Credits: polylabel by Mapbox (https://github.com/mapbox/polylabel)
+
+use core::screen;
+use core::screen::ring_area;
+use std::cmp::Ordering;
+use std::collections::BinaryHeap;
+use std::f64;
+
+/// Square cell with distance to polygon outline
+struct Cell {
+    x: f64,
+    y: f64,
+    /// Half cell size
+    h: f64,
+    /// Signed distance from cell center to polygon (negative outside)
+    d: f64,
+    /// Maximal distance to polygon within cell
+    max: f64,
+}
+
+impl Cell {
+    fn new(x: f64, y: f64, h: f64, polygon: &screen::Polygon) -> Cell {
+        let d = point_to_polygon_dist(x, y, polygon);
+        Cell {
+            x: x,
+            y: y,
+            h: h,
+            d: d,
+            max: d + h * f64::consts::SQRT_2,
+        }
+    }
+}
+
+impl PartialEq for Cell {
+    fn eq(&self, other: &Cell) -> bool {
+        self.max == other.max
+    }
+}
+
+impl Eq for Cell {}
+
+impl PartialOrd for Cell {
+    fn partial_cmp(&self, other: &Cell) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl Ord for Cell {
+    fn cmp(&self, other: &Cell) -> Ordering {
+        self.max.partial_cmp(&other.max).unwrap_or(Ordering::Equal)
+    }
+}
+
+fn seg_dist_sq(px: f64, py: f64, a: &screen::Point, b: &screen::Point) -> f64 {
+    let (mut x, mut y) = (a.x as f64, a.y as f64);
+    let mut dx = b.x as f64 - x;
+    let mut dy = b.y as f64 - y;
+    if dx != 0.0 || dy != 0.0 {
+        let t = ((px - x) * dx + (py - y) * dy) / (dx * dx + dy * dy);
+        if t > 1.0 {
+            x = b.x as f64;
+            y = b.y as f64;
+        } else if t > 0.0 {
+            x += dx * t;
+            y += dy * t;
+        }
+    }
+    dx = px - x;
+    dy = py - y;
+    dx * dx + dy * dy
+}
+
+/// Signed distance from point to polygon outline (negative if outside)
+fn point_to_polygon_dist(x: f64, y: f64, polygon: &screen::Polygon) -> f64 {
+    let mut inside = false;
+    let mut min_dist_sq = f64::INFINITY;
+    for ring in &polygon.rings {
+        let pts = &ring.points;
+        if pts.is_empty() {
+            continue;
+        }
+        let mut j = pts.len() - 1;
+        for i in 0..pts.len() {
+            let (a, b) = (&pts[i], &pts[j]);
+            let (ax, ay, bx, by) = (a.x as f64, a.y as f64, b.x as f64, b.y as f64);
+            if (ay > y) != (by > y) && x < (bx - ax) * (y - ay) / (by - ay) + ax {
+                inside = !inside;
+            }
+            min_dist_sq = min_dist_sq.min(seg_dist_sq(x, y, a, b));
+            j = i;
+        }
+    }
+    let dist = min_dist_sq.sqrt();
+    if inside {
+        dist
+    } else {
+        -dist
+    }
+}
+
+/// Cell at polygon centroid
+fn centroid_cell(polygon: &screen::Polygon) -> Cell {
+    let pts = &polygon.rings[0].points;
+    let mut area = 0.0;
+    let (mut x, mut y) = (0.0, 0.0);
+    let mut j = pts.len() - 1;
+    for i in 0..pts.len() {
+        let (a, b) = (&pts[i], &pts[j]);
+        let f = a.x as f64 * b.y as f64 - b.x as f64 * a.y as f64;
+        x += (a.x + b.x) as f64 * f;
+        y += (a.y + b.y) as f64 * f;
+        area += f * 3.0;
+        j = i;
+    }
+    if area == 0.0 {
+        Cell::new(pts[0].x as f64, pts[0].y as f64, 0.0, polygon)
+    } else {
+        Cell::new(x / area, y / area, 0.0, polygon)
+    }
+}
+
+/// Pole of inaccessibility with given precision in screen units
+pub fn polylabel(polygon: &screen::Polygon, precision: f64) -> Option<screen::Point> {
+    if polygon.rings.is_empty() || polygon.rings[0].points.is_empty() {
+        return None;
+    }
+    let exterior = &polygon.rings[0].points;
+    let minx = exterior.iter().map(|p| p.x).min().unwrap() as f64;
+    let miny = exterior.iter().map(|p| p.y).min().unwrap() as f64;
+    let maxx = exterior.iter().map(|p| p.x).max().unwrap() as f64;
+    let maxy = exterior.iter().map(|p| p.y).max().unwrap() as f64;
+    let width = maxx - minx;
+    let height = maxy - miny;
+    let cell_size = width.min(height);
+    if cell_size == 0.0 {
+        return Some(exterior[0]);
+    }
+    let precision = precision.max(f64::EPSILON);
+    let mut h = cell_size / 2.0;
+
+    // Cover polygon with initial cells
+    let mut queue = BinaryHeap::new();
+    let mut x = minx;
+    while x < maxx {
+        let mut y = miny;
+        while y < maxy {
+            queue.push(Cell::new(x + h, y + h, h, polygon));
+            y += cell_size;
+        }
+        x += cell_size;
+    }
+
+    let mut best = centroid_cell(polygon);
+    let bbox_cell = Cell::new(minx + width / 2.0, miny + height / 2.0, 0.0, polygon);
+    if bbox_cell.d > best.d {
+        best = bbox_cell;
+    }
+
+    while let Some(cell) = queue.pop() {
+        if cell.d > best.d {
+            best = Cell::new(cell.x, cell.y, 0.0, polygon);
+        }
+        // Skip cells which can't contain a better solution
+        if cell.max - best.d <= precision {
+            continue;
+        }
+        h = cell.h / 2.0;
+        queue.push(Cell::new(cell.x - h, cell.y - h, h, polygon));
+        queue.push(Cell::new(cell.x + h, cell.y - h, h, polygon));
+        queue.push(Cell::new(cell.x - h, cell.y + h, h, polygon));
+        queue.push(Cell::new(cell.x + h, cell.y + h, h, polygon));
+    }
+
+    Some(screen::Point {
+        x: best.x.round() as i32,
+        y: best.y.round() as i32,
+    })
+}
+
+/// Label point of the largest polygon of a geometry
+pub fn label_point(geom: &screen::Geometry, precision: f64) -> Option<screen::Point> {
+    match geom {
+        &screen::Geometry::Polygon(ref p) => polylabel(p, precision),
+        &screen::Geometry::MultiPolygon(ref mp) => mp
+            .polygons
+            .iter()
+            .filter(|p| !p.rings.is_empty())
+            .max_by(|a, b| {
+                ring_area(&a.rings[0])
+                    .abs()
+                    .partial_cmp(&ring_area(&b.rings[0]).abs())
+                    .unwrap_or(Ordering::Equal)
+            })
+            .and_then(|p| polylabel(p, precision)),
+        _ => None,
+    }
+}
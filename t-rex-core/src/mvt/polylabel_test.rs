@@ -0,0 +1,163 @@
+//
+// Copyright (c) Pirmin Kalberer. All rights reserved.
+// Licensed under the MIT License. See LICENSE file in the project root for full license information.
+//
+
+use core::feature::{Feature, FeatureAttr, FeatureAttrValType};
+use core::geom::{GeometryType, LineString, Point, Polygon};
+use core::grid::Extent;
+use core::layer::{Layer, LayerLabel};
+use core::screen;
+use mvt::geom_decoder::decode_geom;
+use mvt::polylabel::{label_point, polylabel};
+use mvt::tile::Tile;
+use mvt::vector_tile::Tile_GeomType;
+
+fn ring(coords: &[(i32, i32)]) -> screen::LineString {
+    screen::LineString {
+        points: coords
+            .iter()
+            .map(|&(x, y)| screen::Point { x: x, y: y })
+            .collect(),
+    }
+}
+
+#[test]
+fn test_polylabel() {
+    let square = screen::Polygon {
+        rings: vec![ring(&[(0, 0), (100, 0), (100, 100), (0, 100), (0, 0)])],
+    };
+    assert_eq!(
+        polylabel(&square, 1.0),
+        Some(screen::Point { x: 50, y: 50 })
+    );
+
+    // U-shape with centroid outside of the polygon
+    let ushape = screen::Polygon {
+        rings: vec![ring(&[
+            (0, 0),
+            (300, 0),
+            (300, 300),
+            (200, 300),
+            (200, 100),
+            (100, 100),
+            (100, 300),
+            (0, 300),
+            (0, 0),
+        ])],
+    };
+    let label = polylabel(&ushape, 1.0).unwrap();
+    assert!(label.y < 100);
+    assert!(label.x > 40 && label.x < 260);
+
+    // Degenerated polygon
+    let line = screen::Polygon {
+        rings: vec![ring(&[(0, 0), (100, 0), (0, 0)])],
+    };
+    assert_eq!(polylabel(&line, 1.0), Some(screen::Point { x: 0, y: 0 }));
+
+    // Largest polygon of multipolygon
+    let multipolygon = screen::Geometry::MultiPolygon(screen::MultiPolygon {
+        polygons: vec![
+            screen::Polygon {
+                rings: vec![ring(&[(0, 0), (10, 0), (10, 10), (0, 10), (0, 0)])],
+            },
+            screen::Polygon {
+                rings: vec![ring(&[
+                    (100, 100),
+                    (200, 100),
+                    (200, 200),
+                    (100, 200),
+                    (100, 100),
+                ])],
+            },
+        ],
+    });
+    assert_eq!(
+        label_point(&multipolygon, 1.0),
+        Some(screen::Point { x: 150, y: 150 })
+    );
+}
+
+struct Lake;
+
+impl Feature for Lake {
+    fn fid(&self) -> Option<u64> {
+        Some(7)
+    }
+    fn attributes(&self) -> Vec<FeatureAttr> {
+        vec![FeatureAttr {
+            key: "name".to_string(),
+            value: FeatureAttrValType::String("Zürichsee".to_string()),
+        }]
+    }
+    fn geometry(&self) -> Result<GeometryType, String> {
+        Ok(GeometryType::Polygon(Polygon {
+            rings: vec![LineString {
+                points: vec![
+                    Point::new(0.0, 0.0, None),
+                    Point::new(1000.0, 0.0, None),
+                    Point::new(1000.0, 1000.0, None),
+                    Point::new(0.0, 1000.0, None),
+                    Point::new(0.0, 0.0, None),
+                ],
+                srid: None,
+            }],
+            srid: None,
+        }))
+    }
+}
+
+#[test]
+fn test_label_layer() {
+    let extent = Extent {
+        minx: 0.0,
+        miny: 0.0,
+        maxx: 4096.0,
+        maxy: 4096.0,
+    };
+    let mut tile = Tile::new(&extent, false);
+    let mut layer = Layer::new("lakes");
+    layer.label_point = Some(LayerLabel {
+        replace: false,
+        layer: Some("lakes_label".to_string()),
+        precision: 1.0,
+    });
+    let mut mvt_layer = tile.new_layer(&layer);
+    tile.add_feature(&mut mvt_layer, &Lake);
+    tile.add_layer(mvt_layer);
+
+    let layers = tile.mvt_tile.get_layers();
+    assert_eq!(layers.len(), 2);
+    assert_eq!(layers[0].get_name(), "lakes");
+    assert_eq!(
+        layers[0].get_features()[0].get_field_type(),
+        Tile_GeomType::POLYGON
+    );
+    assert_eq!(layers[1].get_name(), "lakes_label");
+    let label = &layers[1].get_features()[0];
+    assert_eq!(label.get_id(), 7);
+    assert_eq!(layers[1].get_keys(), &["name".to_string()]);
+    assert_eq!(
+        decode_geom(label.get_field_type(), label.get_geometry()),
+        Ok(screen::Geometry::Point(screen::Point { x: 500, y: 500 }))
+    );
+
+    // Replace polygon in same layer
+    let mut tile = Tile::new(&extent, false);
+    layer.label_point = Some(LayerLabel {
+        replace: true,
+        layer: None,
+        precision: 1.0,
+    });
+    let mut mvt_layer = tile.new_layer(&layer);
+    tile.add_feature(&mut mvt_layer, &Lake);
+    tile.add_layer(mvt_layer);
+    let layers = tile.mvt_tile.get_layers();
+    assert_eq!(layers.len(), 1);
+    assert_eq!(layers[0].get_features().len(), 1);
+    assert_eq!(
+        layers[0].get_features()[0].get_field_type(),
+        Tile_GeomType::POINT
+    );
+}
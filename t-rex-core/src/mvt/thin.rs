@@ -0,0 +1,100 @@
+//
+// Copyright (c) Pirmin Kalberer. All rights reserved.
+// Licensed under the MIT License. See LICENSE file in the project root for full license information.
+//
+
+//! Density based point thinning in screen coordinates
+
+use core::layer::LayerThin;
+use mvt::cluster::ScreenPointFeature;
+use std::cmp::Ordering;
+use std::collections::HashMap;
+
+/// Greedy grid based point thinning
+pub struct PointThinner {
+    /// Minimal distance in screen units
+    distance: i32,
+    /// Ranking attribute
+    rank: Option<String>,
+    features: Vec<ScreenPointFeature>,
+}
+
+fn cell(x: i32, y: i32, size: i32) -> (i32, i32) {
+    (
+        (x as f64 / size as f64).floor() as i32,
+        (y as f64 / size as f64).floor() as i32,
+    )
+}
+
+impl PointThinner {
+    /// Create thinner with `pixel_size` screen units per pixel
+    pub fn new(thin: &LayerThin, pixel_size: f64) -> PointThinner {
+        PointThinner {
+            distance: ((thin.distance as f64 * pixel_size).round() as i32).max(1),
+            rank: thin.rank.clone(),
+            features: Vec::new(),
+        }
+    }
+    pub fn add(&mut self, feature: ScreenPointFeature) {
+        self.features.push(feature);
+    }
+    fn rank_value(&self, feature: &ScreenPointFeature) -> Option<f64> {
+        let rank = self.rank.as_ref()?;
+        feature
+            .attributes
+            .iter()
+            .find(|attr| attr.key == *rank)
+            .and_then(|attr| attr.value.numeric_value())
+            .map(|(v, _)| v)
+    }
+    /// Remaining points in input order. Points are kept in descending order of
+    /// the ranking attribute, points without rank value last.
+    pub fn points(self) -> Vec<ScreenPointFeature> {
+        let distance = self.distance;
+        let mut ranked: Vec<(usize, Option<f64>)> = self.features
+            .iter()
+            .enumerate()
+            .map(|(i, f)| (i, self.rank_value(f)))
+            .collect();
+        // Stable sort keeps input order for equal ranks
+        ranked.sort_by(|a, b| match (a.1, b.1) {
+            (None, None) => Ordering::Equal,
+            (None, _) => Ordering::Greater,
+            (_, None) => Ordering::Less,
+            (Some(ra), Some(rb)) => rb.partial_cmp(&ra).unwrap_or(Ordering::Equal),
+        });
+
+        let mut grid: HashMap<(i32, i32), Vec<usize>> = HashMap::new();
+        let mut keep = vec![false; self.features.len()];
+        for (i, _) in ranked {
+            let p = self.features[i].point;
+            let (cx, cy) = cell(p.x, p.y, distance);
+            let mut too_close = false;
+            'cells: for x in cx - 1..cx + 2 {
+                for y in cy - 1..cy + 2 {
+                    if let Some(indices) = grid.get(&(x, y)) {
+                        for &j in indices {
+                            let q = self.features[j].point;
+                            let dx = (q.x - p.x) as i64;
+                            let dy = (q.y - p.y) as i64;
+                            if dx * dx + dy * dy < distance as i64 * distance as i64 {
+                                too_close = true;
+                                break 'cells;
+                            }
+                        }
+                    }
+                }
+            }
+            if !too_close {
+                keep[i] = true;
+                grid.entry((cx, cy)).or_insert(Vec::new()).push(i);
+            }
+        }
+        self.features
+            .into_iter()
+            .zip(keep)
+            .filter(|&(_, keep)| keep)
+            .map(|(f, _)| f)
+            .collect()
+    }
+}
@@ -0,0 +1,67 @@
+//
+// Copyright (c) Pirmin Kalberer. All rights reserved.
+// Licensed under the MIT License. See LICENSE file in the project root for full license information.
+//
+
+use core::feature::{FeatureAttr, FeatureAttrValType};
+use core::layer::LayerThin;
+use core::screen;
+use mvt::cluster::ScreenPointFeature;
+use mvt::thin::PointThinner;
+
+fn point_feature(fid: u64, x: i32, y: i32, population: i64) -> ScreenPointFeature {
+    ScreenPointFeature {
+        fid: Some(fid),
+        attributes: vec![FeatureAttr {
+            key: "population".to_string(),
+            value: FeatureAttrValType::Int(population),
+        }],
+        point: screen::Point { x: x, y: y },
+    }
+}
+
+fn fids(features: &Vec<ScreenPointFeature>) -> Vec<u64> {
+    features.iter().map(|f| f.fid.unwrap()).collect()
+}
+
+fn add_points(thinner: &mut PointThinner) {
+    thinner.add(point_feature(1, 100, 100, 500));
+    thinner.add(point_feature(2, 105, 100, 2000));
+    thinner.add(point_feature(3, 100, 108, 100));
+    thinner.add(point_feature(4, 300, 300, 10));
+}
+
+#[test]
+fn test_thin_input_order() {
+    let thin = LayerThin {
+        distance: 10,
+        rank: None,
+        maxzoom: None,
+    };
+    let mut thinner = PointThinner::new(&thin, 1.0);
+    add_points(&mut thinner);
+    assert_eq!(fids(&thinner.points()), vec![1, 4]);
+}
+
+#[test]
+fn test_thin_rank() {
+    let thin = LayerThin {
+        distance: 10,
+        rank: Some("population".to_string()),
+        maxzoom: None,
+    };
+    let mut thinner = PointThinner::new(&thin, 1.0);
+    add_points(&mut thinner);
+    // Point 3 is 9.4 units away from point 2
+    assert_eq!(fids(&thinner.points()), vec![2, 4]);
+
+    // Distance in pixels with 4 screen units per pixel
+    let thin = LayerThin {
+        distance: 1,
+        rank: Some("population".to_string()),
+        maxzoom: None,
+    };
+    let mut thinner = PointThinner::new(&thin, 4.0);
+    add_points(&mut thinner);
+    assert_eq!(fids(&thinner.points()), vec![1, 2, 3, 4]);
+}
@@ -3,7 +3,7 @@
 // Licensed under the MIT License. See LICENSE file in the project root for full license information.
 //
 
-use core::feature::{Feature, FeatureAttrValType};
+use core::feature::Feature;
 use core::geom;
 use core::geom::GeometryType;
 use core::grid::Extent;
@@ -12,7 +12,10 @@ use core::screen;
 use flate2::read::GzDecoder;
 use flate2::write::GzEncoder;
 use flate2::Compression;
+use mvt::cluster::ScreenPointFeature;
 use mvt::geom_encoder::{CommandSequence, EncodableGeom};
+use mvt::layer_builder::LayerBuilder;
+use mvt::polylabel::label_point;
 use mvt::vector_tile;
 use protobuf::error::ProtobufError;
 use protobuf::stream::CodedOutputStream;
@@ -160,39 +163,55 @@ impl<'a> Tile<'a> {
         }
     }
 
-    pub fn new_layer(&mut self, layer: &Layer) -> vector_tile::Tile_Layer {
-        let mut mvt_layer = vector_tile::Tile_Layer::new();
-        mvt_layer.set_version(2);
-        mvt_layer.set_name(layer.name.clone());
-        mvt_layer.set_extent(layer.tile_size);
+    pub fn new_layer(&mut self, layer: &Layer) -> LayerBuilder {
+        let mut mvt_layer = LayerBuilder::new(&layer.name, layer.tile_size);
+        if layer.merge {
+            mvt_layer.set_merge();
+        }
+        if let Some(ref label) = layer.label_point {
+            mvt_layer.set_label(label);
+        }
+        if let Some(ref order) = layer.order_by {
+            mvt_layer.set_order(order);
+        }
+        mvt_layer.set_fid_mode(layer.fid_mode, layer.fid_field.as_ref());
         mvt_layer
     }
 
-    pub fn encode_geom(&self, geom: geom::GeometryType, tile_size: u32) -> CommandSequence {
+    /// Convert geometry into screen coordinates
+    pub fn screen_geom(&self, geom: &geom::GeometryType, tile_size: u32) -> screen::Geometry {
         match geom {
-            GeometryType::Point(ref g) => {
-                screen::Point::from_geom(&self.extent, self.reverse_y, tile_size, g).encode()
-            }
-            GeometryType::MultiPoint(ref g) => {
-                screen::MultiPoint::from_geom(&self.extent, self.reverse_y, tile_size, g).encode()
-            }
-            GeometryType::LineString(ref g) => {
-                screen::LineString::from_geom(&self.extent, self.reverse_y, tile_size, g).encode()
-            }
-            GeometryType::MultiLineString(ref g) => {
-                screen::MultiLineString::from_geom(&self.extent, self.reverse_y, tile_size, g)
-                    .encode()
-            }
-            GeometryType::Polygon(ref g) => {
-                screen::Polygon::from_geom(&self.extent, self.reverse_y, tile_size, g).encode()
-            }
-            GeometryType::MultiPolygon(ref g) => {
-                screen::MultiPolygon::from_geom(&self.extent, self.reverse_y, tile_size, g).encode()
-            }
-            GeometryType::GeometryCollection(_) => panic!("GeometryCollection not supported"),
+            &GeometryType::Point(ref g) => screen::Geometry::Point(screen::Point::from_geom(
+                &self.extent,
+                self.reverse_y,
+                tile_size,
+                g,
+            )),
+            &GeometryType::MultiPoint(ref g) => screen::Geometry::MultiPoint(
+                screen::MultiPoint::from_geom(&self.extent, self.reverse_y, tile_size, g),
+            ),
+            &GeometryType::LineString(ref g) => screen::Geometry::LineString(
+                screen::LineString::from_geom(&self.extent, self.reverse_y, tile_size, g),
+            ),
+            &GeometryType::MultiLineString(ref g) => screen::Geometry::MultiLineString(
+                screen::MultiLineString::from_geom(&self.extent, self.reverse_y, tile_size, g),
+            ),
+            &GeometryType::Polygon(ref g) => screen::Geometry::Polygon(
+                screen::Polygon::from_geom(&self.extent, self.reverse_y, tile_size, g),
+            ),
+            &GeometryType::MultiPolygon(ref g) => screen::Geometry::MultiPolygon(
+                screen::MultiPolygon::from_geom(&self.extent, self.reverse_y, tile_size, g),
+            ),
+            &GeometryType::GeometryCollection(_) => panic!("GeometryCollection not supported"),
         }
     }
 
+    pub fn encode_geom(&self, geom: geom::GeometryType, tile_size: u32) -> CommandSequence {
+        self.screen_geom(&geom, tile_size).encode()
+    }
+
+    /// Add key/value pair to a raw MVT layer.
+    /// Values are looked up by linear search - use `LayerBuilder` for building larger layers.
     pub fn add_feature_attribute(
         mvt_layer: &mut vector_tile::Tile_Layer,
         mvt_feature: &mut vector_tile::Tile_Feature,
@@ -200,7 +219,6 @@ impl<'a> Tile<'a> {
         mvt_value: vector_tile::Tile_Value,
     ) {
         let keyentry = mvt_layer.get_keys().iter().position(|k| *k == key);
-        // Optimization: maintain a hash table with key/index pairs
         let keyidx = match keyentry {
             None => {
                 mvt_layer.mut_keys().push(key);
@@ -211,7 +229,6 @@ impl<'a> Tile<'a> {
         mvt_feature.mut_tags().push(keyidx as u32);
 
         let valentry = mvt_layer.get_values().iter().position(|v| *v == mvt_value);
-        // Optimization: maintain a hash table with value/index pairs
         let validx = match valentry {
             None => {
                 mvt_layer.mut_values().push(mvt_value);
@@ -222,54 +239,70 @@ impl<'a> Tile<'a> {
         mvt_feature.mut_tags().push(validx as u32);
     }
 
-    pub fn add_feature(&self, mut mvt_layer: &mut vector_tile::Tile_Layer, feature: &Feature) {
+    pub fn add_feature(&self, mvt_layer: &mut LayerBuilder, feature: &Feature) {
+        let attributes = feature.attributes();
+        let fid = mvt_layer.feature_id(feature.fid(), &attributes);
+        let attributes = mvt_layer.filter_attributes(attributes);
+        let geom = match feature.geometry() {
+            Ok(geom) => geom,
+            Err(_) => return,
+        };
+        if let GeometryType::Point(ref p) = geom {
+            if mvt_layer.is_clustering() || mvt_layer.is_thinning() {
+                let point =
+                    screen::Point::from_geom(&self.extent, self.reverse_y, mvt_layer.extent(), p);
+                let feature = ScreenPointFeature {
+                    fid: fid,
+                    attributes: attributes,
+                    point: point,
+                };
+                if mvt_layer.is_clustering() {
+                    mvt_layer.add_cluster_point(feature);
+                } else {
+                    mvt_layer.add_thin_point(feature);
+                }
+                return;
+            }
+        }
+        if geom.is_empty() {
+            return;
+        }
+        let screen_geom = self.screen_geom(&geom, mvt_layer.extent());
+        if mvt_layer.has_min_size() && mvt_layer.is_too_small(&screen_geom) {
+            return;
+        }
         let mut mvt_feature = vector_tile::Tile_Feature::new();
-        if let Some(fid) = feature.fid() {
+        if let Some(fid) = fid {
             mvt_feature.set_id(fid);
         }
-        for attr in feature.attributes() {
-            let mut mvt_value = vector_tile::Tile_Value::new();
-            match attr.value {
-                FeatureAttrValType::String(ref v) => {
-                    mvt_value.set_string_value(v.clone());
-                }
-                FeatureAttrValType::Double(v) => {
-                    mvt_value.set_double_value(v);
-                }
-                FeatureAttrValType::Float(v) => {
-                    mvt_value.set_float_value(v);
-                }
-                FeatureAttrValType::Int(v) => {
-                    mvt_value.set_int_value(v);
-                }
-                FeatureAttrValType::UInt(v) => {
-                    mvt_value.set_uint_value(v);
-                }
-                FeatureAttrValType::SInt(v) => {
-                    mvt_value.set_sint_value(v);
-                }
-                FeatureAttrValType::Bool(v) => {
-                    mvt_value.set_bool_value(v);
+        for attr in &attributes {
+            mvt_layer.add_feature_attribute(&mut mvt_feature, &attr.key, &attr.value);
+        }
+        let label = mvt_layer.label().map(|l| (l.precision, l.replace));
+        let is_polygon = geom.mvt_field_type() == vector_tile::Tile_GeomType::POLYGON;
+        if let (Some((precision, replace)), true) = (label, is_polygon) {
+            if let Some(point) = label_point(&screen_geom, precision) {
+                mvt_layer.add_label_point(fid, &attributes, point);
+                if replace {
+                    return;
                 }
             }
-            Tile::add_feature_attribute(
-                &mut mvt_layer,
-                &mut mvt_feature,
-                attr.key.clone(),
-                mvt_value,
-            );
         }
-        if let Ok(geom) = feature.geometry() {
-            if !geom.is_empty() {
-                mvt_feature.set_field_type(geom.mvt_field_type());
-                mvt_feature.set_geometry(self.encode_geom(geom, mvt_layer.get_extent()).vec());
-                mvt_layer.mut_features().push(mvt_feature);
-            }
+        mvt_feature.set_field_type(geom.mvt_field_type());
+        if mvt_layer.is_merging() {
+            mvt_layer.add_merge_feature(mvt_feature, screen_geom);
+        } else {
+            mvt_feature.set_geometry(screen_geom.encode().vec());
+            mvt_layer.push_feature(mvt_feature);
         }
     }
 
-    pub fn add_layer(&mut self, mvt_layer: vector_tile::Tile_Layer) {
-        self.mvt_tile.mut_layers().push(mvt_layer);
+    pub fn add_layer(&mut self, mut mvt_layer: LayerBuilder) {
+        let label_layer = mvt_layer.take_label_layer();
+        self.mvt_tile.mut_layers().push(mvt_layer.into_layer());
+        if let Some(label_layer) = label_layer {
+            self.mvt_tile.mut_layers().push(label_layer.into_layer());
+        }
     }
 
     pub fn write_to(mut out: &mut Write, mvt_tile: &vector_tile::Tile) {
@@ -299,6 +332,16 @@ impl<'a> Tile<'a> {
         parse_from_reader::<vector_tile::Tile>(&mut reader)
     }
 
+    /// Read tile from raw or gzip compressed data
+    pub fn read_bytes(data: &[u8]) -> Result<vector_tile::Tile, ProtobufError> {
+        let mut reader = data;
+        if data.starts_with(&[0x1f, 0x8b]) {
+            Self::read_gz_from(&mut reader)
+        } else {
+            Self::read_from(&mut reader)
+        }
+    }
+
     pub fn binary_tile(mvt_tile: &vector_tile::Tile) -> Vec<u8> {
         let mut v = Vec::new();
         Self::write_to(&mut v, mvt_tile);
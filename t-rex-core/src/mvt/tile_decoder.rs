@@ -0,0 +1,295 @@
+//
+// Copyright (c) Pirmin Kalberer. All rights reserved.
+// Licensed under the MIT License. See LICENSE file in the project root for full license information.
+//
+
+//! Decode MVT tiles into features and GeoJSON
+
+use core::feature::{FeatureAttr, FeatureAttrValType};
+use core::grid::{web_mercator_to_wgs84, Extent};
+use core::screen;
+use mvt::geom_decoder::decode_geom;
+use mvt::vector_tile;
+use serde_json;
+use std::collections::BTreeMap;
+
+/// Feature read from a MVT layer
+#[derive(Debug)]
+pub struct DecodedFeature {
+    pub id: Option<u64>,
+    pub attributes: Vec<FeatureAttr>,
+    pub geometry: Result<screen::Geometry, String>,
+}
+
+/// Layer read from a MVT tile
+#[derive(Debug)]
+pub struct DecodedLayer {
+    pub name: String,
+    /// Width and height of the layer in screen coordinates
+    pub extent: u32,
+    pub features: Vec<DecodedFeature>,
+}
+
+/// Georeference of tile screen coordinates
+pub struct TileGeoref<'a> {
+    /// Tile extent in grid SRS
+    pub extent: &'a Extent,
+    /// Screen y axis pointing downwards (XYZ tiles)
+    pub reverse_y: bool,
+    /// Grid SRID. Web Mercator coordinates are converted to WGS84.
+    pub srid: i32,
+}
+
+impl<'a> TileGeoref<'a> {
+    /// Convert screen coordinates into geographic coordinates
+    pub fn coords(&self, point: &screen::Point, tile_size: u32) -> (f64, f64) {
+        let x_span = self.extent.maxx - self.extent.minx;
+        let y_span = self.extent.maxy - self.extent.miny;
+        let x = self.extent.minx + point.x as f64 * x_span / tile_size as f64;
+        let y = if self.reverse_y {
+            self.extent.maxy - point.y as f64 * y_span / tile_size as f64
+        } else {
+            self.extent.miny + point.y as f64 * y_span / tile_size as f64
+        };
+        if self.srid == 3857 {
+            web_mercator_to_wgs84(x, y)
+        } else {
+            (x, y)
+        }
+    }
+}
+
+/// Decode all layers of a tile
+pub fn decode_tile(mvt_tile: &vector_tile::Tile) -> Vec<DecodedLayer> {
+    mvt_tile.get_layers().iter().map(decode_layer).collect()
+}
+
+/// Decode features of a layer
+pub fn decode_layer(mvt_layer: &vector_tile::Tile_Layer) -> DecodedLayer {
+    let keys = mvt_layer.get_keys();
+    let values = mvt_layer.get_values();
+    let features = mvt_layer
+        .get_features()
+        .iter()
+        .map(|mvt_feature| {
+            let mut attributes = Vec::new();
+            for tag in mvt_feature.get_tags().chunks(2) {
+                if tag.len() < 2 {
+                    warn!("Layer '{}': odd number of tags", mvt_layer.get_name());
+                    break;
+                }
+                let key = keys.get(tag[0] as usize);
+                let value = values
+                    .get(tag[1] as usize)
+                    .and_then(|v| FeatureAttrValType::from_mvt_value(v));
+                match (key, value) {
+                    (Some(key), Some(value)) => attributes.push(FeatureAttr {
+                        key: key.clone(),
+                        value: value,
+                    }),
+                    _ => warn!("Layer '{}': invalid tag {:?}", mvt_layer.get_name(), tag),
+                }
+            }
+            DecodedFeature {
+                id: if mvt_feature.has_id() {
+                    Some(mvt_feature.get_id())
+                } else {
+                    None
+                },
+                attributes: attributes,
+                geometry: decode_geom(mvt_feature.get_field_type(), mvt_feature.get_geometry()),
+            }
+        })
+        .collect();
+    DecodedLayer {
+        name: mvt_layer.get_name().to_string(),
+        extent: mvt_layer.get_extent(),
+        features: features,
+    }
+}
+
+fn json_coords(
+    points: &Vec<screen::Point>,
+    georef: Option<&TileGeoref>,
+    tile_size: u32,
+) -> serde_json::Value {
+    json!(points
+        .iter()
+        .map(|p| json_point(p, georef, tile_size))
+        .collect::<Vec<_>>())
+}
+
+fn json_point(
+    point: &screen::Point,
+    georef: Option<&TileGeoref>,
+    tile_size: u32,
+) -> serde_json::Value {
+    match georef {
+        Some(georef) => {
+            let (x, y) = georef.coords(point, tile_size);
+            json!([x, y])
+        }
+        None => json!([point.x, point.y]),
+    }
+}
+
+fn json_rings(
+    rings: &Vec<screen::LineString>,
+    georef: Option<&TileGeoref>,
+    tile_size: u32,
+) -> serde_json::Value {
+    json!(rings
+        .iter()
+        .map(|r| json_coords(&r.points, georef, tile_size))
+        .collect::<Vec<_>>())
+}
+
+/// GeoJSON geometry in screen coordinates or georeferenced
+pub fn geometry_to_geojson(
+    geom: &screen::Geometry,
+    georef: Option<&TileGeoref>,
+    tile_size: u32,
+) -> serde_json::Value {
+    match geom {
+        &screen::Geometry::Point(ref g) => json!({
+            "type": "Point",
+            "coordinates": json_point(g, georef, tile_size)
+        }),
+        &screen::Geometry::MultiPoint(ref g) => json!({
+            "type": "MultiPoint",
+            "coordinates": json_coords(&g.points, georef, tile_size)
+        }),
+        &screen::Geometry::LineString(ref g) => json!({
+            "type": "LineString",
+            "coordinates": json_coords(&g.points, georef, tile_size)
+        }),
+        &screen::Geometry::MultiLineString(ref g) => json!({
+            "type": "MultiLineString",
+            "coordinates": json_rings(&g.lines, georef, tile_size)
+        }),
+        &screen::Geometry::Polygon(ref g) => json!({
+            "type": "Polygon",
+            "coordinates": json_rings(&g.rings, georef, tile_size)
+        }),
+        &screen::Geometry::MultiPolygon(ref g) => json!({
+            "type": "MultiPolygon",
+            "coordinates": g.polygons
+                .iter()
+                .map(|p| json_rings(&p.rings, georef, tile_size))
+                .collect::<Vec<_>>()
+        }),
+    }
+}
+
+impl DecodedLayer {
+    /// GeoJSON FeatureCollection of layer features
+    pub fn to_geojson(&self, georef: Option<&TileGeoref>) -> serde_json::Value {
+        let features: Vec<serde_json::Value> = self
+            .features
+            .iter()
+            .map(|feature| {
+                let mut properties = serde_json::Map::new();
+                for attr in &feature.attributes {
+                    properties.insert(attr.key.clone(), attr.value.to_json());
+                }
+                let geometry = match feature.geometry {
+                    Ok(ref geom) => geometry_to_geojson(geom, georef, self.extent),
+                    Err(_) => serde_json::Value::Null,
+                };
+                let mut json = json!({
+                    "type": "Feature",
+                    "geometry": geometry,
+                    "properties": properties
+                });
+                if let Some(id) = feature.id {
+                    json["id"] = json!(id);
+                }
+                json
+            })
+            .collect();
+        json!({
+            "type": "FeatureCollection",
+            "name": self.name,
+            "features": features
+        })
+    }
+    /// Human readable summary of layer content
+    pub fn summary(&self) -> String {
+        let mut geom_types: BTreeMap<&str, usize> = BTreeMap::new();
+        let mut key_counts: BTreeMap<&str, usize> = BTreeMap::new();
+        let mut vertices = 0;
+        let mut invalid = 0;
+        for feature in &self.features {
+            for attr in &feature.attributes {
+                *key_counts.entry(&attr.key).or_insert(0) += 1;
+            }
+            let geom_type = match feature.geometry {
+                Ok(ref geom) => {
+                    vertices += geom.num_points();
+                    geom.type_name()
+                }
+                Err(_) => {
+                    invalid += 1;
+                    "Invalid"
+                }
+            };
+            *geom_types.entry(geom_type).or_insert(0) += 1;
+        }
+        let mut lines = vec![format!("Layer '{}'", self.name)];
+        lines.push(format!("  Extent: {}", self.extent));
+        lines.push(format!("  Features: {}", self.features.len()));
+        for (geom_type, cnt) in &geom_types {
+            lines.push(format!("    {}: {}", geom_type, cnt));
+        }
+        lines.push(format!("  Vertices: {}", vertices));
+        if invalid > 0 {
+            lines.push(format!("  Invalid geometries: {}", invalid));
+        }
+        lines.push(format!("  Attributes: {}", key_counts.len()));
+        for (key, cnt) in &key_counts {
+            lines.push(format!("    {}: {}", key, cnt));
+        }
+        lines.join("\n")
+    }
+}
+
+impl screen::Geometry {
+    pub fn type_name(&self) -> &'static str {
+        match self {
+            &screen::Geometry::Point(_) => "Point",
+            &screen::Geometry::MultiPoint(_) => "MultiPoint",
+            &screen::Geometry::LineString(_) => "LineString",
+            &screen::Geometry::MultiLineString(_) => "MultiLineString",
+            &screen::Geometry::Polygon(_) => "Polygon",
+            &screen::Geometry::MultiPolygon(_) => "MultiPolygon",
+        }
+    }
+    /// Number of vertices
+    pub fn num_points(&self) -> usize {
+        match self {
+            &screen::Geometry::Point(_) => 1,
+            &screen::Geometry::MultiPoint(ref g) => g.points.len(),
+            &screen::Geometry::LineString(ref g) => g.points.len(),
+            &screen::Geometry::MultiLineString(ref g) => {
+                g.lines.iter().map(|l| l.points.len()).sum()
+            }
+            &screen::Geometry::Polygon(ref g) => g.rings.iter().map(|r| r.points.len()).sum(),
+            &screen::Geometry::MultiPolygon(ref g) => g
+                .polygons
+                .iter()
+                .flat_map(|p| p.rings.iter())
+                .map(|r| r.points.len())
+                .sum(),
+        }
+    }
+}
+
+/// Human readable summary of tile content
+pub fn tile_summary(mvt_tile: &vector_tile::Tile) -> String {
+    let layers = decode_tile(mvt_tile);
+    let mut lines = vec![format!("Layers: {}", layers.len())];
+    for layer in &layers {
+        lines.push(layer.summary());
+    }
+    lines.join("\n")
+}
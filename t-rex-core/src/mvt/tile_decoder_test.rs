@@ -0,0 +1,207 @@
+//
+// Copyright (c) Pirmin Kalberer. All rights reserved.
+// Licensed under the MIT License. See LICENSE file in the project root for full license information.
+//
+
+use core::feature::{FeatureAttr, FeatureAttrValType, FeatureStruct};
+use core::geom;
+use core::geom::GeometryType;
+use core::grid::{Extent, Grid};
+use core::layer::Layer;
+use core::screen;
+use mvt::geom_decoder::decode_geom;
+use mvt::geom_encoder::EncodableGeom;
+use mvt::tile::Tile;
+use mvt::tile_decoder::{decode_tile, tile_summary, TileGeoref};
+use mvt::vector_tile::Tile_GeomType;
+use std::fs::File;
+
+#[test]
+fn test_geom_decoding() {
+    let point = screen::Point { x: 25, y: 17 };
+    assert_eq!(
+        decode_geom(Tile_GeomType::POINT, &point.encode().0),
+        Ok(screen::Geometry::Point(point))
+    );
+
+    let multipoint = screen::MultiPoint {
+        points: vec![screen::Point { x: 5, y: 7 }, screen::Point { x: 3, y: 2 }],
+    };
+    assert_eq!(
+        decode_geom(Tile_GeomType::POINT, &[17, 10, 14, 3, 9]),
+        Ok(screen::Geometry::MultiPoint(multipoint))
+    );
+
+    let multilinestring = screen::MultiLineString {
+        lines: vec![
+            screen::LineString {
+                points: vec![
+                    screen::Point { x: 2, y: 2 },
+                    screen::Point { x: 2, y: 10 },
+                    screen::Point { x: 10, y: 10 },
+                ],
+            },
+            screen::LineString {
+                points: vec![screen::Point { x: 1, y: 1 }, screen::Point { x: 3, y: 5 }],
+            },
+        ],
+    };
+    assert_eq!(
+        decode_geom(Tile_GeomType::LINESTRING, &multilinestring.encode().0),
+        Ok(screen::Geometry::MultiLineString(multilinestring))
+    );
+
+    // https://github.com/mapbox/vector-tile-spec/tree/master/2.1#4356-example-multi-polygon
+    let multipolygon = screen::MultiPolygon {
+        polygons: vec![
+            screen::Polygon {
+                rings: vec![screen::LineString {
+                    points: vec![
+                        screen::Point { x: 0, y: 0 },
+                        screen::Point { x: 10, y: 0 },
+                        screen::Point { x: 10, y: 10 },
+                        screen::Point { x: 0, y: 10 },
+                        screen::Point { x: 0, y: 0 },
+                    ],
+                }],
+            },
+            screen::Polygon {
+                rings: vec![
+                    screen::LineString {
+                        points: vec![
+                            screen::Point { x: 11, y: 11 },
+                            screen::Point { x: 20, y: 11 },
+                            screen::Point { x: 20, y: 20 },
+                            screen::Point { x: 11, y: 20 },
+                            screen::Point { x: 11, y: 11 },
+                        ],
+                    },
+                    screen::LineString {
+                        points: vec![
+                            screen::Point { x: 13, y: 13 },
+                            screen::Point { x: 13, y: 17 },
+                            screen::Point { x: 17, y: 17 },
+                            screen::Point { x: 17, y: 13 },
+                            screen::Point { x: 13, y: 13 },
+                        ],
+                    },
+                ],
+            },
+        ],
+    };
+    assert_eq!(
+        multipolygon.encode().0,
+        &[
+            9, 0, 0, 26, 20, 0, 0, 20, 19, 0, 15, 9, 22, 2, 26, 18, 0, 0, 18, 17, 0, 15, 9, 4, 13,
+            26, 0, 8, 8, 0, 0, 7, 15,
+        ]
+    );
+    assert_eq!(
+        decode_geom(Tile_GeomType::POLYGON, &multipolygon.encode().0),
+        Ok(screen::Geometry::MultiPolygon(multipolygon))
+    );
+
+    // errors
+    assert!(decode_geom(Tile_GeomType::POINT, &[]).is_err());
+    assert!(decode_geom(Tile_GeomType::POINT, &[9, 50]).is_err());
+    assert!(decode_geom(Tile_GeomType::LINESTRING, &[18, 0, 16, 16, 0]).is_err());
+    assert!(decode_geom(Tile_GeomType::POLYGON, &[9, 4, 4, 18, 0, 16, 16, 0]).is_err());
+    assert!(decode_geom(Tile_GeomType::UNKNOWN, &[9, 50, 34]).is_err());
+}
+
+#[test]
+fn test_decode_file() {
+    let mut f = File::open("../t-rex-service/src/test/tile.pbf").unwrap();
+    let mvt_tile = Tile::read_from(&mut f).unwrap();
+    let layers = decode_tile(&mvt_tile);
+    assert_eq!(layers[0].name, "roads");
+    assert_eq!(layers[0].extent, 4096);
+    let ref feature = layers[0].features[1];
+    match feature.geometry {
+        Ok(screen::Geometry::Polygon(ref p)) => {
+            assert_eq!(p.rings[0].points[0], screen::Point { x: 4118, y: 2463 });
+        }
+        _ => panic!("Polygon expected"),
+    }
+
+    let summary = tile_summary(&mvt_tile);
+    assert!(summary.starts_with("Layers: "));
+    assert!(summary.contains("Layer 'roads'\n  Extent: 4096\n  Features: "));
+}
+
+#[test]
+fn test_decode_built_tile() {
+    let grid = Grid::web_mercator();
+    let extent = grid.tile_extent_xyz(1072, 717, 11);
+    let mut tile = Tile::new(&extent, true);
+    let layer = Layer::new("points");
+    let mut mvt_layer = tile.new_layer(&layer);
+    let feature = FeatureStruct {
+        fid: Some(42),
+        attributes: vec![
+            FeatureAttr {
+                key: String::from("name"),
+                value: FeatureAttrValType::String(String::from("Zürich")),
+            },
+            FeatureAttr {
+                key: String::from("population"),
+                value: FeatureAttrValType::Int(402762),
+            },
+        ],
+        geometry: GeometryType::Point(geom::Point::new(949398.0, 6002729.0, Some(3857))),
+    };
+    tile.add_feature(&mut mvt_layer, &feature);
+    tile.add_layer(mvt_layer);
+
+    let mut data = Vec::new();
+    Tile::write_gz_to(&mut data, &tile.mvt_tile);
+    let mvt_tile = Tile::read_bytes(&data).unwrap();
+    let layers = decode_tile(&mvt_tile);
+    assert_eq!(layers.len(), 1);
+    let ref feature = layers[0].features[0];
+    assert_eq!(feature.id, Some(42));
+    assert_eq!(feature.attributes[0].key, "name");
+    assert_eq!(feature.attributes[1].value, FeatureAttrValType::Int(402762));
+
+    let geojson = layers[0].to_geojson(None);
+    assert_eq!(
+        geojson["features"][0]["geometry"],
+        json!({"type": "Point", "coordinates": [2122, 965]})
+    );
+    assert_eq!(
+        geojson["features"][0]["properties"]["name"],
+        json!("Zürich")
+    );
+    assert_eq!(geojson["features"][0]["id"], json!(42));
+
+    let georef = TileGeoref {
+        extent: &extent,
+        reverse_y: true,
+        srid: 3857,
+    };
+    let geojson = layers[0].to_geojson(Some(&georef));
+    let coords = &geojson["features"][0]["geometry"]["coordinates"];
+    // Zurich: 8.5285874, 47.3703149
+    assert!((coords[0].as_f64().unwrap() - 8.5286).abs() < 0.001);
+    assert!((coords[1].as_f64().unwrap() - 47.3703).abs() < 0.001);
+}
+
+#[test]
+fn test_georef() {
+    let extent = Extent {
+        minx: -20037508.342789248,
+        miny: -20037508.342789248,
+        maxx: 20037508.342789248,
+        maxy: 20037508.342789248,
+    };
+    let georef = TileGeoref {
+        extent: &extent,
+        reverse_y: true,
+        srid: 3857,
+    };
+    let (lon, lat) = georef.coords(&screen::Point { x: 0, y: 0 }, 4096);
+    assert!((lon + 180.0).abs() < 0.0001);
+    assert!((lat - 85.0511).abs() < 0.0001);
+    let (lon, lat) = georef.coords(&screen::Point { x: 2048, y: 2048 }, 4096);
+    assert_eq!((lon, lat), (0.0, 0.0));
+}
@@ -17,6 +17,8 @@ pub struct Tileset {
     pub extent: Option<Extent>,
     pub center: Option<(f64, f64)>,
     pub start_zoom: Option<u8>,
+    /// Tile width and height in pixels
+    pub tile_size: Option<u32>,
     pub layers: Vec<Layer>,
 }
 
@@ -71,6 +73,7 @@ impl<'a> Config<'a, TilesetCfg> for Tileset {
             extent: tileset_cfg.extent.clone(),
             center: tileset_cfg.center.clone(),
             start_zoom: tileset_cfg.start_zoom.clone(),
+            tile_size: tileset_cfg.tile_size,
             layers: layers,
         })
     }
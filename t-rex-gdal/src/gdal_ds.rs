@@ -230,10 +230,10 @@ impl<'a> Feature for VectorFeature<'a> {
 
 impl DatasourceInput for GdalDatasource {
     /// New instance with connected pool
-    fn connected(&self) -> GdalDatasource {
-        GdalDatasource {
+    fn connected(&self) -> Result<GdalDatasource, String> {
+        Ok(GdalDatasource {
             path: self.path.clone(),
-        }
+        })
     }
     fn detect_layers(&self, _detect_geometry_types: bool) -> Vec<Layer> {
         let mut layers: Vec<Layer> = Vec::new();
@@ -400,7 +400,7 @@ impl DatasourceInput for GdalDatasource {
             None
         };
 
-        let mut bbox_extent = if let Some(pixels) = layer.buffer_size {
+        let mut bbox_extent = if let Some(pixels) = layer.buffer_size(zoom) {
             let pixel_width = grid.pixel_width(zoom);
             let buf = f64::from(pixels) * pixel_width;
             Extent {
@@ -433,7 +433,7 @@ impl DatasourceInput for GdalDatasource {
 
         let fields_defn = ogr_layer.defn().fields().collect::<Vec<_>>();
         let mut cnt = 0;
-        let query_limit = layer.query_limit.unwrap_or(0);
+        let query_limit = layer.query_limit(zoom).unwrap_or(0);
         for feature in ogr_layer.features() {
             let feat = VectorFeature {
                 layer: layer,
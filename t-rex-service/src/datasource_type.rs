@@ -8,11 +8,12 @@ use core::config::{ApplicationCfg, DatasourceCfg};
 use core::feature::Feature;
 use core::grid::Extent;
 use core::grid::Grid;
-use core::layer::Layer;
+use core::layer::{Layer, ParamValue};
 use core::Config;
 #[cfg(not(feature = "with-gdal"))]
 use datasource::DummyDatasource as GdalDatasource;
-use datasource::{DatasourceInput, PostgisInput};
+use datasource::{CsvDatasource, DatasourceInput, FgbDatasource, GeoJsonDatasource, GpkgDatasource,
+                 PostgisInput, TilesDatasource};
 #[cfg(feature = "with-gdal")]
 use gdal_ds::GdalDatasource;
 use std::collections::HashMap;
@@ -20,43 +21,78 @@ use std::collections::HashMap;
 pub enum Datasource {
     Postgis(PostgisInput),
     Gdal(GdalDatasource),
+    Gpkg(GpkgDatasource),
+    GeoJson(GeoJsonDatasource),
+    Fgb(FgbDatasource),
+    Tiles(TilesDatasource),
+    Csv(CsvDatasource),
 }
 
 impl DatasourceInput for Datasource {
-    fn connected(&self) -> Datasource {
-        match self {
-            &Datasource::Postgis(ref ds) => Datasource::Postgis(ds.connected()),
-            &Datasource::Gdal(ref ds) => Datasource::Gdal(ds.connected()),
-        }
+    fn connected(&self) -> Result<Datasource, String> {
+        Ok(match self {
+            &Datasource::Postgis(ref ds) => Datasource::Postgis(ds.connected()?),
+            &Datasource::Gdal(ref ds) => Datasource::Gdal(ds.connected()?),
+            &Datasource::Gpkg(ref ds) => Datasource::Gpkg(ds.connected()?),
+            &Datasource::GeoJson(ref ds) => Datasource::GeoJson(ds.connected()?),
+            &Datasource::Fgb(ref ds) => Datasource::Fgb(ds.connected()?),
+            &Datasource::Tiles(ref ds) => Datasource::Tiles(ds.connected()?),
+            &Datasource::Csv(ref ds) => Datasource::Csv(ds.connected()?),
+        })
     }
     fn detect_layers(&self, detect_geometry_types: bool) -> Vec<Layer> {
         match self {
             &Datasource::Postgis(ref ds) => ds.detect_layers(detect_geometry_types),
             &Datasource::Gdal(ref ds) => ds.detect_layers(detect_geometry_types),
+            &Datasource::Gpkg(ref ds) => ds.detect_layers(detect_geometry_types),
+            &Datasource::GeoJson(ref ds) => ds.detect_layers(detect_geometry_types),
+            &Datasource::Fgb(ref ds) => ds.detect_layers(detect_geometry_types),
+            &Datasource::Tiles(ref ds) => ds.detect_layers(detect_geometry_types),
+            &Datasource::Csv(ref ds) => ds.detect_layers(detect_geometry_types),
         }
     }
     fn detect_data_columns(&self, layer: &Layer, sql: Option<&String>) -> Vec<(String, String)> {
         match self {
             &Datasource::Postgis(ref ds) => ds.detect_data_columns(layer, sql),
             &Datasource::Gdal(ref ds) => ds.detect_data_columns(layer, sql),
+            &Datasource::Gpkg(ref ds) => ds.detect_data_columns(layer, sql),
+            &Datasource::GeoJson(ref ds) => ds.detect_data_columns(layer, sql),
+            &Datasource::Fgb(ref ds) => ds.detect_data_columns(layer, sql),
+            &Datasource::Tiles(ref ds) => ds.detect_data_columns(layer, sql),
+            &Datasource::Csv(ref ds) => ds.detect_data_columns(layer, sql),
         }
     }
     fn extent_from_wgs84(&self, extent: &Extent, dest_srid: i32) -> Option<Extent> {
         match self {
             &Datasource::Postgis(ref ds) => ds.extent_from_wgs84(extent, dest_srid),
             &Datasource::Gdal(ref ds) => ds.extent_from_wgs84(extent, dest_srid),
+            &Datasource::Gpkg(ref ds) => ds.extent_from_wgs84(extent, dest_srid),
+            &Datasource::GeoJson(ref ds) => ds.extent_from_wgs84(extent, dest_srid),
+            &Datasource::Fgb(ref ds) => ds.extent_from_wgs84(extent, dest_srid),
+            &Datasource::Tiles(ref ds) => ds.extent_from_wgs84(extent, dest_srid),
+            &Datasource::Csv(ref ds) => ds.extent_from_wgs84(extent, dest_srid),
         }
     }
     fn layer_extent(&self, layer: &Layer) -> Option<Extent> {
         match self {
             &Datasource::Postgis(ref ds) => ds.layer_extent(layer),
             &Datasource::Gdal(ref ds) => ds.layer_extent(layer),
+            &Datasource::Gpkg(ref ds) => ds.layer_extent(layer),
+            &Datasource::GeoJson(ref ds) => ds.layer_extent(layer),
+            &Datasource::Fgb(ref ds) => ds.layer_extent(layer),
+            &Datasource::Tiles(ref ds) => ds.layer_extent(layer),
+            &Datasource::Csv(ref ds) => ds.layer_extent(layer),
         }
     }
     fn prepare_queries(&mut self, layer: &Layer, grid_srid: i32) {
         match self {
             &mut Datasource::Postgis(ref mut ds) => ds.prepare_queries(layer, grid_srid),
             &mut Datasource::Gdal(ref mut ds) => ds.prepare_queries(layer, grid_srid),
+            &mut Datasource::Gpkg(ref mut ds) => ds.prepare_queries(layer, grid_srid),
+            &mut Datasource::GeoJson(ref mut ds) => ds.prepare_queries(layer, grid_srid),
+            &mut Datasource::Fgb(ref mut ds) => ds.prepare_queries(layer, grid_srid),
+            &mut Datasource::Tiles(ref mut ds) => ds.prepare_queries(layer, grid_srid),
+            &mut Datasource::Csv(ref mut ds) => ds.prepare_queries(layer, grid_srid),
         }
     }
     fn retrieve_features<F>(&self, layer: &Layer, extent: &Extent, zoom: u8, grid: &Grid, read: F)
@@ -66,6 +102,79 @@ impl DatasourceInput for Datasource {
         match self {
             &Datasource::Postgis(ref ds) => ds.retrieve_features(layer, extent, zoom, grid, read),
             &Datasource::Gdal(ref ds) => ds.retrieve_features(layer, extent, zoom, grid, read),
+            &Datasource::Gpkg(ref ds) => ds.retrieve_features(layer, extent, zoom, grid, read),
+            &Datasource::GeoJson(ref ds) => ds.retrieve_features(layer, extent, zoom, grid, read),
+            &Datasource::Fgb(ref ds) => ds.retrieve_features(layer, extent, zoom, grid, read),
+            &Datasource::Tiles(ref ds) => ds.retrieve_features(layer, extent, zoom, grid, read),
+            &Datasource::Csv(ref ds) => ds.retrieve_features(layer, extent, zoom, grid, read),
+        }
+    }
+    fn retrieve_features_with_params<F>(
+        &self,
+        layer: &Layer,
+        extent: &Extent,
+        zoom: u8,
+        grid: &Grid,
+        params: &[ParamValue],
+        read: F,
+    ) -> Result<(), String>
+    where
+        F: FnMut(&Feature),
+    {
+        match self {
+            &Datasource::Postgis(ref ds) => {
+                ds.retrieve_features_with_params(layer, extent, zoom, grid, params, read)
+            }
+            &Datasource::Gdal(ref ds) => {
+                ds.retrieve_features_with_params(layer, extent, zoom, grid, params, read)
+            }
+            &Datasource::Gpkg(ref ds) => {
+                ds.retrieve_features_with_params(layer, extent, zoom, grid, params, read)
+            }
+            &Datasource::GeoJson(ref ds) => {
+                ds.retrieve_features_with_params(layer, extent, zoom, grid, params, read)
+            }
+            &Datasource::Fgb(ref ds) => {
+                ds.retrieve_features_with_params(layer, extent, zoom, grid, params, read)
+            }
+            &Datasource::Tiles(ref ds) => {
+                ds.retrieve_features_with_params(layer, extent, zoom, grid, params, read)
+            }
+            &Datasource::Csv(ref ds) => {
+                ds.retrieve_features_with_params(layer, extent, zoom, grid, params, read)
+            }
+        }
+    }
+    fn retrieve_mvt_layer(
+        &self,
+        layer: &Layer,
+        extent: &Extent,
+        zoom: u8,
+        grid: &Grid,
+        params: &[ParamValue],
+    ) -> Result<Option<Vec<u8>>, String> {
+        match self {
+            &Datasource::Postgis(ref ds) => {
+                ds.retrieve_mvt_layer(layer, extent, zoom, grid, params)
+            }
+            &Datasource::Gdal(ref ds) => {
+                ds.retrieve_mvt_layer(layer, extent, zoom, grid, params)
+            }
+            &Datasource::Gpkg(ref ds) => {
+                ds.retrieve_mvt_layer(layer, extent, zoom, grid, params)
+            }
+            &Datasource::GeoJson(ref ds) => {
+                ds.retrieve_mvt_layer(layer, extent, zoom, grid, params)
+            }
+            &Datasource::Fgb(ref ds) => {
+                ds.retrieve_mvt_layer(layer, extent, zoom, grid, params)
+            }
+            &Datasource::Tiles(ref ds) => {
+                ds.retrieve_mvt_layer(layer, extent, zoom, grid, params)
+            }
+            &Datasource::Csv(ref ds) => {
+                ds.retrieve_mvt_layer(layer, extent, zoom, grid, params)
+            }
         }
     }
 }
@@ -74,6 +183,16 @@ impl<'a> Config<'a, DatasourceCfg> for Datasource {
     fn from_config(ds_cfg: &DatasourceCfg) -> Result<Self, String> {
         if ds_cfg.dbconn.is_some() {
             PostgisInput::from_config(ds_cfg).and_then(|ds| Ok(Datasource::Postgis(ds)))
+        } else if ds_cfg.gpkg.is_some() || native_path(&ds_cfg.path, GPKG_EXTENSIONS) {
+            GpkgDatasource::from_config(ds_cfg).and_then(|ds| Ok(Datasource::Gpkg(ds)))
+        } else if ds_cfg.geojson.is_some() || native_path(&ds_cfg.path, GEOJSON_EXTENSIONS) {
+            GeoJsonDatasource::from_config(ds_cfg).and_then(|ds| Ok(Datasource::GeoJson(ds)))
+        } else if ds_cfg.fgb.is_some() || native_path(&ds_cfg.path, FGB_EXTENSIONS) {
+            FgbDatasource::from_config(ds_cfg).and_then(|ds| Ok(Datasource::Fgb(ds)))
+        } else if ds_cfg.mbtiles.is_some() || ds_cfg.tiles.is_some() {
+            TilesDatasource::from_config(ds_cfg).and_then(|ds| Ok(Datasource::Tiles(ds)))
+        } else if ds_cfg.csv.is_some() || native_path(&ds_cfg.path, CSV_EXTENSIONS) {
+            CsvDatasource::from_config(ds_cfg).and_then(|ds| Ok(Datasource::Csv(ds)))
         } else if ds_cfg.path.is_some() {
             GdalDatasource::from_config(ds_cfg).and_then(|ds| Ok(Datasource::Gdal(ds)))
         } else {
@@ -82,19 +201,48 @@ impl<'a> Config<'a, DatasourceCfg> for Datasource {
     }
     fn gen_config() -> String {
         format!(
-            "{}{}",
+            "{}{}{}{}{}{}{}",
             PostgisInput::gen_config(),
-            GdalDatasource::gen_config()
+            GdalDatasource::gen_config(),
+            GpkgDatasource::gen_config(),
+            GeoJsonDatasource::gen_config(),
+            FgbDatasource::gen_config(),
+            TilesDatasource::gen_config(),
+            CsvDatasource::gen_config()
         )
     }
     fn gen_runtime_config(&self) -> String {
         match self {
             &Datasource::Postgis(ref ds) => ds.gen_runtime_config(),
             &Datasource::Gdal(ref ds) => ds.gen_runtime_config(),
+            &Datasource::Gpkg(ref ds) => ds.gen_runtime_config(),
+            &Datasource::GeoJson(ref ds) => ds.gen_runtime_config(),
+            &Datasource::Fgb(ref ds) => ds.gen_runtime_config(),
+            &Datasource::Tiles(ref ds) => ds.gen_runtime_config(),
+            &Datasource::Csv(ref ds) => ds.gen_runtime_config(),
         }
     }
 }
 
+const GPKG_EXTENSIONS: &[&str] = &[".gpkg"];
+const GEOJSON_EXTENSIONS: &[&str] = &[".geojson", ".json"];
+const FGB_EXTENSIONS: &[&str] = &[".fgb"];
+const MBTILES_EXTENSIONS: &[&str] = &[".mbtiles"];
+const CSV_EXTENSIONS: &[&str] = &[".csv", ".tsv"];
+
+/// File path with one of the given extensions
+fn has_extension(path: &str, extensions: &[&str]) -> bool {
+    let path = path.to_lowercase();
+    extensions.iter().any(|ext| path.ends_with(ext))
+}
+
+/// File path to be read by a native datasource when GDAL is not available
+fn native_path(path: &Option<String>, extensions: &[&str]) -> bool {
+    !cfg!(feature = "with-gdal")
+        && path.as_ref()
+            .map_or(false, |path| has_extension(path, extensions))
+}
+
 pub struct Datasources {
     pub datasources: HashMap<String, Datasource>,
     pub default: Option<String>,
@@ -154,9 +302,23 @@ impl Datasources {
         }
         if let Some(datasource) = args.value_of("datasource") {
             #[cfg(feature = "with-gdal")]
-            let ds = Some(Datasource::Gdal(GdalDatasource::new(datasource)));
+            let ds = if has_extension(datasource, MBTILES_EXTENSIONS) {
+                Some(Datasource::Tiles(TilesDatasource::mbtiles(datasource)))
+            } else {
+                Some(Datasource::Gdal(GdalDatasource::new(datasource)))
+            };
             #[cfg(not(feature = "with-gdal"))]
-            let ds = {
+            let ds = if has_extension(datasource, GPKG_EXTENSIONS) {
+                Some(Datasource::Gpkg(GpkgDatasource::new(datasource)))
+            } else if has_extension(datasource, GEOJSON_EXTENSIONS) {
+                Some(Datasource::GeoJson(GeoJsonDatasource::new(datasource)))
+            } else if has_extension(datasource, FGB_EXTENSIONS) {
+                Some(Datasource::Fgb(FgbDatasource::new(datasource)))
+            } else if has_extension(datasource, MBTILES_EXTENSIONS) {
+                Some(Datasource::Tiles(TilesDatasource::mbtiles(datasource)))
+            } else if has_extension(datasource, CSV_EXTENSIONS) {
+                Some(Datasource::Csv(CsvDatasource::new(datasource)))
+            } else {
                 error!("GDAL datasource not supported in this build");
                 debug!("datasource: {}", datasource);
                 None
@@ -241,6 +403,115 @@ fn test_datasource_config_errors() {
     );
 }
 
+#[test]
+fn test_gpkg_datasource_from_config() {
+    let toml = r#"
+        #[[datasource]]
+        gpkg = "natural_earth.gpkg"
+        "#;
+    match ds_from_config(toml).unwrap() {
+        Datasource::Gpkg(ds) => assert_eq!(ds.path, "natural_earth.gpkg"),
+        _ => panic!(),
+    }
+
+    let toml = r#"
+        #[[datasource]]
+        path = "natural_earth.gpkg"
+        "#;
+    match ds_from_config(toml).unwrap() {
+        #[cfg(feature = "with-gdal")]
+        Datasource::Gdal(ds) => assert_eq!(ds.path, "natural_earth.gpkg"),
+        #[cfg(not(feature = "with-gdal"))]
+        Datasource::Gpkg(ds) => assert_eq!(ds.path, "natural_earth.gpkg"),
+        _ => panic!(),
+    }
+}
+
+#[test]
+fn test_file_datasource_from_config() {
+    let toml = r#"
+        #[[datasource]]
+        geojson = "places.geojson"
+        "#;
+    match ds_from_config(toml).unwrap() {
+        Datasource::GeoJson(ds) => assert_eq!(ds.path, "places.geojson"),
+        _ => panic!(),
+    }
+
+    let toml = r#"
+        #[[datasource]]
+        fgb = "places.fgb"
+        "#;
+    match ds_from_config(toml).unwrap() {
+        Datasource::Fgb(ds) => assert_eq!(ds.path, "places.fgb"),
+        _ => panic!(),
+    }
+
+    let toml = r#"
+        #[[datasource]]
+        path = "places.fgb"
+        "#;
+    match ds_from_config(toml).unwrap() {
+        #[cfg(feature = "with-gdal")]
+        Datasource::Gdal(ds) => assert_eq!(ds.path, "places.fgb"),
+        #[cfg(not(feature = "with-gdal"))]
+        Datasource::Fgb(ds) => assert_eq!(ds.path, "places.fgb"),
+        _ => panic!(),
+    }
+}
+
+#[test]
+fn test_tiles_datasource_from_config() {
+    let toml = r#"
+        #[[datasource]]
+        mbtiles = "osm.mbtiles"
+        "#;
+    match ds_from_config(toml).unwrap() {
+        Datasource::Tiles(ds) => assert_eq!(ds.maxzoom, 14),
+        _ => panic!(),
+    }
+
+    let toml = r#"
+        #[[datasource]]
+        tiles = "http://localhost:6767/osm/{z}/{x}/{y}.pbf"
+        maxzoom = 12
+        "#;
+    match ds_from_config(toml).unwrap() {
+        Datasource::Tiles(ds) => assert_eq!(ds.maxzoom, 12),
+        _ => panic!(),
+    }
+}
+
+#[test]
+fn test_csv_datasource_from_config() {
+    let toml = r#"
+        #[[datasource]]
+        csv = "places.csv"
+        delimiter = ";"
+        x_field = "E"
+        y_field = "N"
+        srid = 3857
+        types = { population = "integer" }
+        "#;
+    match ds_from_config(toml).unwrap() {
+        Datasource::Csv(ds) => {
+            assert_eq!(ds.options.delimiter, ';');
+            assert_eq!(ds.options.srid, 3857);
+        }
+        _ => panic!(),
+    }
+
+    let toml = r#"
+        #[[datasource]]
+        csv = "places.csv"
+        types = { population = "long" }
+        "#;
+    assert_eq!(
+        ds_from_config(toml).err(),
+        Some("Unknown column type 'long'".to_string())
+    );
+}
+
 #[cfg(feature = "with-gdal")]
 mod gdal_tests {
 
@@ -265,7 +536,7 @@ mod gdal_tests {
         } else {
             assert!(dss.default().is_some());
         }
-        dss.default().unwrap().connected();
+        dss.default().unwrap().connected().unwrap();
     }
 
 }
@@ -4,6 +4,7 @@
 //
 
 extern crate clap;
+extern crate crossbeam_utils;
 extern crate elementtree;
 #[macro_use]
 extern crate log;
@@ -32,4 +33,7 @@ pub mod mvt_service;
 #[cfg(test)]
 mod mvt_service_test;
 mod qgs_reader;
+pub mod semaphore;
+#[cfg(test)]
+mod semaphore_test;
 pub use qgs_reader::read_qgs;
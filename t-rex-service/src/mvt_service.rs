@@ -4,20 +4,29 @@
 //
 
 use cache::{Cache, Tilecache};
+use core::geojson::{feature_to_geojson, geojson_srid_supported};
 use core::grid::{Extent, ExtentInt, Grid};
-use core::layer::Layer;
+use core::layer::{Layer, ParamValue};
 use core::ApplicationCfg;
 use core::Config;
+use crossbeam_utils::thread;
 use datasource::DatasourceInput;
 use datasource_type::Datasource;
 use datasource_type::Datasources;
+use mvt::overzoom::{overzoom_layer, ChildTile, MAX_ZOOM_DIFF};
 use mvt::tile::Tile;
 use mvt::vector_tile;
 use pbr::ProgressBar;
 use percent_encoding::percent_decode;
+use serde::de::Error as SerdeError;
+use semaphore::Semaphore;
 use serde_json;
 use service::tileset::{Tileset, WORLD_EXTENT};
+use std::borrow::Cow;
+use std::collections::{BTreeMap, BTreeSet, HashMap};
+use std::fmt;
 use std::io::Stdout;
+use std::sync::Arc;
 
 /// Mapbox Vector Tile Service
 pub struct MvtService {
@@ -25,22 +34,103 @@ pub struct MvtService {
     pub grid: Grid,
     pub tilesets: Vec<Tileset>,
     pub cache: Tilecache,
+    /// Limit of concurrent datasource queries, shared by all requests
+    pub query_limit: Arc<Semaphore>,
 }
 
+/// Separator of tileset names in composite tileset requests
+const COMPOSITE_SEPARATOR: char = ',';
+
 type JsonResult = Result<serde_json::Value, serde_json::error::Error>;
 
+/// Error of a tile request
+#[derive(PartialEq, Debug)]
+pub enum TileError {
+    /// Invalid tileset name or request parameters
+    Request(String),
+    /// Datasource or configuration error
+    Server(String),
+}
+
+impl From<String> for TileError {
+    fn from(e: String) -> TileError {
+        TileError::Server(e)
+    }
+}
+
+impl fmt::Display for TileError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            &TileError::Request(ref e) => write!(f, "{}", e),
+            &TileError::Server(ref e) => write!(f, "{}", e),
+        }
+    }
+}
+
+/// Maximal length of a parameter value in cache paths
+const MAX_CACHE_VALUE_LEN: usize = 64;
+
+/// 64 bit FNV-1a hash (stable across Rust versions, unlike `DefaultHasher`)
+fn fnv1a_hash(bytes: &[u8]) -> u64 {
+    bytes.iter().fold(0xcbf29ce484222325, |hash, b| {
+        (hash ^ *b as u64).wrapping_mul(0x100000001b3)
+    })
+}
+
+/// Cache path segment of request parameters (e.g. "category=a%2Fb&year=2015").
+/// Values equal to the layer default are omitted, long values are replaced by their hash.
+fn params_cache_key(layers: &[&Layer], params: &BTreeMap<String, String>) -> String {
+    let mut entries = BTreeSet::new();
+    for layer in layers {
+        for param in &layer.params {
+            let value = match params.get(&param.name) {
+                Some(value) => value,
+                None => continue,
+            };
+            let canonical = match param.parse(value) {
+                Ok(ref parsed) if *parsed == param.default => continue,
+                Ok(ParamValue::Int(v)) => v.to_string(),
+                Ok(ParamValue::Float(v)) => v.to_string(),
+                Ok(ParamValue::Bool(v)) => v.to_string(),
+                // Text or invalid value
+                _ => value
+                    .bytes()
+                    .map(|b| {
+                        if (b as char).is_ascii_alphanumeric() || b"-_.".contains(&b) {
+                            (b as char).to_string()
+                        } else {
+                            format!("%{:02X}", b)
+                        }
+                    })
+                    .collect(),
+            };
+            let canonical = if canonical.len() > MAX_CACHE_VALUE_LEN {
+                format!("~{:016x}", fnv1a_hash(canonical.as_bytes()))
+            } else {
+                canonical
+            };
+            entries.insert(format!("{}={}", param.name, canonical));
+        }
+    }
+    entries.into_iter().collect::<Vec<_>>().join("&")
+}
+
 impl MvtService {
     /// Connect all datasources
     // Needed before calling methods on PostGIS datasources like prepare_feature_queries or get_mbtiles_metadata
     // TODO: connect automatically when needed
-    pub fn connect(&mut self) {
+    pub fn connect(&mut self) -> Result<(), String> {
         let mut datasources = Datasources::new();
         datasources.default = self.datasources.default.clone();
         for (name, ds) in &self.datasources.datasources {
-            datasources.add(&name, ds.connected());
+            let dsconn = ds
+                .connected()
+                .map_err(|e| format!("Datasource '{}': {}", name, e))?;
+            datasources.add(&name, dsconn);
         }
         datasources.setup();
         self.datasources = datasources;
+        Ok(())
     }
     fn ds(&self, layer: &Layer) -> Option<&Datasource> {
         self.datasources.datasource(&layer.datasource)
@@ -50,12 +140,44 @@ impl MvtService {
         let dec_name = percent_decode(name.as_bytes()).decode_utf8().unwrap();
         self.tilesets.iter().find(|t| t.name == dec_name)
     }
+    /// Tilesets of a single or composite tileset name ("base,overlay")
+    fn get_tilesets(&self, name: &str) -> Vec<&Tileset> {
+        let dec_name = percent_decode(name.as_bytes()).decode_utf8().unwrap();
+        dec_name
+            .split(COMPOSITE_SEPARATOR)
+            .filter_map(|part| self.get_tileset(part))
+            .collect()
+    }
+    /// Tilesets of a single or composite tileset name with equal zoom offsets
+    fn get_composite_tilesets(&self, name: &str) -> Result<Vec<&Tileset>, String> {
+        let dec_name = percent_decode(name.as_bytes())
+            .decode_utf8()
+            .map_err(|e| format!("Invalid tileset name: {}", e))?;
+        let mut tilesets = Vec::new();
+        for part in dec_name.split(COMPOSITE_SEPARATOR) {
+            match self.get_tileset(part) {
+                Some(tileset) => tilesets.push(tileset),
+                None => return Err(format!("Unknown tileset `{}`", part)),
+            }
+        }
+        let offset = self.tileset_zoom_offset(tilesets[0]);
+        if tilesets
+            .iter()
+            .any(|tileset| self.tileset_zoom_offset(tileset) != offset)
+        {
+            return Err(format!(
+                "Tilesets of composite tileset `{}` have different tile sizes",
+                dec_name
+            ));
+        }
+        Ok(tilesets)
+    }
     /// Get layers (as reference) of given tileset
     fn get_tileset_layers(&self, name: &str) -> Vec<&Layer> {
-        match self.get_tileset(name) {
-            Some(set) => set.layers.iter().map(|l| l).collect(),
-            None => Vec::new(),
-        }
+        self.get_tilesets(name)
+            .iter()
+            .flat_map(|set| set.layers.iter())
+            .collect()
     }
     /// Service metadata for backend web application
     pub fn get_mvt_metadata(&self) -> JsonResult {
@@ -110,15 +232,29 @@ impl MvtService {
         serde_json::to_value(mvt_info)
     }
     fn get_tilejson_metadata(&self, tileset: &str) -> JsonResult {
-        let ts = self.get_tileset(tileset).unwrap();
-        let ext = ts.get_extent();
+        let tilesets = self.get_composite_tilesets(tileset)
+            .map_err(serde_json::Error::custom)?;
+        let ts = tilesets[0];
+        let mut ext = ts.get_extent().clone();
+        for set in &tilesets[1..] {
+            let set_ext = set.get_extent();
+            ext.minx = ext.minx.min(set_ext.minx);
+            ext.miny = ext.miny.min(set_ext.miny);
+            ext.maxx = ext.maxx.max(set_ext.maxx);
+            ext.maxy = ext.maxy.max(set_ext.maxy);
+        }
         let center = ts.get_center();
         let zoom = ts.get_start_zoom();
+        let attribution: Vec<String> = tilesets
+            .iter()
+            .map(|set| set.attribution())
+            .filter(|attr| attr != "")
+            .collect();
         Ok(json!({
             "id": tileset,
             "name": tileset,
             "description": tileset,
-            "attribution": ts.attribution(),
+            "attribution": attribution.join(", "),
             "format": "pbf",
             "version": "2.0.0",
             "scheme": "xyz",
@@ -126,8 +262,8 @@ impl MvtService {
                        ext.miny,
                        ext.maxx,
                        ext.maxy],
-            "minzoom": ts.minzoom(),
-            "maxzoom": ts.maxzoom(),
+            "minzoom": tilesets.iter().map(|set| set.minzoom()).min(),
+            "maxzoom": tilesets.iter().map(|set| set.maxzoom()).max(),
             "center": [center.0, center.1, zoom],
             "basename": tileset
         }))
@@ -167,7 +303,7 @@ impl MvtService {
     /// TileJSON MVT vector layer extension (https://github.com/mapbox/tilejson-spec/issues/14)
     fn get_tilejson_vector_layers(&self, tileset: &str) -> JsonResult {
         let layers = self.get_tileset_layers(tileset);
-        let vector_layers: Vec<serde_json::Value> = layers
+        let mut vector_layers: Vec<serde_json::Value> = layers
             .iter()
             .map(|layer| {
                 let meta = layer.metadata();
@@ -190,6 +326,14 @@ impl MvtService {
                 layer_json
             })
             .collect();
+        // Derived label point layers
+        for (i, layer) in layers.iter().enumerate() {
+            if let Some(name) = layer.label_point.as_ref().and_then(|l| l.layer.as_ref()) {
+                let mut layer_json = vector_layers[i].clone();
+                layer_json["id"] = json!(name);
+                vector_layers.push(layer_json);
+            }
+        }
         Ok(json!(vector_layers))
     }
     /// TileJSON metadata (https://github.com/mapbox/tilejson-spec)
@@ -238,6 +382,12 @@ impl MvtService {
                 }
             }
         });
+        // Parts of composite tilesets have the same zoom offset
+        let tilesets = self.get_composite_tilesets(tileset)
+            .map_err(serde_json::Error::custom)?;
+        if let Some(tile_size) = tilesets[0].tile_size {
+            stylejson["sources"][tileset]["tileSize"] = json!(tile_size);
+        }
         let background_layer = json!({
           "id": "background_",
           "type": "background",
@@ -321,27 +471,337 @@ impl MvtService {
             }
         }
     }
+    /// Request parameters of tileset layers contained in query string
+    pub fn tile_params(
+        &self,
+        tileset: &str,
+        query: &HashMap<String, String>,
+    ) -> Result<BTreeMap<String, String>, String> {
+        if tileset.contains(COMPOSITE_SEPARATOR) {
+            self.get_composite_tilesets(tileset)?;
+        }
+        let mut params = BTreeMap::new();
+        for layer in self.get_tileset_layers(tileset) {
+            for param in &layer.params {
+                if let Some(value) = query.get(&param.name) {
+                    param.parse(value)?;
+                    params.insert(param.name.clone(), value.clone());
+                }
+            }
+        }
+        Ok(params)
+    }
+    /// Add features of layer to tile
+    fn add_layer_features(
+        &self,
+        tile: &mut Tile,
+        layer: &Layer,
+        extent: &Extent,
+        zoom: u8,
+        grid: &Grid,
+        params: &BTreeMap<String, String>,
+    ) -> Result<(), TileError> {
+        let param_values = layer.param_values(params).map_err(TileError::Request)?;
+        let _permit = self.query_limit.acquire();
+        if layer.st_asmvt {
+            // Layer encoded by datasource
+            if let Some(data) = self.ds(layer).unwrap().retrieve_mvt_layer(
+                layer,
+                extent,
+                zoom,
+                grid,
+                &param_values,
+            )? {
+                match Tile::read_bytes(&data) {
+                    Ok(mut mvt_tile) => {
+                        for mvt_layer in mvt_tile.take_layers().into_iter() {
+                            tile.mvt_tile.mut_layers().push(mvt_layer);
+                        }
+                    }
+                    Err(e) => error!("Layer '{}': invalid MVT data - {}", layer.name, e),
+                }
+                return Ok(());
+            }
+        }
+        let mut mvt_layer = tile.new_layer(&layer.zoom_layer(zoom));
+        let pixel_size = layer.tile_size(zoom) as f64 / grid.tile_width() as f64;
+        if let Some(cluster) = layer.cluster(zoom) {
+            mvt_layer.set_cluster(cluster, pixel_size);
+        }
+        if let Some(thin) = layer.thin(zoom) {
+            mvt_layer.set_thin(thin, pixel_size);
+        }
+        mvt_layer.set_min_size(layer.min_area(zoom), layer.min_length(zoom), pixel_size);
+        if let Some(query) = layer.attribute_query(zoom) {
+            mvt_layer.set_attribute_query(query);
+        }
+        self.ds(layer).unwrap().retrieve_features_with_params(
+            layer,
+            extent,
+            zoom,
+            grid,
+            &param_values,
+            |feat| {
+                tile.add_feature(&mut mvt_layer, feat);
+            },
+        )?;
+        tile.add_layer(mvt_layer);
+        Ok(())
+    }
+    /// Zoom level offset of tilesets with larger tiles than the grid (1 for 512 pixel tiles)
+    fn zoom_offset(&self, tileset: &str) -> u8 {
+        // Parts of composite tilesets have the same zoom offset
+        self.get_tilesets(tileset)
+            .first()
+            .map_or(0, |ts| self.tileset_zoom_offset(ts))
+    }
+    fn tileset_zoom_offset(&self, tileset: &Tileset) -> u8 {
+        let width = self.grid.tile_width() as u32;
+        match tileset.tile_size {
+            Some(size) if size > width => (size / width).trailing_zeros() as u8,
+            _ => 0,
+        }
+    }
+    /// Zoom level of layer data for requested zoom level
+    fn source_zoom(&self, tileset: &str, layer: &Layer, zoom: u8) -> Option<u8> {
+        // Layer zoom levels refer to grid zoom levels
+        let offset = self.zoom_offset(tileset);
+        let mut source_zoom = zoom;
+        if let Some(maxzoom) = self.get_tileset(tileset).and_then(|ts| ts.maxzoom) {
+            source_zoom = source_zoom.min(maxzoom);
+        }
+        if layer.overzoom {
+            source_zoom = source_zoom.min(layer.maxzoom().saturating_sub(offset));
+        }
+        let grid_zoom = source_zoom as u16 + offset as u16;
+        if grid_zoom >= layer.minzoom() as u16 && grid_zoom <= layer.maxzoom() as u16 {
+            Some(source_zoom)
+        } else {
+            None
+        }
+    }
+    /// Grid with tile size of tileset and device pixel ratio.
+    /// Tiles at zoom level z of tilesets with zoom offset are created at grid zoom level
+    /// z+offset, which is used for all zoom level dependent settings and query variables.
+    fn tile_grid(&self, tileset: &str, pixel_ratio: u16) -> Cow<Grid> {
+        let offset = self.zoom_offset(tileset);
+        if pixel_ratio > 1 || offset > 0 {
+            Cow::Owned(self.grid.scaled(pixel_ratio).with_zoom_offset(offset))
+        } else {
+            Cow::Borrowed(&self.grid)
+        }
+    }
+    /// Grid zoom level of tile at `zoom` (None: beyond grid levels)
+    fn grid_zoom(&self, tileset: &str, zoom: u8) -> Option<u8> {
+        let grid_zoom = zoom as u16 + self.zoom_offset(tileset) as u16;
+        if grid_zoom < self.grid.nlevels() as u16 {
+            Some(grid_zoom as u8)
+        } else {
+            None
+        }
+    }
+    /// Encoded layers of a single tileset layer at x, y, z in TMS adressing scheme
+    fn layer_tile(
+        &self,
+        layer: &Layer,
+        xtile: u32,
+        ytile: u32,
+        zoom: u8,
+        grid: &Grid,
+        params: &BTreeMap<String, String>,
+    ) -> Result<Vec<vector_tile::Tile_Layer>, TileError> {
+        let extent = grid.tile_extent(xtile, ytile, zoom);
+        let mut tile = Tile::new(&extent, true);
+        self.add_layer_features(&mut tile, layer, &extent, zoom, grid, params)?;
+        Ok(tile.mvt_tile.take_layers().into_vec())
+    }
+    /// Layers of a tileset layer clipped and scaled from the layers of its parent tile
+    /// at grid zoom level `source_zoom`
+    fn overzoom_layer_tile(
+        &self,
+        layer: &Layer,
+        xtile: u32,
+        ytile: u32,
+        dz: u8,
+        source_zoom: u8,
+        grid: &Grid,
+        parent_layers: &[vector_tile::Tile_Layer],
+    ) -> Result<Vec<vector_tile::Tile_Layer>, TileError> {
+        let mut names = vec![layer.name.as_str()];
+        if let Some(name) = layer.label_point.as_ref().and_then(|l| l.layer.as_ref()) {
+            names.push(name.as_str());
+        }
+        let child = ChildTile::from_tms(xtile, ytile, dz).map_err(TileError::Request)?;
+        let buffer = layer.buffer_size(source_zoom).unwrap_or(0) * layer.tile_size(source_zoom)
+            / grid.tile_width() as u32;
+        Ok(parent_layers
+            .iter()
+            .filter(|mvt_layer| names.contains(&mvt_layer.get_name()))
+            .map(|mvt_layer| overzoom_layer(mvt_layer, &child, buffer))
+            .collect())
+    }
+    /// Layers of cached or created tile at x, y, z in TMS adressing scheme
+    fn cached_tile_layers(
+        &self,
+        tileset: &str,
+        xtile: u32,
+        ytile: u32,
+        zoom: u8,
+        pixel_ratio: u16,
+        params: &BTreeMap<String, String>,
+    ) -> Result<Vec<vector_tile::Tile_Layer>, TileError> {
+        // Adressing scheme of tile requests (see tile_cached_scaled)
+        let y = if self.grid.srid == 3857 {
+            self.grid.ytile_from_xyz(ytile, zoom)
+        } else {
+            ytile
+        };
+        let tilegz = self.tile_cached_scaled(tileset, xtile, y, zoom, pixel_ratio, true, params)?;
+        match Tile::read_gz_from(&mut &tilegz[..]) {
+            Ok(mut mvt_tile) => Ok(mvt_tile.take_layers().into_vec()),
+            Err(e) => {
+                error!("Error reading tile of tileset `{}`: {}", tileset, e);
+                Ok(Vec::new())
+            }
+        }
+    }
+    /// Query layers concurrently within the datasource query limit
+    fn query_layer_tiles(
+        &self,
+        layers: &[&Layer],
+        xtile: u32,
+        ytile: u32,
+        zoom: u8,
+        grid: &Grid,
+        params: &BTreeMap<String, String>,
+    ) -> Result<Vec<Vec<vector_tile::Tile_Layer>>, TileError> {
+        let workers = layers.len().min(self.query_limit.permits());
+        if workers <= 1 {
+            return layers
+                .iter()
+                .map(|layer| self.layer_tile(layer, xtile, ytile, zoom, grid, params))
+                .collect();
+        }
+        // Each worker queries every n-th layer
+        let worker_results = thread::scope(|scope| {
+            let handles: Vec<_> = (0..workers)
+                .map(|worker| {
+                    scope.spawn(move || {
+                        layers
+                            .iter()
+                            .enumerate()
+                            .filter(|&(idx, _)| idx % workers == worker)
+                            .map(|(idx, layer)| {
+                                (idx, self.layer_tile(layer, xtile, ytile, zoom, grid, params))
+                            })
+                            .collect::<Vec<_>>()
+                    })
+                })
+                .collect();
+            handles
+                .into_iter()
+                .map(|handle| handle.join())
+                .collect::<Vec<_>>()
+        });
+        let mut layer_tiles: Vec<Vec<vector_tile::Tile_Layer>> =
+            layers.iter().map(|_| Vec::new()).collect();
+        for worker_result in worker_results {
+            let worker_result = worker_result.map_err(|_| "Layer query failed".to_string())?;
+            for (idx, layer_tile) in worker_result {
+                layer_tiles[idx] = layer_tile?;
+            }
+        }
+        Ok(layer_tiles)
+    }
     /// Create vector tile from input at x, y, z in TMS adressing scheme
-    pub fn tile(&self, tileset: &str, xtile: u32, ytile: u32, zoom: u8) -> vector_tile::Tile {
-        let extent = self.grid.tile_extent(xtile, ytile, zoom);
+    pub fn tile(
+        &self,
+        tileset: &str,
+        xtile: u32,
+        ytile: u32,
+        zoom: u8,
+    ) -> Result<vector_tile::Tile, TileError> {
+        self.tile_scaled(tileset, xtile, ytile, zoom, 1, &BTreeMap::new())
+    }
+    /// Create vector tile for high resolution displays (e.g. `pixel_ratio` 2 for @2x tiles)
+    pub fn tile_scaled(
+        &self,
+        tileset: &str,
+        xtile: u32,
+        ytile: u32,
+        zoom: u8,
+        pixel_ratio: u16,
+        params: &BTreeMap<String, String>,
+    ) -> Result<vector_tile::Tile, TileError> {
+        let grid = self.tile_grid(tileset, pixel_ratio);
+        let offset = self.zoom_offset(tileset);
+        let grid_zoom = match self.grid_zoom(tileset, zoom) {
+            Some(grid_zoom) => grid_zoom,
+            None => return Ok(vector_tile::Tile::default()),
+        };
+        let extent = grid.tile_extent(xtile, ytile, grid_zoom);
         debug!("MVT tile request {:?}", extent);
+        let layers = self.get_tileset_layers(tileset);
+        let source_zooms: Vec<Option<u8>> = layers
+            .iter()
+            .map(|layer| self.source_zoom(tileset, layer, zoom))
+            .collect();
+        let query_layers: Vec<&Layer> = layers
+            .iter()
+            .zip(source_zooms.iter())
+            .filter(|&(_, source_zoom)| *source_zoom == Some(zoom))
+            .map(|(layer, _)| *layer)
+            .collect();
+        // Query layers concurrently
+        let layer_tiles =
+            self.query_layer_tiles(&query_layers, xtile, ytile, grid_zoom, &grid, params)?;
+        // Overzoom: parent tiles are created or read from the cache once per source zoom level
+        let mut parent_tiles: BTreeMap<u8, Vec<vector_tile::Tile_Layer>> = BTreeMap::new();
+        for source_zoom in source_zooms.iter().filter_map(|z| *z).filter(|z| *z < zoom) {
+            if !parent_tiles.contains_key(&source_zoom) {
+                let dz = zoom - source_zoom;
+                if dz > MAX_ZOOM_DIFF {
+                    return Err(TileError::Request(format!(
+                        "Zoom level {} exceeds maximal overzoom level {}",
+                        zoom,
+                        source_zoom as u16 + MAX_ZOOM_DIFF as u16
+                    )));
+                }
+                debug!("Overzoom from parent tile at zoom level {}", source_zoom);
+                let parent_layers = self.cached_tile_layers(
+                    tileset,
+                    xtile >> dz,
+                    ytile >> dz,
+                    source_zoom,
+                    pixel_ratio,
+                    params,
+                )?;
+                parent_tiles.insert(source_zoom, parent_layers);
+            }
+        }
+        let mut layer_tiles = layer_tiles.into_iter();
         let mut tile = Tile::new(&extent, true);
-        for layer in self.get_tileset_layers(tileset) {
-            if zoom >= layer.minzoom() && zoom <= layer.maxzoom() {
-                let mut mvt_layer = tile.new_layer(layer);
-                self.ds(&layer).unwrap().retrieve_features(
-                    &layer,
-                    &extent,
-                    zoom,
-                    &self.grid,
-                    |feat| {
-                        tile.add_feature(&mut mvt_layer, feat);
-                    },
-                );
-                tile.add_layer(mvt_layer);
+        for (layer, source_zoom) in layers.iter().zip(source_zooms.into_iter()) {
+            let mvt_layers = match source_zoom {
+                Some(source_zoom) if source_zoom == zoom => {
+                    layer_tiles.next().unwrap_or(Vec::new())
+                }
+                Some(source_zoom) => self.overzoom_layer_tile(
+                    layer,
+                    xtile,
+                    ytile,
+                    zoom - source_zoom,
+                    source_zoom + offset,
+                    &grid,
+                    &parent_tiles[&source_zoom],
+                )?,
+                None => Vec::new(),
+            };
+            for mvt_layer in mvt_layers {
+                tile.mvt_tile.mut_layers().push(mvt_layer);
             }
         }
-        tile.mvt_tile
+        Ok(tile.mvt_tile)
     }
     /// Fetch or create vector tile from input at x, y, z
     pub fn tile_cached(
@@ -350,15 +810,50 @@ impl MvtService {
         xtile: u32,
         ytile: u32,
         zoom: u8,
-        _gzip: bool,
-    ) -> Vec<u8> {
+        gzip: bool,
+    ) -> Result<Vec<u8>, TileError> {
+        self.tile_cached_scaled(tileset, xtile, ytile, zoom, 1, gzip, &BTreeMap::new())
+    }
+    /// Fetch or create vector tile for high resolution displays at x, y, z
+    pub fn tile_cached_scaled(
+        &self,
+        tileset: &str,
+        xtile: u32,
+        ytile: u32,
+        zoom: u8,
+        pixel_ratio: u16,
+        gzip: bool,
+        params: &BTreeMap<String, String>,
+    ) -> Result<Vec<u8>, TileError> {
+        if tileset.contains(COMPOSITE_SEPARATOR) {
+            return self.composite_tile_cached(
+                tileset,
+                xtile,
+                ytile,
+                zoom,
+                pixel_ratio,
+                gzip,
+                params,
+            );
+        }
         // Reverse y for XYZ scheme (TODO: protocol instead of CRS dependent?)
         let y = if self.grid.srid == 3857 {
             self.grid.ytile_from_xyz(ytile, zoom)
         } else {
             ytile
         };
-        let path = format!("{}/{}/{}/{}.pbf", tileset, zoom, xtile, ytile);
+        // Tiles with request parameters are cached separately
+        let params_key = params_cache_key(&self.get_tileset_layers(tileset), params);
+        let basepath = if params_key.is_empty() {
+            tileset.to_string()
+        } else {
+            format!("{}/{}", tileset, params_key)
+        };
+        let path = if pixel_ratio > 1 {
+            format!("{}/{}/{}/{}@{}x.pbf", basepath, zoom, xtile, ytile, pixel_ratio)
+        } else {
+            format!("{}/{}/{}/{}.pbf", basepath, zoom, xtile, ytile)
+        };
 
         let mut tile: Option<Vec<u8>> = None;
         self.cache.read(&path, |f| {
@@ -368,17 +863,172 @@ impl MvtService {
         });
         if tile.is_some() {
             //TODO: unzip if gzip == false
-            return tile.unwrap();
+            return Ok(tile.unwrap());
         }
 
-        let mvt_tile = self.tile(tileset, xtile, y, zoom);
+        let mvt_tile = self.tile_scaled(tileset, xtile, y, zoom, pixel_ratio, params)?;
 
         let mut tilegz = Vec::new();
         Tile::write_gz_to(&mut tilegz, &mvt_tile);
         let _ = self.cache.write(&path, &tilegz);
 
         //TODO: return unzipped if gzip == false
-        tilegz
+        Ok(tilegz)
+    }
+    /// GeoJSON features of all layers at x, y, z.
+    /// Features are read with the layer query, without applying fid_mode, min_area,
+    /// min_length, thinning, clustering, merging and label points of MVT tiles.
+    fn tile_layer_features(
+        &self,
+        tileset: &str,
+        xtile: u32,
+        ytile: u32,
+        zoom: u8,
+        params: &BTreeMap<String, String>,
+    ) -> Result<Vec<(String, Vec<serde_json::Value>)>, TileError> {
+        if !geojson_srid_supported(self.grid.srid) {
+            return Err(TileError::Server(format!(
+                "GeoJSON output not supported for grid with SRID {}",
+                self.grid.srid
+            )));
+        }
+        // Reverse y for XYZ scheme (see tile_cached)
+        let y = if self.grid.srid == 3857 {
+            self.grid.ytile_from_xyz(ytile, zoom)
+        } else {
+            ytile
+        };
+        let grid = self.tile_grid(tileset, 1);
+        let offset = self.zoom_offset(tileset);
+        let grid_zoom = match self.grid_zoom(tileset, zoom) {
+            Some(grid_zoom) => grid_zoom,
+            None => return Ok(Vec::new()),
+        };
+        let extent = grid.tile_extent(xtile, y, grid_zoom);
+        debug!("GeoJSON tile request {:?}", extent);
+        let mut layers = Vec::new();
+        for layer in self.get_tileset_layers(tileset) {
+            // Overzoomed layers return the features of the source zoom level within the tile.
+            // Layers encoded with ST_AsMVT are read with their feature query.
+            let source_zoom = match self.source_zoom(tileset, layer, zoom) {
+                Some(source_zoom) => source_zoom + offset,
+                None => continue,
+            };
+            let param_values = layer.param_values(params).map_err(TileError::Request)?;
+            let query = layer.attribute_query(source_zoom);
+            let mut features = Vec::new();
+            let _permit = self.query_limit.acquire();
+            self.ds(layer).unwrap().retrieve_features_with_params(
+                layer,
+                &extent,
+                source_zoom,
+                &grid,
+                &param_values,
+                |feat| {
+                    features.push(feature_to_geojson(feat, query, grid.srid));
+                },
+            )?;
+            layers.push((layer.name.clone(), features));
+        }
+        Ok(layers)
+    }
+    /// GeoJSON FeatureCollection with features of all layers at x, y, z
+    pub fn tile_geojson(
+        &self,
+        tileset: &str,
+        xtile: u32,
+        ytile: u32,
+        zoom: u8,
+        params: &BTreeMap<String, String>,
+    ) -> Result<serde_json::Value, TileError> {
+        let mut features = Vec::new();
+        for (name, layer_features) in self.tile_layer_features(tileset, xtile, ytile, zoom, params)?
+        {
+            for mut feature in layer_features {
+                feature["layer"] = json!(name);
+                features.push(feature);
+            }
+        }
+        Ok(json!({
+            "type": "FeatureCollection",
+            "features": features
+        }))
+    }
+    /// GeoJSON FeatureCollection per layer at x, y, z
+    pub fn tile_geojson_layers(
+        &self,
+        tileset: &str,
+        xtile: u32,
+        ytile: u32,
+        zoom: u8,
+        params: &BTreeMap<String, String>,
+    ) -> Result<serde_json::Value, TileError> {
+        let mut layers = serde_json::Map::new();
+        for (name, features) in self.tile_layer_features(tileset, xtile, ytile, zoom, params)? {
+            layers.insert(
+                name.clone(),
+                json!({
+                    "type": "FeatureCollection",
+                    "name": name,
+                    "features": features
+                }),
+            );
+        }
+        Ok(json!(layers))
+    }
+    /// Combine layers of cached tiles of multiple tilesets ("base,overlay")
+    fn composite_tile_cached(
+        &self,
+        tileset: &str,
+        xtile: u32,
+        ytile: u32,
+        zoom: u8,
+        pixel_ratio: u16,
+        gzip: bool,
+        params: &BTreeMap<String, String>,
+    ) -> Result<Vec<u8>, TileError> {
+        // Parts with different zoom offsets would cover different extents
+        self.get_composite_tilesets(tileset)
+            .map_err(TileError::Request)?;
+        let mut mvt_tile = vector_tile::Tile::default();
+        for name in tileset.split(COMPOSITE_SEPARATOR) {
+            // Cache path only depends on parameters of the tileset's layers
+            let layers = self.get_tileset_layers(name);
+            let part_params: BTreeMap<String, String> = params
+                .iter()
+                .filter(|&(param, _)| {
+                    layers
+                        .iter()
+                        .any(|layer| layer.params.iter().any(|p| &p.name == param))
+                })
+                .map(|(param, value)| (param.clone(), value.clone()))
+                .collect();
+            let tilegz =
+                self.tile_cached_scaled(name, xtile, ytile, zoom, pixel_ratio, gzip, &part_params)?;
+            match Tile::read_gz_from(&mut &tilegz[..]) {
+                Ok(mut part) => {
+                    for mvt_layer in part.take_layers().into_iter() {
+                        if mvt_tile
+                            .get_layers()
+                            .iter()
+                            .any(|l| l.get_name() == mvt_layer.get_name())
+                        {
+                            warn!(
+                                "Skipping duplicate layer `{}` of tileset `{}`",
+                                mvt_layer.get_name(),
+                                name
+                            );
+                            continue;
+                        }
+                        mvt_tile.mut_layers().push(mvt_layer);
+                    }
+                }
+                Err(e) => error!("Error reading tile of tileset `{}`: {}", name, e),
+            }
+        }
+        let mut tilegz = Vec::new();
+        Tile::write_gz_to(&mut tilegz, &mvt_tile);
+        Ok(tilegz)
     }
     fn progress_bar(&self, msg: &str, limits: &ExtentInt) -> ProgressBar<Stdout> {
         let tiles =
@@ -471,11 +1121,14 @@ impl MvtService {
 
                         if overwrite || !self.cache.exists(&path) {
                             // Entry doesn't exist, or we're ignoring it, so generate it
-                            let mvt_tile =
-                                self.tile(&tileset.name, xtile as u32, ytile as u32, zoom);
-                            let mut tilegz = Vec::new();
-                            Tile::write_gz_to(&mut tilegz, &mvt_tile);
-                            let _ = self.cache.write(&path, &tilegz);
+                            match self.tile(&tileset.name, xtile as u32, ytile as u32, zoom) {
+                                Ok(mvt_tile) => {
+                                    let mut tilegz = Vec::new();
+                                    Tile::write_gz_to(&mut tilegz, &mvt_tile);
+                                    let _ = self.cache.write(&path, &tilegz);
+                                }
+                                Err(e) => error!("Tile {}: {}", path, e),
+                            }
                         }
 
                         if progress {
@@ -546,17 +1199,32 @@ impl<'a> Config<'a, ApplicationCfg> for MvtService {
     fn from_config(config: &ApplicationCfg) -> Result<Self, String> {
         let datasources = Datasources::from_config(config)?;
         let grid = Grid::from_config(&config.grid)?;
-        let tilesets = config
+        let tilesets: Vec<Tileset> = config
             .tilesets
             .iter()
             .map(|ts_cfg| Tileset::from_config(ts_cfg).unwrap())
             .collect();
+        let width = grid.tile_width() as u32;
+        for tileset in &tilesets {
+            match tileset.tile_size {
+                Some(size) if size % width != 0 || !(size / width).is_power_of_two() => {
+                    return Err(format!(
+                        "Tileset '{}': tile_size {} is no power of two multiple of {}",
+                        tileset.name, size, width
+                    ));
+                }
+                _ => {}
+            }
+        }
         let cache = Tilecache::from_config(&config)?;
         Ok(MvtService {
             datasources: datasources,
             grid: grid,
             tilesets: tilesets,
             cache: cache,
+            query_limit: Arc::new(Semaphore::new(
+                config.webserver.threads.unwrap_or(4) as usize,
+            )),
         })
     }
     fn gen_config() -> String {
@@ -161,6 +161,7 @@ pub fn read_qgs(fname: &str) -> (Datasources, Tileset) {
         extent: None,
         center: None,
         start_zoom: None,
+        tile_size: None,
         layers: Vec::new(),
     };
     for qgslayer in projectlayers.find_all("maplayer") {
@@ -0,0 +1,55 @@
+//
+// Copyright (c) Pirmin Kalberer. All rights reserved.
+// Licensed under the MIT License. See LICENSE file in the project root for full license information.
+//
+
+use std::sync::{Condvar, Mutex};
+
+/// Counting semaphore limiting concurrent datasource queries
+pub struct Semaphore {
+    permits: usize,
+    available: Mutex<usize>,
+    released: Condvar,
+}
+
+/// Permit of a semaphore, released when dropped
+pub struct SemaphoreGuard<'a> {
+    semaphore: &'a Semaphore,
+}
+
+impl Semaphore {
+    pub fn new(permits: usize) -> Semaphore {
+        let permits = permits.max(1);
+        Semaphore {
+            permits: permits,
+            available: Mutex::new(permits),
+            released: Condvar::new(),
+        }
+    }
+    /// Total number of permits
+    pub fn permits(&self) -> usize {
+        self.permits
+    }
+    /// Wait for a free permit
+    pub fn acquire(&self) -> SemaphoreGuard {
+        let mut available = self.available.lock().unwrap_or_else(|e| e.into_inner());
+        while *available == 0 {
+            available = self.released
+                .wait(available)
+                .unwrap_or_else(|e| e.into_inner());
+        }
+        *available -= 1;
+        SemaphoreGuard { semaphore: self }
+    }
+}
+
+impl<'a> Drop for SemaphoreGuard<'a> {
+    fn drop(&mut self) {
+        let mut available = self.semaphore
+            .available
+            .lock()
+            .unwrap_or_else(|e| e.into_inner());
+        *available += 1;
+        self.semaphore.released.notify_one();
+    }
+}
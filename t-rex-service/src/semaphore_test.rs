@@ -0,0 +1,39 @@
+//
+// Copyright (c) Pirmin Kalberer. All rights reserved.
+// Licensed under the MIT License. See LICENSE file in the project root for full license information.
+//
+
+use semaphore::Semaphore;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::{Arc, Mutex};
+use std::thread;
+use std::time::Duration;
+
+#[test]
+fn test_concurrency_limit() {
+    let semaphore = Arc::new(Semaphore::new(2));
+    let running = Arc::new(AtomicUsize::new(0));
+    let max_running = Arc::new(Mutex::new(0));
+    let handles: Vec<_> = (0..8)
+        .map(|_| {
+            let semaphore = semaphore.clone();
+            let running = running.clone();
+            let max_running = max_running.clone();
+            thread::spawn(move || {
+                let _permit = semaphore.acquire();
+                let count = running.fetch_add(1, Ordering::SeqCst) + 1;
+                {
+                    let mut max = max_running.lock().unwrap();
+                    *max = (*max).max(count);
+                }
+                thread::sleep(Duration::from_millis(10));
+                running.fetch_sub(1, Ordering::SeqCst);
+            })
+        })
+        .collect();
+    for handle in handles {
+        handle.join().unwrap();
+    }
+    assert_eq!(*max_running.lock().unwrap(), 2);
+    assert_eq!(Semaphore::new(0).permits(), 1);
+}
@@ -9,6 +9,7 @@ extern crate log;
 extern crate actix;
 extern crate actix_web;
 extern crate futures;
+extern crate futures_cpupool;
 extern crate open;
 #[macro_use]
 extern crate lazy_static;
@@ -17,6 +18,6 @@ extern crate t_rex_core;
 extern crate t_rex_service;
 
 use t_rex_core::{cache, core, datasource, service};
-use t_rex_service::{datasource_type, mvt_service, read_qgs};
+use t_rex_service::{datasource_type, mvt_service, read_qgs, semaphore};
 
 pub mod server;
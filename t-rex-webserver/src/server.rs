@@ -11,8 +11,9 @@ use core::layer::Layer;
 use core::{parse_config, read_config, Config};
 use datasource::DatasourceInput;
 use datasource_type::Datasources;
-use mvt_service::MvtService;
+use mvt_service::{MvtService, TileError};
 use read_qgs;
+use semaphore::Semaphore;
 use service::tileset::Tileset;
 
 use actix;
@@ -21,11 +22,14 @@ use actix_web::{http::header, http::ContentEncoding, http::Method, middleware,
                 Path};
 use clap::ArgMatches;
 use futures::future::{result, FutureResult};
+use futures::Future;
+use futures_cpupool::{CpuFuture, CpuPool};
 use open;
-use std::collections::HashMap;
+use std::collections::{BTreeMap, HashMap};
 use std::process;
 use std::str;
 use std::str::FromStr;
+use std::sync::Arc;
 
 struct StaticFiles {
     files: HashMap<&'static str, (&'static [u8], &'static str)>,
@@ -185,7 +189,10 @@ pub fn service_from_args(config: &ApplicationCfg, args: &ArgMatches) -> MvtServi
             println!("Error reading configuration - {} ", err);
             process::exit(1)
         });
-        svc.connect();
+        svc.connect().unwrap_or_else(|err| {
+            println!("Error connecting datasource - {} ", err);
+            process::exit(1)
+        });
         svc
     } else {
         let cache = match args.value_of("cache") {
@@ -215,7 +222,10 @@ pub fn service_from_args(config: &ApplicationCfg, args: &ArgMatches) -> MvtServi
             }
             let detect_geometry_types = true; //TODO: add option (maybe slow for many geometries)
             for (_name, ds) in &datasources.datasources {
-                let dsconn = ds.connected();
+                let dsconn = ds.connected().unwrap_or_else(|err| {
+                    println!("Error connecting datasource - {} ", err);
+                    process::exit(1)
+                });
                 let mut layers = dsconn.detect_layers(detect_geometry_types);
                 while let Some(mut l) = layers.pop() {
                     let extent = dsconn.layer_extent(&l);
@@ -228,6 +238,7 @@ pub fn service_from_args(config: &ApplicationCfg, args: &ArgMatches) -> MvtServi
                         extent: extent,
                         center: None,
                         start_zoom: None,
+                        tile_size: None,
                         layers: vec![l],
                     };
                     tilesets.push(tileset);
@@ -240,16 +251,38 @@ pub fn service_from_args(config: &ApplicationCfg, args: &ArgMatches) -> MvtServi
             grid: grid,
             tilesets: tilesets,
             cache: cache,
+            query_limit: Arc::new(Semaphore::new(
+                config.webserver.threads.unwrap_or(4) as usize,
+            )),
         };
-        svc.connect(); //TODO: ugly - we connect twice
+        svc.connect().unwrap_or_else(|err| {
+            println!("Error connecting datasource - {} ", err);
+            process::exit(1)
+        }); //TODO: ugly - we connect twice
         svc
     }
 }
 
 /// Application state
 struct AppState {
-    service: MvtService,
+    service: Arc<MvtService>,
     config: ApplicationCfg,
+    /// Thread pool for datasource queries
+    executor: CpuPool,
+}
+
+type FutureResponse = Box<Future<Item = HttpResponse, Error = Error>>;
+
+/// Run service call on thread pool without blocking the event loop
+fn spawn_service<F, R>(req: &HttpRequest<AppState>, f: F) -> CpuFuture<R, Error>
+where
+    F: FnOnce(&MvtService) -> R + Send + 'static,
+    R: Send + 'static,
+{
+    let service = req.state().service.clone();
+    req.state()
+        .executor
+        .spawn_fn(move || -> Result<R, Error> { Ok(f(&service)) })
 }
 
 fn mvt_metadata(req: HttpRequest<AppState>) -> FutureResult<HttpResponse, Error> {
@@ -297,63 +330,144 @@ fn req_baseurl(req: &HttpRequest<AppState>) -> String {
     format!("{}://{}", conninfo.scheme(), conninfo.host())
 }
 
-fn tileset_tilejson(
-    (req, tileset): (HttpRequest<AppState>, Path<String>),
-) -> FutureResult<HttpResponse, Error> {
-    let json = req.state()
-        .service
-        .get_tilejson(&req_baseurl(&req), &tileset)
-        .unwrap();
-    result(Ok(HttpResponse::Ok().json(json)))
+fn tileset_tilejson((req, tileset): (HttpRequest<AppState>, Path<String>)) -> FutureResponse {
+    let baseurl = req_baseurl(&req);
+    let tileset = tileset.into_inner();
+    let json = spawn_service(&req, move |service| service.get_tilejson(&baseurl, &tileset));
+    Box::new(json.map(|json| match json {
+        Ok(json) => HttpResponse::Ok().json(json),
+        Err(e) => HttpResponse::BadRequest().body(e.to_string()),
+    }))
 }
 
-fn tileset_style_json(
-    (req, tileset): (HttpRequest<AppState>, Path<String>),
-) -> FutureResult<HttpResponse, Error> {
-    let json = req.state()
-        .service
-        .get_stylejson(&req_baseurl(&req), &tileset)
-        .unwrap();
-    result(Ok(HttpResponse::Ok().json(json)))
+fn tileset_style_json((req, tileset): (HttpRequest<AppState>, Path<String>)) -> FutureResponse {
+    let baseurl = req_baseurl(&req);
+    let tileset = tileset.into_inner();
+    let json = spawn_service(&req, move |service| service.get_stylejson(&baseurl, &tileset));
+    Box::new(json.map(|json| match json {
+        Ok(json) => HttpResponse::Ok().json(json),
+        Err(e) => HttpResponse::BadRequest().body(e.to_string()),
+    }))
 }
 
 fn tileset_metadata_json(
     (req, tileset): (HttpRequest<AppState>, Path<String>),
-) -> FutureResult<HttpResponse, Error> {
-    let json = req.state().service.get_mbtiles_metadata(&tileset).unwrap();
-    result(Ok(HttpResponse::Ok().json(json)))
+) -> FutureResponse {
+    let tileset = tileset.into_inner();
+    let json = spawn_service(&req, move |service| service.get_mbtiles_metadata(&tileset));
+    Box::new(json.map(|json| match json {
+        Ok(json) => HttpResponse::Ok().json(json),
+        Err(e) => HttpResponse::BadRequest().body(e.to_string()),
+    }))
+}
+
+/// Request parameters of tileset layers (Err: Bad Request response)
+fn req_tile_params(
+    req: &HttpRequest<AppState>,
+    tileset: &str,
+) -> Result<BTreeMap<String, String>, HttpResponse> {
+    req.state()
+        .service
+        .tile_params(tileset, &req.query())
+        .map_err(|e| HttpResponse::BadRequest().body(e))
+}
+
+/// Bad Request response for invalid requests, Internal Server Error otherwise
+fn tile_error_response(e: TileError) -> HttpResponse {
+    match e {
+        TileError::Request(e) => HttpResponse::BadRequest().body(e),
+        TileError::Server(e) => {
+            error!("{}", e);
+            HttpResponse::InternalServerError().body(e)
+        }
+    }
 }
 
 fn tile_pbf(
     (req, params): (HttpRequest<AppState>, Path<(String, u8, u32, u32)>),
-) -> FutureResult<HttpResponse, Error> {
-    let tileset = &params.0;
-    let z = params.1;
-    let x = params.2;
-    let y = params.3;
+) -> FutureResponse {
+    tile_response(req, params, 1)
+}
+
+/// High resolution tile
+fn tile_pbf_2x(
+    (req, params): (HttpRequest<AppState>, Path<(String, u8, u32, u32)>),
+) -> FutureResponse {
+    tile_response(req, params, 2)
+}
+
+fn tile_response(
+    req: HttpRequest<AppState>,
+    params: Path<(String, u8, u32, u32)>,
+    pixel_ratio: u16,
+) -> FutureResponse {
+    let (tileset, z, x, y) = params.into_inner();
+    let tile_params = match req_tile_params(&req, &tileset) {
+        Ok(tile_params) => tile_params,
+        Err(resp) => return Box::new(result(Ok(resp))),
+    };
     let gzip = true;
     /* TODO:
     let gzip = accept_encoding.is_some() && accept_encoding.unwrap().iter().any(
                |ref qit| qit.item == Encoding::Gzip );
                */
-    let tile = req.state().service.tile_cached(tileset, x, y, z, gzip);
+    let tile = spawn_service(&req, move |service| {
+        service.tile_cached_scaled(&tileset, x, y, z, pixel_ratio, gzip, &tile_params)
+    });
     let cache_max_age = req.state()
         .config
         .webserver
         .cache_control_max_age
         .unwrap_or(300);
 
-    let resp = HttpResponse::Ok()
-        .content_type("application/x-protobuf")
-        .if_true(gzip, |r| {
-            // data is already gzip compressed
-            r.content_encoding(ContentEncoding::Identity)
-                .header(header::CONTENT_ENCODING, "gzip");
-        })
-        .header(header::CACHE_CONTROL, format!("max-age={}", cache_max_age))
-        .body(tile); // TODO: chunked response
-
-    result(Ok(resp))
+    Box::new(tile.map(move |tile| match tile {
+        Ok(tile) => HttpResponse::Ok()
+            .content_type("application/x-protobuf")
+            .if_true(gzip, |r| {
+                // data is already gzip compressed
+                r.content_encoding(ContentEncoding::Identity)
+                    .header(header::CONTENT_ENCODING, "gzip");
+            })
+            .header(header::CACHE_CONTROL, format!("max-age={}", cache_max_age))
+            .body(tile), // TODO: chunked response
+        Err(e) => tile_error_response(e),
+    }))
+}
+
+fn tile_geojson(
+    (req, params): (HttpRequest<AppState>, Path<(String, u8, u32, u32)>),
+) -> FutureResponse {
+    let (tileset, z, x, y) = params.into_inner();
+    let tile_params = match req_tile_params(&req, &tileset) {
+        Ok(tile_params) => tile_params,
+        Err(resp) => return Box::new(result(Ok(resp))),
+    };
+    let json = spawn_service(&req, move |service| {
+        service.tile_geojson(&tileset, x, y, z, &tile_params)
+    });
+    Box::new(json.map(|json| match json {
+        Ok(json) => HttpResponse::Ok()
+            .content_type("application/geo+json")
+            .json(json),
+        Err(e) => tile_error_response(e),
+    }))
+}
+
+fn tile_geojson_layers(
+    (req, params): (HttpRequest<AppState>, Path<(String, u8, u32, u32)>),
+) -> FutureResponse {
+    let (tileset, z, x, y) = params.into_inner();
+    let tile_params = match req_tile_params(&req, &tileset) {
+        Ok(tile_params) => tile_params,
+        Err(resp) => return Box::new(result(Ok(resp))),
+    };
+    let json = spawn_service(&req, move |service| {
+        service.tile_geojson_layers(&tileset, x, y, z, &tile_params)
+    });
+    Box::new(json.map(|json| match json {
+        Ok(json) => HttpResponse::Ok().json(json),
+        Err(e) => tile_error_response(e),
+    }))
 }
 
 fn static_file_handler(req: HttpRequest<AppState>) -> Result<HttpResponse, Error> {
@@ -378,19 +492,23 @@ pub fn webserver(args: ArgMatches<'static>) {
     let mvt_viewer = config.service.mvt.viewer;
     let openbrowser =
         bool::from_str(args.value_of("openbrowser").unwrap_or("true")).unwrap_or(false);
-    let _threads = config.webserver.threads.unwrap_or(4) as usize; //TODO (?)
+    let threads = config.webserver.threads.unwrap_or(4) as usize;
+    let executor = CpuPool::new(threads);
+    // Datasource queries of all workers share one limit
+    let query_limit = Arc::new(Semaphore::new(threads));
 
     actix::System::run(move || {
         HttpServer::new(move || {
             let config = config_from_args(&args);
             let mut service = service_from_args(&config, &args);
+            service.query_limit = query_limit.clone();
 
             let mvt_viewer = config.service.mvt.viewer;
 
             service.prepare_feature_queries();
             service.init_cache();
 
-            App::with_state(AppState{service, config})
+            App::with_state(AppState{service: Arc::new(service), config, executor: executor.clone()})
                 .middleware(middleware::Logger::default())
                 .resource("/index.json", |r| r.method(Method::GET).a(mvt_metadata))
                 /* TODO: CORS does only set allowed_origin. actix-web bug?
@@ -412,7 +530,10 @@ pub fn webserver(args: ArgMatches<'static>) {
                 .resource("/{tileset}.style.json", |r| r.method(Method::GET).with_async(tileset_style_json))
                 .resource("/{tileset}/metadata.json", |r| r.method(Method::GET).with_async(tileset_metadata_json))
                 .resource("/{tileset}.json", |r| r.method(Method::GET).with_async(tileset_tilejson))
+                .resource("/{tileset}/{z}/{x}/{y}@2x.pbf", |r| r.method(Method::GET).with_async(tile_pbf_2x))
                 .resource("/{tileset}/{z}/{x}/{y}.pbf", |r| r.method(Method::GET).with_async(tile_pbf))
+                .resource("/{tileset}/{z}/{x}/{y}.geojson", |r| r.method(Method::GET).with_async(tile_geojson))
+                .resource("/{tileset}/{z}/{x}/{y}.json", |r| r.method(Method::GET).with_async(tile_geojson_layers))
                 /* TODO: conflicts with static_file_handler
                 .configure(|app| {
                     if path::Path::new("./public/").is_dir() {
@@ -451,6 +572,7 @@ pub fn gen_config(args: &ArgMatches) -> String {
 # Bind address. Use 0.0.0.0 to listen on all adresses.
 bind = "127.0.0.1"
 port = 6767
+# Number of threads for datasource queries
 threads = 4
 #cache_control_max_age = 43200
 "#;